@@ -0,0 +1,316 @@
+//! Converting database rows into Arrow record batches and writing them to Parquet files.
+//!
+//! Every batch starts with a non-null `id` column followed by one column per property in the
+//! same order as [`crate::export`]. Numbers are `Float64`, checkboxes `Boolean`, selects
+//! dictionary encoded strings, multi-selects, relations and files lists of strings and people a
+//! list of `{ id, name, email }` structs. Timestamps are UTC millisecond timestamps. A date
+//! property is a `Date32` column when none of the rows' values has a time, otherwise a timestamp
+//! column where dates without a time are midnight. Date properties get a second `<name>:end`
+//! column for the end of a range. Formulas, rollups and anything else are rendered as strings.
+use crate::{
+    database::{DatabaseObject, DatabaseProperty},
+    export::{cell, ExportOptions, Exporter},
+    page::{PageObject, PageProperty},
+    user::UserObject,
+    Client,
+};
+use ::arrow::{
+    array::{
+        ArrayBuilder, ArrayRef, BooleanBuilder, Date32Builder, Float64Builder, ListBuilder,
+        StringBuilder, StringDictionaryBuilder, StructBuilder, TimestampMillisecondBuilder,
+    },
+    datatypes::{DataType, Field, Fields, Int32Type, Schema, SchemaRef, TimeUnit},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use chrono::{DateTime, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use serde_json::Value;
+use std::{error::Error, io::Write, sync::Arc};
+
+/// The Arrow schema the rows of the database are converted to. Which type a date column has
+/// depends on the rows' values.
+pub fn schema(database: &DatabaseObject, rows: &[PageObject]) -> Schema {
+    schema_of(&columns(database, rows))
+}
+
+fn schema_of(columns: &[(String, Kind)]) -> Schema {
+    let mut fields = vec![Field::new("id", DataType::Utf8, false)];
+    for (name, kind) in columns {
+        fields.push(Field::new(name, kind.data_type(), true));
+        if matches!(kind, Kind::Date | Kind::Day) {
+            fields.push(Field::new(format!("{}:end", name), kind.data_type(), true));
+        }
+    }
+    Schema::new(fields)
+}
+
+/// Convert rows of the database into a single batch
+pub fn record_batch(
+    database: &DatabaseObject,
+    rows: &[PageObject],
+) -> Result<RecordBatch, ArrowError> {
+    batch(database, &columns(database, rows), rows)
+}
+
+fn batch(
+    database: &DatabaseObject,
+    columns: &[(String, Kind)],
+    rows: &[PageObject],
+) -> Result<RecordBatch, ArrowError> {
+    let exporter = Exporter::new(database, ExportOptions::default());
+
+    let mut ids = StringBuilder::new();
+    let mut builders = Vec::<Box<dyn ArrayBuilder>>::new();
+    for (_, kind) in columns {
+        builders.push(kind.builder());
+        if matches!(kind, Kind::Date | Kind::Day) {
+            builders.push(kind.builder());
+        }
+    }
+
+    for row in rows {
+        ids.append_value(&row.id);
+        let mut builders = builders.iter_mut();
+        for ((name, kind), value) in columns.iter().zip(exporter.values(row)) {
+            let builder = builders.next().expect("a builder for every column");
+            match kind {
+                Kind::Text => builder_mut::<StringBuilder>(builder)
+                    .append_option(Some(cell(&value)).filter(|_| !value.is_null())),
+                Kind::Number => {
+                    builder_mut::<Float64Builder>(builder).append_option(value.as_f64())
+                }
+                Kind::Checkbox => {
+                    builder_mut::<BooleanBuilder>(builder).append_option(value.as_bool())
+                }
+                Kind::Select => builder_mut::<StringDictionaryBuilder<Int32Type>>(builder)
+                    .append_option(value.as_str()),
+                Kind::Time => builder_mut::<TimestampMillisecondBuilder>(builder)
+                    .append_option(value.as_str().and_then(millis)),
+                Kind::Date | Kind::Day => {
+                    let (start, end) = match row.properties.get(name) {
+                        Some(PageProperty::Date {
+                            date: Some(date), ..
+                        }) => (Some(date.start), date.end),
+                        _ => (None, None),
+                    };
+                    let end_builder = builders.next().expect("an end column for every date");
+                    for (builder, time) in [(builder, start), (end_builder, end)] {
+                        if *kind == Kind::Day {
+                            builder_mut::<Date32Builder>(builder)
+                                .append_option(time.map(|time| days(time.date())));
+                        } else {
+                            builder_mut::<TimestampMillisecondBuilder>(builder)
+                                .append_option(time.map(|time| time.to_utc().timestamp_millis()));
+                        }
+                    }
+                }
+                Kind::List => {
+                    let list = builder_mut::<ListBuilder<StringBuilder>>(builder);
+                    match value {
+                        Value::Array(items) => {
+                            for item in items {
+                                list.values().append_value(cell(&item));
+                            }
+                            list.append(true);
+                        }
+                        _ => list.append(false),
+                    }
+                }
+                Kind::People => {
+                    let list = builder_mut::<ListBuilder<StructBuilder>>(builder);
+                    match row.properties.get(name) {
+                        Some(PageProperty::People { people, .. }) => {
+                            for user in people {
+                                append_user(list.values(), user);
+                            }
+                            list.append(true);
+                        }
+                        _ => list.append(false),
+                    }
+                }
+            }
+        }
+    }
+
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(ids.finish())];
+    arrays.extend(builders.iter_mut().map(|builder| builder.finish()));
+    RecordBatch::try_new(Arc::new(schema_of(columns)), arrays)
+}
+
+/// Query every row of the database and write them to a Parquet file, one row group per
+/// `ROW_GROUP_SIZE` rows. Every row is read before anything is written, since the type of date
+/// columns depends on all of them. Returns how many rows were written.
+pub async fn write_parquet<W: Write + Send>(
+    client: &Client,
+    database_id: &str,
+    writer: W,
+) -> Result<usize, Box<dyn Error>> {
+    let database = client.get_database(database_id).await?.into_database()?;
+    let rows = client
+        .query_database_all(database_id, None, None)
+        .await?
+        .into_iter()
+        .map(|result| result.into_page())
+        .collect::<Result<Vec<_>, _>>()?;
+    let columns = columns(&database, &rows);
+    let schema: SchemaRef = Arc::new(schema_of(&columns));
+    let mut writer = ArrowWriter::try_new(writer, schema, None)?;
+
+    for group in rows.chunks(ROW_GROUP_SIZE) {
+        writer.write(&batch(&database, &columns, group)?)?;
+        writer.flush()?;
+    }
+
+    writer.close()?;
+    Ok(rows.len())
+}
+
+/// As many rows as Notion returns in a page of results
+const ROW_GROUP_SIZE: usize = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Number,
+    Checkbox,
+    Select,
+    Date,
+    /// A date property none of whose values has a time
+    Day,
+    Time,
+    List,
+    People,
+}
+
+impl Kind {
+    fn of(property: &DatabaseProperty) -> Self {
+        match property {
+            DatabaseProperty::Number { .. } => Self::Number,
+            DatabaseProperty::Checkbox { .. } => Self::Checkbox,
+            DatabaseProperty::Select { .. } => Self::Select,
+            DatabaseProperty::Date { .. } => Self::Date,
+            DatabaseProperty::CreatedTime { .. } | DatabaseProperty::LastEditedTime { .. } => {
+                Self::Time
+            }
+            DatabaseProperty::MultiSelect { .. }
+            | DatabaseProperty::Relation { .. }
+            | DatabaseProperty::Files { .. } => Self::List,
+            DatabaseProperty::People { .. } => Self::People,
+            _ => Self::Text,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Text => DataType::Utf8,
+            Self::Number => DataType::Float64,
+            Self::Checkbox => DataType::Boolean,
+            Self::Select => {
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+            }
+            Self::Date | Self::Time => {
+                DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+            }
+            Self::Day => DataType::Date32,
+            Self::List => DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true))),
+            Self::People => DataType::List(Arc::new(Field::new_list_field(
+                DataType::Struct(user_fields()),
+                true,
+            ))),
+        }
+    }
+
+    fn builder(self) -> Box<dyn ArrayBuilder> {
+        match self {
+            Self::Text => Box::new(StringBuilder::new()),
+            Self::Number => Box::new(Float64Builder::new()),
+            Self::Checkbox => Box::new(BooleanBuilder::new()),
+            Self::Select => Box::new(StringDictionaryBuilder::<Int32Type>::new()),
+            Self::Date | Self::Time => {
+                Box::new(TimestampMillisecondBuilder::new().with_timezone("UTC"))
+            }
+            Self::Day => Box::new(Date32Builder::new()),
+            Self::List => Box::new(ListBuilder::new(StringBuilder::new())),
+            Self::People => Box::new(ListBuilder::new(StructBuilder::from_fields(
+                user_fields(),
+                0,
+            ))),
+        }
+    }
+}
+
+fn columns(database: &DatabaseObject, rows: &[PageObject]) -> Vec<(String, Kind)> {
+    Exporter::new(database, ExportOptions::default())
+        .headers()
+        .into_iter()
+        .map(|name| {
+            let kind = match Kind::of(&database.properties[&name]) {
+                Kind::Date if only_dates(rows, &name) => Kind::Day,
+                kind => kind,
+            };
+            (name, kind)
+        })
+        .collect()
+}
+
+/// Whether the rows have a value for the date property and none of them has a time
+fn only_dates(rows: &[PageObject], name: &str) -> bool {
+    let mut dates = rows
+        .iter()
+        .filter_map(|row| match row.properties.get(name) {
+            Some(PageProperty::Date {
+                date: Some(date), ..
+            }) => Some(date),
+            _ => None,
+        })
+        .peekable();
+    dates.peek().is_some()
+        && dates.all(|date| date.start.is_date() && date.end.is_none_or(|end| end.is_date()))
+}
+
+fn user_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("id", DataType::Utf8, true),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("email", DataType::Utf8, true),
+    ])
+}
+
+fn append_user(builder: &mut StructBuilder, user: &UserObject) {
+    let (name, email) = match user {
+        UserObject::Person { name, person, .. } => (name.as_deref(), Some(person.email.as_str())),
+        UserObject::Bot { name, .. } => (name.as_deref(), None),
+        UserObject::Unknown { .. } | UserObject::Partial { .. } => (None, None),
+    };
+    for (index, value) in [user.id(), name, email].iter().enumerate() {
+        builder
+            .field_builder::<StringBuilder>(index)
+            .expect("user fields are strings")
+            .append_option(*value);
+    }
+    builder.append(true);
+}
+
+fn builder_mut<T: ArrayBuilder>(builder: &mut Box<dyn ArrayBuilder>) -> &mut T {
+    builder
+        .as_any_mut()
+        .downcast_mut()
+        .expect("builder matches its column kind")
+}
+
+/// Days since the epoch, which is what `Date32` holds
+fn days(date: NaiveDate) -> i32 {
+    (date - NaiveDate::default()).num_days() as i32
+}
+
+/// Milliseconds since the epoch of an RFC 3339 time or a plain date
+fn millis(text: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.timestamp_millis())
+        .ok()
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+            Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+        })
+}
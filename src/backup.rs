@@ -0,0 +1,375 @@
+//! Offline backups of everything an integration can see. A backup directory looks like
+//!
+//! ```text
+//! manifest.json
+//! databases/<id>.json   the database object with its schema
+//! pages/<id>.json       the page object, including database rows
+//! blocks/<id>.json      the page's block tree
+//! files/<id>/<name>     hosted files from the page's properties, if downloaded
+//! ```
+//!
+//! Every file is written with sorted keys so that backups of an unchanged workspace are byte for
+//! byte identical, apart from the time the run started in the manifest. Running a backup into an
+//! existing directory only fetches the block trees of pages whose `last_edited_time` changed since
+//! the manifest was written, or that were edited in the minute the last run started, and removes
+//! the files of anything that no longer exists. Databases are only rewritten when they were edited, and their
+//! rows are queried newest edit first, stopping at the first one older than the last run. Rows
+//! that weren't edited are kept as long as the page search still finds them.
+use crate::{
+    block::BlockTree,
+    database::{DatabaseObject, Direction, QueryDatabaseSort, QueryDatabaseTime},
+    page::{PageFile, PageObject, PageProperty},
+    search::SearchFilter,
+    time::Time,
+    Client,
+};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default)]
+pub struct BackupOptions {
+    /// Download files hosted by Notion that are attached to pages
+    pub download_files: bool,
+    /// Ignore the manifest and fetch everything again
+    pub full: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Manifest {
+    /// When the run that wrote the manifest started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<String>,
+    pub databases: BTreeMap<String, ManifestEntry>,
+    pub pages: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ManifestEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub last_edited_time: String,
+    /// The rows of a database
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<String>,
+    /// Files downloaded for a page, relative to the backup directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct BackupReport {
+    pub databases: usize,
+    pub pages: usize,
+    /// Pages whose block tree was fetched in this run
+    pub fetched: usize,
+    /// Pages that hadn't changed since the last run
+    pub unchanged: usize,
+    /// Pages and databases that were in the manifest but no longer exist
+    pub removed: usize,
+}
+
+impl Manifest {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = dir.as_ref().join("manifest.json");
+        if path.exists() {
+            read_json(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Back up every page and database the client can see into `dir`
+pub async fn backup(
+    client: &Client,
+    dir: impl AsRef<Path>,
+    options: &BackupOptions,
+) -> Result<BackupReport, Box<dyn Error>> {
+    let dir = dir.as_ref();
+    for sub in &["databases", "pages", "blocks"] {
+        fs::create_dir_all(dir.join(sub))?;
+    }
+
+    let previous = if options.full {
+        Manifest::default()
+    } else {
+        Manifest::load(dir)?
+    };
+    let mut manifest = Manifest {
+        started: Some(Time::from(Utc::now()).to_string()),
+        ..Manifest::default()
+    };
+    let mut report = BackupReport::default();
+
+    let filter = |value: &str| SearchFilter {
+        value: Some(value.to_string()),
+        property: Some("object".to_string()),
+    };
+
+    // Notion keeps edit times to the minute, so pages edited in the minute the last run started
+    // might have changed after it read them
+    let last_run = previous
+        .started
+        .as_deref()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .and_then(|time| {
+            time.with_timezone(&Utc)
+                .duration_trunc(TimeDelta::minutes(1))
+                .ok()
+        });
+
+    let mut pages = BTreeMap::new();
+    for result in client.search_all(None, Some(filter("page"))).await? {
+        let page = result.into_page()?;
+        pages.insert(page.id.clone(), page);
+    }
+
+    for result in client.search_all(None, Some(filter("database"))).await? {
+        let database = result.into_database()?;
+        let last_edited_time = database.last_edited_time.to_string();
+        let path = database_path(dir, &database.id);
+        let entry = previous.databases.get(&database.id);
+
+        // Rows edited since the last run, the others are still there if the search found them
+        let since = entry.and_then(|entry| newest_row(&previous, &entry.rows));
+        let mut rows = entry
+            .iter()
+            .flat_map(|entry| &entry.rows)
+            .filter(|id| pages.contains_key(*id))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for row in edited_rows(client, &database.id, since).await? {
+            rows.insert(row.id.clone());
+            pages.insert(row.id.clone(), row);
+        }
+
+        if entry.is_none_or(|entry| entry.last_edited_time != last_edited_time) || !path.exists() {
+            write_json(&path, &database)?;
+        }
+        manifest.databases.insert(
+            database.id.clone(),
+            ManifestEntry {
+                title: Some(database_title(&database)),
+                last_edited_time,
+                rows: rows.into_iter().collect(),
+                files: Vec::new(),
+            },
+        );
+        report.databases += 1;
+    }
+
+    for (id, page) in &pages {
+        let last_edited_time = page.last_edited_time.to_string();
+        let blocks = blocks_path(dir, id);
+        let unchanged = previous
+            .pages
+            .get(id)
+            .is_some_and(|entry| entry.last_edited_time == last_edited_time)
+            && last_run.is_some_and(|last_run| page.last_edited_time.to_utc() < last_run)
+            && blocks.exists();
+
+        let files = if unchanged {
+            report.unchanged += 1;
+            previous.pages[id].files.clone()
+        } else {
+            report.fetched += 1;
+            write_json(&blocks, &client.get_block_tree(id).await?)?;
+            let files = if options.download_files {
+                download_files(client, dir, page).await?
+            } else {
+                Vec::new()
+            };
+            for old in previous.pages.get(id).iter().flat_map(|e| &e.files) {
+                if !files.contains(old) {
+                    remove_file(&dir.join(old))?;
+                }
+            }
+            files
+        };
+
+        if !unchanged || !page_path(dir, id).exists() {
+            write_json(&page_path(dir, id), page)?;
+        }
+        manifest.pages.insert(
+            id.clone(),
+            ManifestEntry {
+                title: page.title(),
+                last_edited_time,
+                rows: Vec::new(),
+                files,
+            },
+        );
+        report.pages += 1;
+    }
+
+    for id in previous.databases.keys() {
+        if !manifest.databases.contains_key(id) {
+            remove_file(&database_path(dir, id))?;
+            report.removed += 1;
+        }
+    }
+    for (id, entry) in &previous.pages {
+        if !manifest.pages.contains_key(id) {
+            remove_file(&page_path(dir, id))?;
+            remove_file(&blocks_path(dir, id))?;
+            for file in &entry.files {
+                remove_file(&dir.join(file))?;
+            }
+            report.removed += 1;
+        }
+    }
+
+    write_json(&dir.join("manifest.json"), &manifest)?;
+    Ok(report)
+}
+
+/// Read a backed up database
+pub fn read_database(dir: impl AsRef<Path>, id: &str) -> Result<DatabaseObject, Box<dyn Error>> {
+    read_json(&database_path(dir.as_ref(), id))
+}
+
+/// Read a backed up page
+pub fn read_page(dir: impl AsRef<Path>, id: &str) -> Result<PageObject, Box<dyn Error>> {
+    read_json(&page_path(dir.as_ref(), id))
+}
+
+/// Read the block tree of a backed up page
+pub fn read_blocks(dir: impl AsRef<Path>, id: &str) -> Result<Vec<BlockTree>, Box<dyn Error>> {
+    read_json(&blocks_path(dir.as_ref(), id))
+}
+
+async fn download_files(
+    client: &Client,
+    dir: &Path,
+    page: &PageObject,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut names = BTreeSet::new();
+    let mut properties = page.properties.iter().collect::<Vec<_>>();
+    properties.sort_by_key(|(name, _)| *name);
+
+    for (_, property) in properties {
+        let references = match property {
+            PageProperty::Files { files, .. } => files,
+            _ => continue,
+        };
+        for reference in references {
+            // Only files hosted by Notion are downloaded, external ones are just links
+            let url = match &reference.file {
+                PageFile::File { file } => &file.url,
+                _ => continue,
+            };
+            let name = unique_name(reference.name.replace(['/', '\\'], "_"), &mut names);
+            let relative = format!("files/{}/{}", page.id, name);
+            let bytes = client.download(url).await?;
+            fs::create_dir_all(dir.join("files").join(&page.id))?;
+            fs::write(dir.join(&relative), bytes)?;
+            files.push(relative);
+        }
+    }
+
+    Ok(files)
+}
+
+/// The rows of a database edited at or after `since`, or all of them without it
+async fn edited_rows(
+    client: &Client,
+    database_id: &str,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<PageObject>, Box<dyn Error>> {
+    let sorts = vec![QueryDatabaseSort {
+        property: None,
+        timestamp: Some(QueryDatabaseTime::LastEditedTime),
+        direction: Some(Direction::Descending),
+    }];
+    let mut rows = Vec::new();
+    let mut start_cursor = None;
+    loop {
+        let list = client
+            .query_database(database_id, None, Some(sorts.clone()), start_cursor, None)
+            .await?
+            .into_list()?;
+        for result in list.results {
+            let row = result.into_page()?;
+            if since.is_some_and(|since| row.last_edited_time.to_utc() < since) {
+                return Ok(rows);
+            }
+            rows.push(row);
+        }
+        match list.next_cursor {
+            Some(cursor) if list.has_more => start_cursor = Some(cursor),
+            _ => return Ok(rows),
+        }
+    }
+}
+
+/// When the most recently edited of a database's rows was edited in the last run. Rows edited in
+/// the same instant are fetched again, since Notion only keeps edit times to the minute.
+fn newest_row(previous: &Manifest, rows: &[String]) -> Option<DateTime<Utc>> {
+    rows.iter()
+        .filter_map(|id| previous.pages.get(id))
+        .filter_map(|entry| DateTime::parse_from_rfc3339(&entry.last_edited_time).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .max()
+}
+
+/// Attachments can share a name, later ones get a number before the extension e.g. `notes-2.pdf`
+fn unique_name(name: String, used: &mut BTreeSet<String>) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name.as_str(), ""),
+    };
+    let mut unique = name.clone();
+    let mut count = 1;
+    while used.contains(&unique) {
+        count += 1;
+        unique = format!("{}-{}{}", stem, count, extension);
+    }
+    used.insert(unique.clone());
+    unique
+}
+
+fn database_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join("databases").join(format!("{}.json", id))
+}
+
+fn page_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join("pages").join(format!("{}.json", id))
+}
+
+fn blocks_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join("blocks").join(format!("{}.json", id))
+}
+
+fn database_title(database: &DatabaseObject) -> String {
+    database
+        .title
+        .iter()
+        .map(crate::rich_text::RichTextObject::plain_text)
+        .collect()
+}
+
+/// Going through `Value` sorts the keys of every map so the output is stable between runs
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn Error>> {
+    let mut json = serde_json::to_string_pretty(&serde_json::to_value(value)?)?;
+    json.push('\n');
+    fs::write(path, json)?;
+    Ok(())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn remove_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Box::new(e)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,47 @@
+use inkling::{
+    backup::{backup, BackupOptions},
+    Client,
+};
+use std::{env, error::Error, process};
+
+const USAGE: &str = "Usage: inkling-backup <directory> [--files] [--full]
+
+Backs up every page and database the integration can see. The token is read from NOTION_TOKEN.
+
+  --files  download files hosted by Notion
+  --full   fetch everything again instead of only what changed";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut dir = None;
+    let mut options = BackupOptions::default();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--files" => options.download_files = true,
+            "--full" => options.full = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if dir.is_none() && !arg.starts_with('-') => dir = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+    }
+    let (dir, token) = match (dir, env::var("NOTION_TOKEN")) {
+        (Some(dir), Ok(token)) => (dir, token),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let report = backup(&Client::new(token), &dir, &options).await?;
+    println!(
+        "Backed up {} databases and {} pages to {} ({} fetched, {} unchanged, {} removed)",
+        report.databases, report.pages, dir, report.fetched, report.unchanged, report.removed
+    );
+    Ok(())
+}
@@ -1,9 +1,15 @@
-use crate::{rich_text::RichTextObject, time::Time};
+use crate::{
+    database::Empty,
+    rich_text::{Mention, RichTextObject},
+    time::Time,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum BlockObject {
     Paragraph {
         id: String,
@@ -11,6 +17,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         paragraph: Paragraph,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     #[serde(rename = "heading_1")]
     Heading1 {
@@ -19,6 +27,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         heading_1: Heading1,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     #[serde(rename = "heading_2")]
     Heading2 {
@@ -27,6 +37,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         heading_2: Heading2,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     #[serde(rename = "heading_3")]
     Heading3 {
@@ -35,6 +47,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         heading_3: Heading3,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     BulletedListItem {
         id: String,
@@ -42,6 +56,17 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         bulleted_list_item: BulletedListItem,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    NumberedListItem {
+        id: String,
+        created_time: Time,
+        last_edited_time: Time,
+        has_children: bool,
+        numbered_list_item: NumberedListItem,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     ToDo {
         id: String,
@@ -49,6 +74,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         to_do: ToDo,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     Toggle {
         id: String,
@@ -56,6 +83,8 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         toggle: Toggle,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     ChildPage {
         id: String,
@@ -63,65 +92,445 @@ pub enum BlockObject {
         last_edited_time: Time,
         has_children: bool,
         child_page: ChildPage,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    ChildDatabase {
+        id: String,
+        created_time: Time,
+        last_edited_time: Time,
+        has_children: bool,
+        child_database: ChildDatabase,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    Code {
+        id: String,
+        created_time: Time,
+        last_edited_time: Time,
+        has_children: bool,
+        code: Code,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    LinkToPage {
+        id: String,
+        created_time: Time,
+        last_edited_time: Time,
+        has_children: bool,
+        link_to_page: LinkToPage,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
     Unsupported {
         id: String,
         created_time: Time,
         last_edited_time: Time,
         has_children: bool,
+        #[serde(default)]
+        unsupported: Empty,
+        #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+        extra: HashMap<String, serde_json::Value>,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(
+    BlockObject,
+    "type",
+    [
+        "paragraph",
+        "heading_1",
+        "heading_2",
+        "heading_3",
+        "bulleted_list_item",
+        "numbered_list_item",
+        "to_do",
+        "toggle",
+        "child_page",
+        "child_database",
+        "code",
+        "link_to_page",
+        "unsupported"
+    ]
+);
+
+impl BlockObject {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Paragraph { id, .. }
+            | Self::Heading1 { id, .. }
+            | Self::Heading2 { id, .. }
+            | Self::Heading3 { id, .. }
+            | Self::BulletedListItem { id, .. }
+            | Self::NumberedListItem { id, .. }
+            | Self::ToDo { id, .. }
+            | Self::Toggle { id, .. }
+            | Self::ChildPage { id, .. }
+            | Self::ChildDatabase { id, .. }
+            | Self::Code { id, .. }
+            | Self::LinkToPage { id, .. }
+            | Self::Unsupported { id, .. } => Some(id),
+            Self::Unknown { raw, .. } => raw.get("id").and_then(serde_json::Value::as_str),
+        }
+    }
+
+    pub fn has_children(&self) -> bool {
+        match self {
+            Self::Paragraph { has_children, .. }
+            | Self::Heading1 { has_children, .. }
+            | Self::Heading2 { has_children, .. }
+            | Self::Heading3 { has_children, .. }
+            | Self::BulletedListItem { has_children, .. }
+            | Self::NumberedListItem { has_children, .. }
+            | Self::ToDo { has_children, .. }
+            | Self::Toggle { has_children, .. }
+            | Self::ChildPage { has_children, .. }
+            | Self::ChildDatabase { has_children, .. }
+            | Self::Code { has_children, .. }
+            | Self::LinkToPage { has_children, .. }
+            | Self::Unsupported { has_children, .. } => *has_children,
+            Self::Unknown { raw, .. } => raw
+                .get("has_children")
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The value of the `type` field e.g. `paragraph`
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::Paragraph { .. } => "paragraph",
+            Self::Heading1 { .. } => "heading_1",
+            Self::Heading2 { .. } => "heading_2",
+            Self::Heading3 { .. } => "heading_3",
+            Self::BulletedListItem { .. } => "bulleted_list_item",
+            Self::NumberedListItem { .. } => "numbered_list_item",
+            Self::ToDo { .. } => "to_do",
+            Self::Toggle { .. } => "toggle",
+            Self::ChildPage { .. } => "child_page",
+            Self::ChildDatabase { .. } => "child_database",
+            Self::Code { .. } => "code",
+            Self::LinkToPage { .. } => "link_to_page",
+            Self::Unsupported { .. } => "unsupported",
+            Self::Unknown { type_name, .. } => type_name,
+        }
+    }
+
+    /// Whether the block has children that are part of the same page. Child pages and databases
+    /// have children too, but those are the blocks and rows of a page of their own.
+    pub fn has_nested_blocks(&self) -> bool {
+        self.has_children() && !matches!(self, Self::ChildPage { .. } | Self::ChildDatabase { .. })
+    }
+
+    /// The rich text content of the block, if it has any
+    pub fn text(&self) -> Option<&[RichTextObject]> {
+        match self {
+            Self::Paragraph { paragraph, .. } => Some(&paragraph.text),
+            Self::Heading1 { heading_1, .. } => Some(&heading_1.text),
+            Self::Heading2 { heading_2, .. } => Some(&heading_2.text),
+            Self::Heading3 { heading_3, .. } => Some(&heading_3.text),
+            Self::BulletedListItem {
+                bulleted_list_item, ..
+            } => Some(&bulleted_list_item.text),
+            Self::NumberedListItem {
+                numbered_list_item, ..
+            } => Some(&numbered_list_item.text),
+            Self::ToDo { to_do, .. } => Some(&to_do.text),
+            Self::Toggle { toggle, .. } => Some(&toggle.text),
+            Self::Code { code, .. } => Some(&code.text),
+            Self::ChildPage { .. }
+            | Self::ChildDatabase { .. }
+            | Self::LinkToPage { .. }
+            | Self::Unsupported { .. }
+            | Self::Unknown { .. } => None,
+        }
+    }
+
+    pub fn text_mut(&mut self) -> Option<&mut Vec<RichTextObject>> {
+        match self {
+            Self::Paragraph { paragraph, .. } => Some(&mut paragraph.text),
+            Self::Heading1 { heading_1, .. } => Some(&mut heading_1.text),
+            Self::Heading2 { heading_2, .. } => Some(&mut heading_2.text),
+            Self::Heading3 { heading_3, .. } => Some(&mut heading_3.text),
+            Self::BulletedListItem {
+                bulleted_list_item, ..
+            } => Some(&mut bulleted_list_item.text),
+            Self::NumberedListItem {
+                numbered_list_item, ..
+            } => Some(&mut numbered_list_item.text),
+            Self::ToDo { to_do, .. } => Some(&mut to_do.text),
+            Self::Toggle { toggle, .. } => Some(&mut toggle.text),
+            Self::Code { code, .. } => Some(&mut code.text),
+            Self::ChildPage { .. }
+            | Self::ChildDatabase { .. }
+            | Self::LinkToPage { .. }
+            | Self::Unsupported { .. }
+            | Self::Unknown { .. } => None,
+        }
+    }
+
+    /// The nested blocks if they were included in the response
+    pub fn children(&self) -> Option<&[BlockObject]> {
+        match self {
+            Self::Paragraph { paragraph, .. } => paragraph.children.as_deref(),
+            Self::BulletedListItem {
+                bulleted_list_item, ..
+            } => bulleted_list_item.children.as_deref(),
+            Self::NumberedListItem {
+                numbered_list_item, ..
+            } => numbered_list_item.children.as_deref(),
+            Self::ToDo { to_do, .. } => to_do.children.as_deref(),
+            Self::Toggle { toggle, .. } => toggle.children.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl BlockObject {
+    /// The content of the block to send to Notion to create a copy of it. Children aren't
+    /// included and have to be appended to the new block separately. Blocks that can't be created
+    /// through the API, like child pages, return `None`.
+    pub fn to_input(&self) -> Option<BlockObjectInput> {
+        let object = "block".to_string();
+        Some(match self.clone() {
+            Self::Paragraph { mut paragraph, .. } => {
+                paragraph.children = None;
+                BlockObjectInput::Paragraph { object, paragraph }
+            }
+            Self::Heading1 { heading_1, .. } => BlockObjectInput::Heading1 { object, heading_1 },
+            Self::Heading2 { heading_2, .. } => BlockObjectInput::Heading2 { object, heading_2 },
+            Self::Heading3 { heading_3, .. } => BlockObjectInput::Heading3 { object, heading_3 },
+            Self::BulletedListItem {
+                mut bulleted_list_item,
+                ..
+            } => {
+                bulleted_list_item.children = None;
+                BlockObjectInput::BulletedListItem {
+                    object,
+                    bulleted_list_item,
+                }
+            }
+            Self::NumberedListItem {
+                mut numbered_list_item,
+                ..
+            } => {
+                numbered_list_item.children = None;
+                BlockObjectInput::NumberedListItem {
+                    object,
+                    numbered_list_item,
+                }
+            }
+            Self::ToDo { mut to_do, .. } => {
+                to_do.children = None;
+                BlockObjectInput::ToDo { object, to_do }
+            }
+            Self::Toggle { mut toggle, .. } => {
+                toggle.children = None;
+                BlockObjectInput::Toggle { object, toggle }
+            }
+            Self::Code { code, .. } => BlockObjectInput::Code { object, code },
+            Self::LinkToPage { link_to_page, .. } => BlockObjectInput::LinkToPage {
+                object,
+                link_to_page,
+            },
+            Self::ChildPage { .. }
+            | Self::ChildDatabase { .. }
+            | Self::Unsupported { .. }
+            | Self::Unknown { .. } => return None,
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl BlockObjectInput {
+    pub fn text_mut(&mut self) -> Option<&mut Vec<RichTextObject>> {
+        match self {
+            Self::Paragraph { paragraph, .. } => Some(&mut paragraph.text),
+            Self::Heading1 { heading_1, .. } => Some(&mut heading_1.text),
+            Self::Heading2 { heading_2, .. } => Some(&mut heading_2.text),
+            Self::Heading3 { heading_3, .. } => Some(&mut heading_3.text),
+            Self::BulletedListItem {
+                bulleted_list_item, ..
+            } => Some(&mut bulleted_list_item.text),
+            Self::NumberedListItem {
+                numbered_list_item, ..
+            } => Some(&mut numbered_list_item.text),
+            Self::ToDo { to_do, .. } => Some(&mut to_do.text),
+            Self::Toggle { toggle, .. } => Some(&mut toggle.text),
+            Self::Code { code, .. } => Some(&mut code.text),
+            Self::ChildPage { .. } | Self::LinkToPage { .. } => None,
+        }
+    }
+
+    /// The same content as an update to an existing block, `None` for blocks that can't be
+    /// edited in place
+    pub fn to_update(&self) -> Option<BlockObjectUpdate> {
+        Some(match self.clone() {
+            Self::Paragraph { paragraph, .. } => BlockObjectUpdate::Paragraph(paragraph),
+            Self::Heading1 { heading_1, .. } => BlockObjectUpdate::Heading1(heading_1),
+            Self::Heading2 { heading_2, .. } => BlockObjectUpdate::Heading2(heading_2),
+            Self::Heading3 { heading_3, .. } => BlockObjectUpdate::Heading3(heading_3),
+            Self::BulletedListItem {
+                bulleted_list_item, ..
+            } => BlockObjectUpdate::BulletedListItem(bulleted_list_item),
+            Self::NumberedListItem {
+                numbered_list_item, ..
+            } => BlockObjectUpdate::NumberedListItem(numbered_list_item),
+            Self::ToDo { to_do, .. } => BlockObjectUpdate::ToDo(to_do),
+            Self::Toggle { toggle, .. } => BlockObjectUpdate::Toggle(toggle),
+            Self::Code { code, .. } => BlockObjectUpdate::Code(code),
+            Self::ChildPage { .. } | Self::LinkToPage { .. } => return None,
+        })
+    }
+
+    /// Point mentions and page links at new ids, ids that aren't in the map are left alone
+    pub fn remap_ids(&mut self, ids: &BTreeMap<String, String>) {
+        if let Self::LinkToPage { link_to_page, .. } = self {
+            match link_to_page {
+                LinkToPage::PageId { page_id: id } | LinkToPage::DatabaseId { database_id: id } => {
+                    if let Some(new) = ids.get(id) {
+                        *id = new.clone();
+                    }
+                }
+                LinkToPage::Unknown { .. } => {}
+            }
+        }
+        for text in self.text_mut().into_iter().flatten() {
+            text.remap_ids(ids);
+        }
+    }
+}
+
+/// A block along with all of its children, as fetched by `Client::get_block_tree`. The children
+/// of child pages and databases aren't included.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockTree {
+    pub block: BlockObject,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BlockTree>,
+}
+
+/// Every mention in the blocks and all of their children, e.g. from `Client::get_block_tree`.
+/// Notion doesn't include children when listing blocks, so they have to be fetched as a tree.
+pub fn mentions(blocks: &[BlockTree]) -> Vec<&Mention> {
+    blocks
+        .iter()
+        .flat_map(|tree| {
+            tree.block
+                .text()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|text| match text {
+                    RichTextObject::Mention { mention, .. } => Some(mention),
+                    _ => None,
+                })
+                .chain(mentions(&tree.children))
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Paragraph {
     pub text: Vec<RichTextObject>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<BlockObject>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Heading1 {
     pub text: Vec<RichTextObject>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Heading2 {
     pub text: Vec<RichTextObject>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Heading3 {
     pub text: Vec<RichTextObject>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BulletedListItem {
     pub text: Vec<RichTextObject>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<BlockObject>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NumberedListItem {
     pub text: Vec<RichTextObject>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<BlockObject>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ToDo {
     pub text: Vec<RichTextObject>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checked: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<BlockObject>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Toggle {
     pub text: Vec<RichTextObject>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<BlockObject>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChildPage {
     pub title: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChildDatabase {
+    pub title: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
+pub enum LinkToPage {
+    PageId {
+        page_id: String,
+    },
+    DatabaseId {
+        database_id: String,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(LinkToPage, "type", ["page_id", "database_id"]);
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Code {
+    pub text: Vec<RichTextObject>,
+    pub language: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum BlockObjectInput {
@@ -148,6 +557,10 @@ pub enum BlockObjectInput {
         object: String,
         bulleted_list_item: BulletedListItem,
     },
+    NumberedListItem {
+        object: String,
+        numbered_list_item: NumberedListItem,
+    },
     ToDo {
         object: String,
         to_do: ToDo,
@@ -160,4 +573,31 @@ pub enum BlockObjectInput {
         object: String,
         child_page: ChildPage,
     },
+    Code {
+        object: String,
+        code: Code,
+    },
+    LinkToPage {
+        object: String,
+        link_to_page: LinkToPage,
+    },
+}
+
+/// The body of a block update, Notion expects only the type key and its new content e.g.
+/// `{ "to_do": { "text": [...], "checked": true } }`
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockObjectUpdate {
+    Paragraph(Paragraph),
+    #[serde(rename = "heading_1")]
+    Heading1(Heading1),
+    #[serde(rename = "heading_2")]
+    Heading2(Heading2),
+    #[serde(rename = "heading_3")]
+    Heading3(Heading3),
+    BulletedListItem(BulletedListItem),
+    NumberedListItem(NumberedListItem),
+    ToDo(ToDo),
+    Toggle(Toggle),
+    Code(Code),
 }
@@ -0,0 +1,343 @@
+//! A synchronous [`Client`] for programs that don't otherwise need an async runtime. It has the
+//! same methods as the async [`Client`](crate::Client) and builds its requests the same way, it
+//! just waits for each response before returning.
+//!
+//! The reqwest transport starts its own runtime, so this client shouldn't be used from inside an
+//! async context.
+use crate::{
+    block::{BlockObjectInput, BlockObjectUpdate, BlockTree},
+    cassette::Cassette,
+    database::{DatabaseSchema, DatabaseSchemaChanges, QueryDatabaseFilter, QueryDatabaseSort},
+    endpoint::{self, ClientCore},
+    middleware::Middleware,
+    page::{PagePropertyValue, Parent},
+    rich_text::RichTextObject,
+    search::{SearchFilter, SearchSort},
+    transport::{Request, Response},
+    NotionObject,
+};
+use std::{collections::HashMap, error::Error, sync::Arc, time::Instant};
+
+/// The synchronous counterpart of [`crate::transport::Transport`]
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> Result<Response, Box<dyn Error>>;
+}
+
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport(pub reqwest::blocking::Client);
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        use crate::transport::Method;
+
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+        };
+        let mut builder = self.0.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send()?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let body = response.bytes()?.to_vec();
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+pub struct Client {
+    transport: Arc<dyn Transport>,
+    core: ClientCore,
+}
+
+impl Client {
+    #[cfg(feature = "reqwest")]
+    pub fn new(token: impl Into<String>) -> Self {
+        Self::with_transport(token, ReqwestTransport::new())
+    }
+
+    /// A client that sends its requests through `transport` instead of reqwest
+    pub fn with_transport(token: impl Into<String>, transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            core: ClientCore::new(token.into()),
+        }
+    }
+
+    /// Send requests somewhere other than `https://api.notion.com/v1/`, e.g. a mock server. The
+    /// url should end with a `/`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.core.base_url = base_url.into();
+        self
+    }
+
+    /// Record every request and response to a cassette, or answer requests from one
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.core.cassette = Some(cassette);
+        self
+    }
+
+    /// Add a layer to the end of the middleware chain
+    pub fn with_middleware(mut self, layer: impl Middleware + 'static) -> Self {
+        self.core.middleware.push(Arc::new(layer));
+        self
+    }
+
+    fn execute(&self, endpoint: endpoint::Built) -> Result<NotionObject, Box<dyn Error>> {
+        let endpoint = endpoint?;
+        if let Some(object) = self.core.replay(&endpoint)? {
+            return Ok(object);
+        }
+        let request = self.core.request(&endpoint);
+        #[cfg(feature = "tracing")]
+        let spans = self.core.spans(&endpoint, &request);
+        #[cfg(feature = "tracing")]
+        let _entered = spans.iter().map(tracing::Span::enter).collect::<Vec<_>>();
+        let started = Instant::now();
+        let response = match self.transport.send(request.clone()) {
+            Ok(response) => response,
+            Err(error) => {
+                self.core
+                    .failed(&endpoint, &request, &*error, started.elapsed());
+                return Err(error);
+            }
+        };
+        self.core
+            .response(&endpoint, &request, response, started.elapsed())
+    }
+
+    pub fn get_database(&self, id: &str) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::get_database(id))
+    }
+
+    /// Currently broken on Notion's end and will return only an empty list as of 05-17-2021
+    pub fn list_databases(
+        &self,
+        start_cursor: Option<String>,
+        page_size: Option<i32>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::list_databases(start_cursor, page_size))
+    }
+
+    pub fn get_user(&self, id: &str) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::get_user(id))
+    }
+
+    pub fn list_users(
+        &self,
+        start_cursor: Option<String>,
+        page_size: Option<i32>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::list_users(start_cursor, page_size))
+    }
+
+    pub fn get_block_children(
+        &self,
+        id: &str,
+        start_cursor: Option<String>,
+        page_size: Option<i32>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::get_block_children(id, start_cursor, page_size))
+    }
+
+    pub fn get_block(&self, id: &str) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::get_block(id))
+    }
+
+    pub fn get_page(&self, id: &str) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::get_page(id))
+    }
+
+    pub fn update_page_properties(
+        &self,
+        id: &str,
+        properties: HashMap<String, PagePropertyValue>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::update_page_properties(id, properties))
+    }
+
+    pub fn update_database(
+        &self,
+        id: &str,
+        title: Option<Vec<RichTextObject>>,
+        schema_changes: Option<DatabaseSchemaChanges>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::update_database(id, title, schema_changes))
+    }
+
+    pub fn append_block_children(
+        &self,
+        id: &str,
+        children: Vec<BlockObjectInput>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::append_block_children(id, children))
+    }
+
+    /// Insert blocks after the child block `after` instead of at the end
+    pub fn append_block_children_after(
+        &self,
+        id: &str,
+        children: Vec<BlockObjectInput>,
+        after: &str,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::append_block_children_after(id, children, after))
+    }
+
+    pub fn update_block(
+        &self,
+        id: &str,
+        content: BlockObjectUpdate,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::update_block(id, content))
+    }
+
+    /// Notion doesn't remove the block outright, it archives it and returns the archived block
+    pub fn delete_block(&self, id: &str) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::delete_block(id))
+    }
+
+    pub fn create_page(
+        &self,
+        parent: Parent,
+        properties: HashMap<String, PagePropertyValue>,
+        children_blocks: Option<Vec<BlockObjectInput>>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::create_page(parent, properties, children_blocks))
+    }
+
+    pub fn create_database(
+        &self,
+        parent_page: &str,
+        title: Vec<RichTextObject>,
+        schema: DatabaseSchema,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::create_database(parent_page, title, schema))
+    }
+
+    pub fn query_database(
+        &self,
+        id: &str,
+        filter: Option<QueryDatabaseFilter>,
+        sorts: Option<Vec<QueryDatabaseSort>>,
+        start_cursor: Option<String>,
+        page_size: Option<i32>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::query_database(
+            id,
+            filter,
+            sorts,
+            start_cursor,
+            page_size,
+        ))
+    }
+
+    pub fn search(
+        &self,
+        query: Option<String>,
+        filter: Option<SearchFilter>,
+        sort: Option<SearchSort>,
+        start_cursor: Option<String>,
+        page_size: Option<i32>,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        self.execute(endpoint::search(
+            query,
+            filter,
+            sort,
+            start_cursor,
+            page_size,
+        ))
+    }
+
+    /// Run a search and follow `next_cursor` until every result has been fetched
+    pub fn search_all(
+        &self,
+        query: Option<String>,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<NotionObject>, Box<dyn Error>> {
+        let mut results = Vec::new();
+        let mut start_cursor = None;
+        loop {
+            let list = self
+                .search(query.clone(), filter.clone(), None, start_cursor, None)?
+                .into_list()?;
+            results.extend(list.results);
+            match list.next_cursor {
+                Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                _ => return Ok(results),
+            }
+        }
+    }
+
+    /// Query a database and follow `next_cursor` until every row has been fetched
+    pub fn query_database_all(
+        &self,
+        id: &str,
+        filter: Option<QueryDatabaseFilter>,
+        sorts: Option<Vec<QueryDatabaseSort>>,
+    ) -> Result<Vec<NotionObject>, Box<dyn Error>> {
+        let mut results = Vec::new();
+        let mut start_cursor = None;
+        loop {
+            let list = self
+                .query_database(id, filter.clone(), sorts.clone(), start_cursor, None)?
+                .into_list()?;
+            results.extend(list.results);
+            match list.next_cursor {
+                Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                _ => return Ok(results),
+            }
+        }
+    }
+
+    /// Fetch every child of a block following `next_cursor`, along with all of their children.
+    /// Child pages and databases are included but not what's inside them.
+    pub fn get_block_tree(&self, id: &str) -> Result<Vec<BlockTree>, Box<dyn Error>> {
+        let mut tree = Vec::new();
+        let mut start_cursor = None;
+        loop {
+            let list = self
+                .get_block_children(id, start_cursor, None)?
+                .into_list()?;
+            for result in list.results {
+                let block = result.into_block()?;
+                let children = match block.id() {
+                    Some(id) if block.has_nested_blocks() => self.get_block_tree(id)?,
+                    _ => Vec::new(),
+                };
+                tree.push(BlockTree { block, children });
+            }
+            match list.next_cursor {
+                Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                _ => return Ok(tree),
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Recording the requests a [`Client`](crate::Client) makes and the responses it gets so they
+//! can be played back later without a network connection or a token. A recording cassette sends
+//! requests as usual and writes every exchange to a JSON file with the token redacted. A
+//! replaying cassette answers each request with the first unused recording with the same method,
+//! path and body, and fails any request it has no recording for.
+//!
+//! Bodies are compared as JSON, so the order of object keys doesn't matter.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Which interactions have already been played back
+    used: Mutex<Vec<bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The url after the base url e.g. `databases/<id>/query`
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// The response as JSON, or a string if it wasn't JSON
+    pub body: Value,
+}
+
+impl Cassette {
+    /// Record to `path`, replacing anything already recorded there
+    pub fn recording(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            mode: Mode::Record,
+            interactions: Mutex::new(Vec::new()),
+            used: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Play back what was recorded to `path`
+    pub fn replaying(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let tape: Tape = serde_json::from_str(&fs::read_to_string(path.as_ref())?)?;
+        let used = vec![false; tape.interactions.len()];
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: Mode::Replay,
+            interactions: Mutex::new(tape.interactions),
+            used: Mutex::new(used),
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.mode == Mode::Replay
+    }
+
+    /// Everything recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        lock(&self.interactions).clone()
+    }
+
+    /// The recorded response to a request when replaying, `None` when recording
+    pub(crate) fn play(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&[u8]>,
+    ) -> Result<Option<String>, CassetteError> {
+        if self.mode == Mode::Record {
+            return Ok(None);
+        }
+        let body = normalize(body);
+        let interactions = lock(&self.interactions);
+        let mut used = lock(&self.used);
+        let found = interactions
+            .iter()
+            .enumerate()
+            .position(|(index, recorded)| {
+                !used[index]
+                    && recorded.request.method == method
+                    && recorded.request.path == path
+                    && recorded.request.body == body
+            });
+        match found {
+            Some(index) => {
+                used[index] = true;
+                Ok(Some(match &interactions[index].response.body {
+                    Value::String(text) => text.clone(),
+                    body => body.to_string(),
+                }))
+            }
+            None => Err(CassetteError {
+                path: self.path.clone(),
+                method: method.to_string(),
+                request_path: path.to_string(),
+                body,
+            }),
+        }
+    }
+
+    /// Add an exchange to the cassette and save it when recording
+    pub(crate) fn record(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&[u8]>,
+        status: u16,
+        response: &str,
+        token: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.mode == Mode::Replay {
+            return Ok(());
+        }
+        let mut headers = BTreeMap::new();
+        headers.insert("Authorization".to_string(), format!("Bearer {}", REDACTED));
+        headers.insert(
+            "Notion-Version".to_string(),
+            crate::endpoint::NOTION_VERSION.to_string(),
+        );
+        let redact_token = |text: &str| {
+            if token.is_empty() {
+                text.to_string()
+            } else {
+                text.replace(token, REDACTED)
+            }
+        };
+        let response = redact_token(response);
+        let interaction = Interaction {
+            request: RecordedRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                headers,
+                body: normalize(body).map(|body| redact(body, &redact_token)),
+            },
+            response: RecordedResponse {
+                status,
+                body: serde_json::from_str(&response).unwrap_or(Value::String(response)),
+            },
+        };
+
+        let mut interactions = lock(&self.interactions);
+        interactions.push(interaction);
+        let tape = Tape {
+            interactions: interactions.clone(),
+        };
+        fs::write(&self.path, serde_json::to_string_pretty(&tape)?)?;
+        Ok(())
+    }
+}
+
+/// A request that nothing on the cassette matches
+#[derive(Debug)]
+pub struct CassetteError {
+    pub path: PathBuf,
+    pub method: String,
+    pub request_path: String,
+    pub body: Option<Value>,
+}
+impl fmt::Display for CassetteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No unused recording in {} for {} {}",
+            self.path.display(),
+            self.method,
+            self.request_path
+        )?;
+        if let Some(body) = &self.body {
+            write!(f, " with body {}", body)?;
+        }
+        Ok(())
+    }
+}
+impl Error for CassetteError {}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A request body as JSON with its keys sorted, or a string if it isn't JSON
+fn normalize(body: Option<&[u8]>) -> Option<Value> {
+    let body = body.filter(|body| !body.is_empty())?;
+    Some(
+        serde_json::from_slice(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned())),
+    )
+}
+
+fn redact(value: Value, redact_token: &impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(text) => Value::String(redact_token(&text)),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| redact(item, redact_token))
+            .collect(),
+        Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, value)| (key, redact(value, redact_token)))
+            .collect(),
+        value => value,
+    }
+}
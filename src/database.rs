@@ -1,27 +1,34 @@
-use crate::{rich_text::RichTextObject, time::Time};
+use crate::{page::Parent, rich_text::RichTextObject, time::Time};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DatabaseObject {
     pub id: String,
     pub created_time: Time,
     pub last_edited_time: Time,
     pub properties: HashMap<String, DatabaseProperty>,
     pub title: Vec<RichTextObject>,
+    /// Newer versions of the API send where the database lives, it's also filled in for inline
+    /// databases found through their `child_database` block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Parent>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum DatabaseProperty {
     Title {
         id: String,
-        title: Option<HashMap<(), ()>>,
+        title: Empty,
     },
     RichText {
         id: String,
-        rich_text: Option<HashMap<(), ()>>,
+        rich_text: Empty,
     },
     Number {
         id: String,
@@ -35,30 +42,33 @@ pub enum DatabaseProperty {
         id: String,
         multi_select: MultiSelect,
     },
-    Date {},
+    Date {
+        id: String,
+        date: Empty,
+    },
     People {
         id: String,
-        people: Option<HashMap<(), ()>>,
+        people: Empty,
     },
     Files {
         id: String,
-        files: Option<HashMap<(), ()>>,
+        files: Empty,
     },
     Checkbox {
         id: String,
-        checkbox: Option<HashMap<(), ()>>,
+        checkbox: Empty,
     },
     Url {
         id: String,
-        url: Option<HashMap<(), ()>>,
+        url: Empty,
     },
     Email {
         id: String,
-        email: Option<HashMap<(), ()>>,
+        email: Empty,
     },
     PhoneNumber {
         id: String,
-        phone_number: Option<HashMap<(), ()>>,
+        phone_number: Empty,
     },
     Formula {
         id: String,
@@ -66,35 +76,146 @@ pub enum DatabaseProperty {
     },
     Relation {
         id: String,
-        relation: Option<HashMap<(), ()>>,
+        relation: Relation,
     },
     Rollup {
         id: String,
-        rollup: Option<HashMap<(), ()>>,
+        rollup: Rollup,
     },
     CreatedTime {
         id: String,
-        created_time: Option<HashMap<(), ()>>,
+        created_time: Empty,
     },
     CreatedBy {
         id: String,
-        created_by: Option<HashMap<(), ()>>,
+        created_by: Empty,
     },
     LastEditedTime {
         id: String,
-        last_edited_time: Option<HashMap<(), ()>>,
+        last_edited_time: Empty,
     },
     LastEditedBy {
         id: String,
-        last_edited_by: Option<HashMap<(), ()>>,
+        last_edited_by: Empty,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
     },
 }
+crate::unknown::tagged_enum!(
+    DatabaseProperty,
+    "type",
+    [
+        "title",
+        "rich_text",
+        "number",
+        "select",
+        "multi_select",
+        "date",
+        "people",
+        "files",
+        "checkbox",
+        "url",
+        "email",
+        "phone_number",
+        "formula",
+        "relation",
+        "rollup",
+        "created_time",
+        "created_by",
+        "last_edited_time",
+        "last_edited_by"
+    ]
+);
+
+impl DatabaseProperty {
+    /// The name Notion uses for this property's type e.g. `multi_select`
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::Title { .. } => "title",
+            Self::RichText { .. } => "rich_text",
+            Self::Number { .. } => "number",
+            Self::Select { .. } => "select",
+            Self::MultiSelect { .. } => "multi_select",
+            Self::Date { .. } => "date",
+            Self::People { .. } => "people",
+            Self::Files { .. } => "files",
+            Self::Checkbox { .. } => "checkbox",
+            Self::Url { .. } => "url",
+            Self::Email { .. } => "email",
+            Self::PhoneNumber { .. } => "phone_number",
+            Self::Formula { .. } => "formula",
+            Self::Relation { .. } => "relation",
+            Self::Rollup { .. } => "rollup",
+            Self::CreatedTime { .. } => "created_time",
+            Self::CreatedBy { .. } => "created_by",
+            Self::LastEditedTime { .. } => "last_edited_time",
+            Self::LastEditedBy { .. } => "last_edited_by",
+            Self::Unknown { type_name, .. } => type_name,
+        }
+    }
+
+    /// The configuration to send to Notion to create a property like this one
+    pub fn to_input(&self) -> Option<DatabasePropertyInput> {
+        let options = |options: Vec<(String, DatabaseColor)>| SelectInput {
+            options: options
+                .into_iter()
+                .map(|(name, color)| SelectOptionsInput {
+                    name,
+                    color: Some(color),
+                })
+                .collect(),
+        };
+        Some(match self.clone() {
+            Self::Title { .. } => DatabasePropertyInput::Title(Empty {}),
+            Self::RichText { .. } => DatabasePropertyInput::RichText(Empty {}),
+            Self::Number { number, .. } => DatabasePropertyInput::Number(number),
+            Self::Select { select, .. } => DatabasePropertyInput::Select(options(
+                select
+                    .options
+                    .into_iter()
+                    .map(|o| (o.name, o.color))
+                    .collect(),
+            )),
+            Self::MultiSelect { multi_select, .. } => DatabasePropertyInput::MultiSelect(options(
+                multi_select
+                    .options
+                    .into_iter()
+                    .map(|o| (o.name, o.color))
+                    .collect(),
+            )),
+            Self::Date { .. } => DatabasePropertyInput::Date(Empty {}),
+            Self::People { .. } => DatabasePropertyInput::People(Empty {}),
+            Self::Files { .. } => DatabasePropertyInput::Files(Empty {}),
+            Self::Checkbox { .. } => DatabasePropertyInput::Checkbox(Empty {}),
+            Self::Url { .. } => DatabasePropertyInput::Url(Empty {}),
+            Self::Email { .. } => DatabasePropertyInput::Email(Empty {}),
+            Self::PhoneNumber { .. } => DatabasePropertyInput::PhoneNumber(Empty {}),
+            Self::Formula { formula, .. } => DatabasePropertyInput::Formula(formula),
+            Self::Relation { relation, .. } => DatabasePropertyInput::Relation(relation),
+            Self::Rollup { rollup, .. } => DatabasePropertyInput::Rollup(rollup),
+            Self::CreatedTime { .. } => DatabasePropertyInput::CreatedTime(Empty {}),
+            Self::CreatedBy { .. } => DatabasePropertyInput::CreatedBy(Empty {}),
+            Self::LastEditedTime { .. } => DatabasePropertyInput::LastEditedTime(Empty {}),
+            Self::LastEditedBy { .. } => DatabasePropertyInput::LastEditedBy(Empty {}),
+            Self::Unknown { .. } => return None,
+        })
+    }
+}
+
+/// Configuration for property types that don't have any, Notion sends these as `{}`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Empty {}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Number {
     pub format: NumberFormat,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum NumberFormat {
     Number,
@@ -108,28 +229,45 @@ pub enum NumberFormat {
     Rupee,
     Won,
     Yuan,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Select {
     pub options: Vec<SelectOptions>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SelectOptions {
     pub name: String,
     pub id: String,
     pub color: DatabaseColor,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MultiSelect {
     pub options: Vec<MultiSelectOptions>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MultiSelectOptions {
     pub name: String,
     pub id: String,
     pub color: DatabaseColor,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum DatabaseColor {
     Default,
@@ -142,28 +280,46 @@ pub enum DatabaseColor {
     Purple,
     Pink,
     Red,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Expression {
     pub expression: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Relation {
     pub database_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub synced_property_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub synced_property_id: Option<String>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rollup {
     pub relation_property_name: String,
-    pub relation_property_id: String,
+    /// Notion always sends the ids, when creating a rollup the names are enough
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation_property_id: Option<String>,
     pub rollup_property_name: String,
-    pub rollup_property_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollup_property_id: Option<String>,
     pub function: Function,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Function {
     CountAll,
@@ -179,9 +335,251 @@ pub enum Function {
     Min,
     Max,
     Range,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+
+/// The configuration of a single property when creating or changing a database schema. This
+/// serializes to the shape Notion expects e.g. `{ "number": { "format": "dollar" } }`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabasePropertyInput {
+    Title(Empty),
+    RichText(Empty),
+    Number(Number),
+    Select(SelectInput),
+    MultiSelect(SelectInput),
+    Date(Empty),
+    People(Empty),
+    Files(Empty),
+    Checkbox(Empty),
+    Url(Empty),
+    Email(Empty),
+    PhoneNumber(Empty),
+    Formula(Expression),
+    Relation(Relation),
+    Rollup(Rollup),
+    CreatedTime(Empty),
+    CreatedBy(Empty),
+    LastEditedTime(Empty),
+    LastEditedBy(Empty),
+}
+impl DatabasePropertyInput {
+    /// The name Notion uses for this property's type e.g. `multi_select`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Title(_) => "title",
+            Self::RichText(_) => "rich_text",
+            Self::Number(_) => "number",
+            Self::Select(_) => "select",
+            Self::MultiSelect(_) => "multi_select",
+            Self::Date(_) => "date",
+            Self::People(_) => "people",
+            Self::Files(_) => "files",
+            Self::Checkbox(_) => "checkbox",
+            Self::Url(_) => "url",
+            Self::Email(_) => "email",
+            Self::PhoneNumber(_) => "phone_number",
+            Self::Formula(_) => "formula",
+            Self::Relation(_) => "relation",
+            Self::Rollup(_) => "rollup",
+            Self::CreatedTime(_) => "created_time",
+            Self::CreatedBy(_) => "created_by",
+            Self::LastEditedTime(_) => "last_edited_time",
+            Self::LastEditedBy(_) => "last_edited_by",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelectInput {
+    pub options: Vec<SelectOptionsInput>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelectOptionsInput {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<DatabaseColor>,
+}
+
+/// Builder for the properties of a new database
+#[derive(Serialize, Debug, Default)]
+#[serde(transparent)]
+pub struct DatabaseSchema {
+    properties: HashMap<String, DatabasePropertyInput>,
+}
+
+impl DatabaseSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn property(mut self, name: impl Into<String>, property: DatabasePropertyInput) -> Self {
+        self.properties.insert(name.into(), property);
+        self
+    }
+
+    pub fn title(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Title(Empty {}))
+    }
+
+    pub fn rich_text(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::RichText(Empty {}))
+    }
+
+    pub fn number(self, name: impl Into<String>, format: NumberFormat) -> Self {
+        self.property(
+            name,
+            DatabasePropertyInput::Number(Number {
+                format,
+                extra: HashMap::new(),
+            }),
+        )
+    }
+
+    pub fn select(self, name: impl Into<String>, options: Vec<SelectOptionsInput>) -> Self {
+        self.property(name, DatabasePropertyInput::Select(SelectInput { options }))
+    }
+
+    pub fn multi_select(self, name: impl Into<String>, options: Vec<SelectOptionsInput>) -> Self {
+        self.property(
+            name,
+            DatabasePropertyInput::MultiSelect(SelectInput { options }),
+        )
+    }
+
+    pub fn date(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Date(Empty {}))
+    }
+
+    pub fn people(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::People(Empty {}))
+    }
+
+    pub fn files(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Files(Empty {}))
+    }
+
+    pub fn checkbox(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Checkbox(Empty {}))
+    }
+
+    pub fn url(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Url(Empty {}))
+    }
+
+    pub fn email(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::Email(Empty {}))
+    }
+
+    pub fn phone_number(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::PhoneNumber(Empty {}))
+    }
+
+    pub fn formula(self, name: impl Into<String>, expression: impl Into<String>) -> Self {
+        self.property(
+            name,
+            DatabasePropertyInput::Formula(Expression {
+                expression: expression.into(),
+                extra: HashMap::new(),
+            }),
+        )
+    }
+
+    pub fn relation(self, name: impl Into<String>, database_id: impl Into<String>) -> Self {
+        self.property(
+            name,
+            DatabasePropertyInput::Relation(Relation {
+                database_id: database_id.into(),
+                synced_property_name: None,
+                synced_property_id: None,
+                extra: HashMap::new(),
+            }),
+        )
+    }
+
+    pub fn rollup(self, name: impl Into<String>, rollup: Rollup) -> Self {
+        self.property(name, DatabasePropertyInput::Rollup(rollup))
+    }
+
+    pub fn created_time(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::CreatedTime(Empty {}))
+    }
+
+    pub fn created_by(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::CreatedBy(Empty {}))
+    }
+
+    pub fn last_edited_time(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::LastEditedTime(Empty {}))
+    }
+
+    pub fn last_edited_by(self, name: impl Into<String>) -> Self {
+        self.property(name, DatabasePropertyInput::LastEditedBy(Empty {}))
+    }
+}
+
+/// Builder for changes to an existing database schema. Properties are keyed by their current
+/// name or id, a `None` entry removes the property.
+#[derive(Serialize, Debug, Default)]
+#[serde(transparent)]
+pub struct DatabaseSchemaChanges {
+    properties: HashMap<String, Option<DatabasePropertyUpdate>>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct DatabasePropertyUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub property: Option<DatabasePropertyInput>,
+}
+
+impl DatabaseSchemaChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new property or change the type/configuration of an existing one
+    pub fn property(mut self, name: impl Into<String>, property: DatabasePropertyInput) -> Self {
+        self.properties
+            .entry(name.into())
+            .or_insert_with(|| Some(DatabasePropertyUpdate::default()))
+            .get_or_insert_with(DatabasePropertyUpdate::default)
+            .property = Some(property);
+        self
+    }
+
+    pub fn rename(mut self, name: impl Into<String>, new_name: impl Into<String>) -> Self {
+        self.properties
+            .entry(name.into())
+            .or_insert_with(|| Some(DatabasePropertyUpdate::default()))
+            .get_or_insert_with(DatabasePropertyUpdate::default)
+            .name = Some(new_name.into());
+        self
+    }
+
+    pub fn remove(mut self, name: impl Into<String>) -> Self {
+        self.properties.insert(name.into(), None);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
 }
 
-#[derive(Serialize, Debug)]
+/// Date and formula filters are boxed, they're much larger than the others since `Time` can hold
+/// a date or a time
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
 pub enum QueryDatabaseFilter {
     Or {
         or: Vec<QueryDatabaseFilter>,
@@ -207,7 +605,7 @@ pub enum QueryDatabaseFilter {
     },
     Phone {
         property: String,
-        fhone: TextFilter,
+        phone_number: TextFilter,
     },
     Number {
         property: String,
@@ -215,7 +613,7 @@ pub enum QueryDatabaseFilter {
     },
     CheckBoxFilter {
         property: String,
-        check_box: CheckBoxFilter,
+        checkbox: CheckBoxFilter,
     },
     SelectFilter {
         property: String,
@@ -227,19 +625,19 @@ pub enum QueryDatabaseFilter {
     },
     Date {
         property: String,
-        date: DateFilter,
+        date: Box<DateFilter>,
     },
     CreatedTime {
         property: String,
-        created_time: DateFilter,
+        created_time: Box<DateFilter>,
     },
     LastEditedTime {
         property: String,
-        created_time: DateFilter,
+        last_edited_time: Box<DateFilter>,
     },
-    PersonDate {
+    People {
         property: String,
-        date: PersonFilter,
+        people: PersonFilter,
     },
     CreatedBy {
         property: String,
@@ -247,7 +645,7 @@ pub enum QueryDatabaseFilter {
     },
     LastEditedBy {
         property: String,
-        created_by: PersonFilter,
+        last_edited_by: PersonFilter,
     },
     Files {
         property: String,
@@ -259,11 +657,11 @@ pub enum QueryDatabaseFilter {
     },
     Formula {
         property: String,
-        formula: FormulaFilter,
+        formula: Box<FormulaFilter>,
     },
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct TextFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub equals: Option<String>,
@@ -283,7 +681,7 @@ pub struct TextFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct NumberFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub equals: Option<String>,
@@ -302,7 +700,7 @@ pub struct NumberFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_not_empty: Option<bool>,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct CheckBoxFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub equals: Option<String>,
@@ -310,7 +708,7 @@ pub struct CheckBoxFilter {
     pub does_not_equal: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SelectFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub equals: Option<String>,
@@ -322,7 +720,7 @@ pub struct SelectFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct MultiSelectFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contains: Option<String>,
@@ -334,7 +732,7 @@ pub struct MultiSelectFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct DateFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub equals: Option<Time>,
@@ -364,7 +762,7 @@ pub struct DateFilter {
     pub next_year: Option<HashMap<(), ()>>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct PersonFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contains: Option<String>,
@@ -376,7 +774,7 @@ pub struct PersonFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct FilesFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contains: Option<String>,
@@ -388,7 +786,7 @@ pub struct FilesFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct RelationFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contains: Option<String>,
@@ -400,7 +798,7 @@ pub struct RelationFilter {
     pub is_not_empty: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct FormulaFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<TextFilter>,
@@ -412,7 +810,7 @@ pub struct FormulaFilter {
     pub date: Option<DateFilter>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct QueryDatabaseSort {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub property: Option<String>,
@@ -422,14 +820,14 @@ pub struct QueryDatabaseSort {
     pub direction: Option<Direction>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryDatabaseTime {
     CreatedTime,
     LastEditedTime,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
     Ascending,
@@ -0,0 +1,474 @@
+//! What changed between two versions of a page's properties or block tree.
+//!
+//! Property values are compared by meaning rather than by their JSON. Rich text split into spans
+//! differently but with the same content and formatting is equal, dates are compared as instants,
+//! and the order of multi-select options, relations and people doesn't matter.
+//!
+//! Blocks are matched by id, and blocks that can't be matched that way are matched by content
+//! among their siblings. A matched block is updated if its content differs and moved if it has a
+//! new parent or its position among the siblings that weren't moved changed.
+use crate::{
+    block::{BlockObject, BlockTree},
+    export::render,
+    page::{PageObject, PageProperty},
+    rich_text::RichTextObject,
+    time::Time,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PropertyChange {
+    pub name: String,
+    pub old: Option<PageProperty>,
+    pub new: Option<PageProperty>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PageDiff {
+    pub id: String,
+    /// The old and new `archived` flag if it changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<(bool, bool)>,
+    pub properties: Vec<PropertyChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BlockDiff {
+    pub ops: Vec<BlockOp>,
+}
+
+/// Where a block is, `parent` is `None` for the top level
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Position {
+    pub parent: Option<String>,
+    pub index: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "op")]
+#[serde(rename_all = "snake_case")]
+pub enum BlockOp {
+    /// A new block, its children are part of the tree rather than separate inserts
+    Insert { position: Position, tree: BlockTree },
+    /// A removed block along with its children
+    Delete { position: Position, tree: BlockTree },
+    Update {
+        id: Option<String>,
+        old: BlockObject,
+        new: BlockObject,
+    },
+    Move {
+        id: Option<String>,
+        from: Position,
+        to: Position,
+    },
+}
+
+/// Compare two versions of the same page
+pub fn diff_pages(old: &PageObject, new: &PageObject) -> PageDiff {
+    PageDiff {
+        id: new.id.clone(),
+        archived: Some((old.archived, new.archived)).filter(|(old, new)| old != new),
+        properties: diff_properties(&old.properties, &new.properties),
+    }
+}
+
+/// Properties that were added, removed or whose values differ, sorted by name
+pub fn diff_properties(
+    old: &HashMap<String, PageProperty>,
+    new: &HashMap<String, PageProperty>,
+) -> Vec<PropertyChange> {
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|name| {
+            let (old, new) = (old.get(name), new.get(name));
+            match (old, new) {
+                (Some(old), Some(new)) if same_value(old, new) => None,
+                _ => Some(PropertyChange {
+                    name: name.clone(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Whether two property values mean the same thing, property ids are ignored
+pub fn same_value(old: &PageProperty, new: &PageProperty) -> bool {
+    use PageProperty::*;
+    match (old, new) {
+        (Title { title: old, .. }, Title { title: new, .. })
+        | (RichText { rich_text: old, .. }, RichText { rich_text: new, .. }) => same_text(old, new),
+        (Date { date: old, .. }, Date { date: new, .. }) => match (old, new) {
+            (Some(old), Some(new)) => {
+                same_time(&old.start, &new.start)
+                    && match (&old.end, &new.end) {
+                        (Some(old), Some(new)) => same_time(old, new),
+                        (old, new) => old.is_none() && new.is_none(),
+                    }
+            }
+            (old, new) => old.is_none() && new.is_none(),
+        },
+        (
+            MultiSelect {
+                multi_select: old, ..
+            },
+            MultiSelect {
+                multi_select: new, ..
+            },
+        ) => set(old.iter().map(|o| &o.name)) == set(new.iter().map(|o| &o.name)),
+        (Select { select: old, .. }, Select { select: new, .. }) => {
+            old.as_ref().map(|o| &o.name) == new.as_ref().map(|o| &o.name)
+        }
+        (Relation { relation: old, .. }, Relation { relation: new, .. }) => {
+            set(old.iter().map(|r| &r.id)) == set(new.iter().map(|r| &r.id))
+        }
+        (People { people: old, .. }, People { people: new, .. }) => {
+            set(old.iter().map(|u| u.id())) == set(new.iter().map(|u| u.id()))
+        }
+        (old, new) => without_id(old) == without_id(new),
+    }
+}
+
+/// Compare two block trees, e.g. the same page fetched at different times
+pub fn diff_blocks(old: &[BlockTree], new: &[BlockTree]) -> BlockDiff {
+    let old_nodes = flatten(old);
+    let new_nodes = flatten(new);
+
+    // Old node index to new node index
+    let mut matched = HashMap::new();
+    let mut matched_new = HashMap::new();
+    let new_ids = new_nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| Some((node.tree.block.id()?, index)))
+        .collect::<HashMap<_, _>>();
+    for (index, node) in old_nodes.iter().enumerate() {
+        if let Some(&new_index) = node.tree.block.id().and_then(|id| new_ids.get(id)) {
+            matched.insert(index, new_index);
+            matched_new.insert(new_index, index);
+        }
+    }
+    for (index, node) in old_nodes.iter().enumerate() {
+        if matched.contains_key(&index) {
+            continue;
+        }
+        let wanted = content(&node.tree.block);
+        let found = new_nodes.iter().enumerate().find(|(new_index, new)| {
+            !matched_new.contains_key(new_index)
+                && same_parent(node, new, &matched)
+                && new.tree.block.id().is_none()
+                && wanted == content(&new.tree.block)
+        });
+        if let Some((new_index, _)) = found {
+            matched.insert(index, new_index);
+            matched_new.insert(new_index, index);
+        }
+    }
+
+    let mut ops = Vec::new();
+    for (index, node) in old_nodes.iter().enumerate() {
+        let inside_deleted = node.parent.is_some_and(|p| !matched.contains_key(&p));
+        if !matched.contains_key(&index) && !inside_deleted {
+            ops.push(BlockOp::Delete {
+                position: node.position.clone(),
+                tree: node.tree.clone(),
+            });
+        }
+    }
+
+    // Blocks that stayed under the same parent only count as moved if they aren't part of the
+    // longest run that kept its order
+    let mut siblings = HashMap::<Option<usize>, Vec<(usize, usize)>>::new();
+    for (&old_index, &new_index) in &matched {
+        let (old, new) = (&old_nodes[old_index], &new_nodes[new_index]);
+        if same_parent(old, new, &matched) {
+            siblings
+                .entry(new.parent)
+                .or_default()
+                .push((new.position.index, old.position.index));
+        }
+    }
+    let mut in_order = BTreeSet::new();
+    for (parent, mut pairs) in siblings {
+        pairs.sort();
+        let old_order = pairs.iter().map(|(_, old)| *old).collect::<Vec<_>>();
+        for old_index in longest_increasing(&old_order) {
+            in_order.insert((parent, old_index));
+        }
+    }
+
+    let mut pairs = matched
+        .iter()
+        .map(|(&old, &new)| (old, new))
+        .collect::<Vec<_>>();
+    pairs.sort_by_key(|(_, new_index)| *new_index);
+    for (old_index, new_index) in pairs {
+        let (old, new) = (&old_nodes[old_index], &new_nodes[new_index]);
+        if content(&old.tree.block) != content(&new.tree.block) {
+            ops.push(BlockOp::Update {
+                id: new.tree.block.id().map(str::to_string),
+                old: old.tree.block.clone(),
+                new: new.tree.block.clone(),
+            });
+        }
+        let kept =
+            same_parent(old, new, &matched) && in_order.contains(&(new.parent, old.position.index));
+        if !kept {
+            ops.push(BlockOp::Move {
+                id: new.tree.block.id().map(str::to_string),
+                from: old.position.clone(),
+                to: new.position.clone(),
+            });
+        }
+    }
+
+    for (index, node) in new_nodes.iter().enumerate() {
+        let inside_inserted = node.parent.is_some_and(|p| !matched_new.contains_key(&p));
+        if !matched_new.contains_key(&index) && !inside_inserted {
+            ops.push(BlockOp::Insert {
+                position: node.position.clone(),
+                tree: node.tree.clone(),
+            });
+        }
+    }
+
+    BlockDiff { ops }
+}
+
+impl PageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.archived.is_none() && self.properties.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl BlockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl fmt::Display for PageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((_, archived)) = self.archived {
+            writeln!(f, "{}", if archived { "archived" } else { "unarchived" })?;
+        }
+        for change in &self.properties {
+            match (&change.old, &change.new) {
+                (None, Some(new)) => writeln!(f, "+ {}: {:?}", change.name, render(new))?,
+                (Some(old), None) => writeln!(f, "- {}: {:?}", change.name, render(old))?,
+                (Some(old), Some(new)) => writeln!(
+                    f,
+                    "~ {}: {:?} -> {:?}",
+                    change.name,
+                    render(old),
+                    render(new)
+                )?,
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            match op {
+                BlockOp::Insert { position, tree } => {
+                    writeln!(f, "+ {} {}", position, describe(&tree.block))?
+                }
+                BlockOp::Delete { position, tree } => {
+                    writeln!(f, "- {} {}", position, describe(&tree.block))?
+                }
+                BlockOp::Update { old, new, .. } => {
+                    writeln!(f, "~ {} -> {}", describe(old), describe(new))?
+                }
+                BlockOp::Move { from, to, .. } => writeln!(f, "> {} -> {}", from, to)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.parent {
+            Some(parent) => write!(f, "{}[{}]", parent, self.index),
+            None => write!(f, "[{}]", self.index),
+        }
+    }
+}
+
+struct Node<'a> {
+    tree: &'a BlockTree,
+    /// Index of the parent node
+    parent: Option<usize>,
+    position: Position,
+}
+
+/// Whether the old and new node's parents were matched to each other, or both are at the top level.
+/// Parents are compared by node rather than by id so that blocks without ids still count.
+fn same_parent(old: &Node, new: &Node, matched: &HashMap<usize, usize>) -> bool {
+    match (old.parent, new.parent) {
+        (Some(old), Some(new)) => matched.get(&old) == Some(&new),
+        (old, new) => old.is_none() && new.is_none(),
+    }
+}
+
+fn flatten(trees: &[BlockTree]) -> Vec<Node<'_>> {
+    fn walk<'a>(trees: &'a [BlockTree], parent: Option<usize>, nodes: &mut Vec<Node<'a>>) {
+        let parent_id = parent.and_then(|p| nodes[p].tree.block.id().map(str::to_string));
+        for (index, tree) in trees.iter().enumerate() {
+            nodes.push(Node {
+                tree,
+                parent,
+                position: Position {
+                    parent: parent_id.clone(),
+                    index,
+                },
+            });
+            let this = nodes.len() - 1;
+            walk(&tree.children, Some(this), nodes);
+        }
+    }
+    let mut nodes = Vec::new();
+    walk(trees, None, &mut nodes);
+    nodes
+}
+
+/// A block's JSON without the fields that change on every edit, with its text normalized
+fn content(block: &BlockObject) -> Value {
+    let mut value = serde_json::to_value(block).unwrap_or(Value::Null);
+    if let Value::Object(fields) = &mut value {
+        for field in &["id", "created_time", "last_edited_time", "has_children"] {
+            fields.remove(*field);
+        }
+    }
+    if let (Some(text), Some(kind)) = (block.text(), block_kind(block)) {
+        if let Some(inner) = value.get_mut(kind.as_str()).and_then(Value::as_object_mut) {
+            inner.insert("text".to_string(), normalize_text(text));
+        }
+    }
+    value
+}
+
+fn block_kind(block: &BlockObject) -> Option<String> {
+    serde_json::to_value(block)
+        .ok()?
+        .get("type")?
+        .as_str()
+        .map(str::to_string)
+}
+
+pub(crate) fn describe(block: &BlockObject) -> String {
+    let kind = block_kind(block).unwrap_or_else(|| "block".to_string());
+    match block.text() {
+        Some(text) => format!(
+            "{} {:?}",
+            kind,
+            text.iter()
+                .map(RichTextObject::plain_text)
+                .collect::<String>()
+        ),
+        None => kind,
+    }
+}
+
+fn same_text(old: &[RichTextObject], new: &[RichTextObject]) -> bool {
+    normalize_text(old) == normalize_text(new)
+}
+
+/// Adjacent text spans with the same formatting are merged so that how Notion happened to split
+/// the text doesn't matter. Spans written by hand often leave out `plain_text` and `href`, which
+/// Notion fills in from the content and link, so those are ignored.
+pub(crate) fn normalize_text(text: &[RichTextObject]) -> Value {
+    let mut spans: Vec<(String, Value)> = Vec::new();
+    for span in text {
+        let (plain, style) = match span {
+            RichTextObject::Text {
+                plain_text,
+                annotations,
+                text,
+                ..
+            } => (
+                if plain_text.is_empty() {
+                    text.content.clone()
+                } else {
+                    plain_text.clone()
+                },
+                serde_json::json!({ "annotations": annotations, "link": text.link }),
+            ),
+            other => {
+                // Mentions and equations are compared whole
+                let mut value = serde_json::to_value(other).unwrap_or(Value::Null);
+                if let Value::Object(fields) = &mut value {
+                    fields.remove("plain_text");
+                    fields.remove("href");
+                }
+                spans.push((other.plain_text().to_string(), value));
+                continue;
+            }
+        };
+        match spans.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.push_str(&plain),
+            _ => spans.push((plain, style)),
+        }
+    }
+    serde_json::to_value(spans).unwrap_or(Value::Null)
+}
+
+/// Dates are only the same as dates, and times as the same instant whatever their offset
+fn same_time(old: &Time, new: &Time) -> bool {
+    old.is_date() == new.is_date() && old.to_utc() == new.to_utc()
+}
+
+fn set<T: Ord>(items: impl Iterator<Item = T>) -> BTreeSet<T> {
+    items.collect()
+}
+
+fn without_id(property: &PageProperty) -> Value {
+    let mut value = serde_json::to_value(property).unwrap_or(Value::Null);
+    if let Value::Object(fields) = &mut value {
+        fields.remove("id");
+    }
+    value
+}
+
+/// The values of the longest strictly increasing subsequence
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    let mut lengths = vec![1; values.len()];
+    let mut previous = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut end = (0..values.len()).max_by_key(|&i| lengths[i]);
+    let mut sequence = Vec::new();
+    while let Some(i) = end {
+        sequence.push(values[i]);
+        end = previous[i];
+    }
+    sequence.reverse();
+    sequence
+}
@@ -0,0 +1,137 @@
+//! Copying a page along with its child pages and inline databases. The whole tree is read first
+//! and then written with the same code [`crate::restore`] uses, so mentions, page links and
+//! relations between pages in the tree point at the copies.
+use crate::{
+    block::{BlockObject, BlockTree},
+    page::{PageObject, PageProperty, Parent},
+    restore::{write_snapshot, RestoreState, SkippedBlock, Snapshot},
+    rich_text::RichTextObject,
+    BoxFuture, Client,
+};
+use std::{collections::BTreeMap, error::Error};
+
+#[derive(Default)]
+pub struct DuplicateOptions<'a> {
+    /// Write the copy with another client, e.g. one with a token for a different workspace.
+    /// References to anything outside of the copied tree are removed in that case, see
+    /// [`Snapshot::strip_external`].
+    pub target: Option<&'a Client>,
+    /// Give the copy of the top page a new title
+    pub title: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Duplicate {
+    /// The id of the copy of the top page
+    pub page_id: String,
+    /// Every old page and database id mapped to its copy
+    pub ids: BTreeMap<String, String>,
+    /// Blocks that couldn't be copied, by the page they're on
+    pub skipped: BTreeMap<String, Vec<SkippedBlock>>,
+}
+
+impl Client {
+    /// Copy the page `source`, everything in it and every page and database nested inside it
+    /// under the page `new_parent`
+    pub async fn duplicate_page(
+        &self,
+        source: &str,
+        new_parent: &str,
+        options: DuplicateOptions<'_>,
+    ) -> Result<Duplicate, Box<dyn Error>> {
+        let mut snapshot = Snapshot::default();
+        let page = self.get_page(source).await?.into_page()?;
+        read_page(self, page, &mut snapshot).await?;
+
+        if let Some(new_title) = options.title {
+            let top = snapshot
+                .pages
+                .get_mut(source)
+                .ok_or("The source page wasn't read")?;
+            for property in top.properties.values_mut() {
+                if let PageProperty::Title { title, .. } = property {
+                    *title = vec![RichTextObject::text(new_title.clone())];
+                }
+            }
+        }
+
+        let target = match options.target {
+            Some(target) => {
+                snapshot.strip_external();
+                target
+            }
+            None => self,
+        };
+
+        let mut state = RestoreState {
+            parent: new_parent.to_string(),
+            ..RestoreState::default()
+        };
+        write_snapshot(target, &snapshot, &mut state, |_| Ok(())).await?;
+        Ok(Duplicate {
+            page_id: state.pages[source].clone(),
+            ids: state.ids(),
+            skipped: state.skipped,
+        })
+    }
+}
+
+/// Add a page and its block tree to the snapshot, along with the child pages and databases
+/// found in it
+fn read_page<'a>(
+    client: &'a Client,
+    page: PageObject,
+    snapshot: &'a mut Snapshot,
+) -> BoxFuture<'a, Result<(), Box<dyn Error>>> {
+    Box::pin(async move {
+        if snapshot.pages.contains_key(&page.id) {
+            return Ok(());
+        }
+        let id = page.id.clone();
+        let trees = client.get_block_tree(&id).await?;
+        snapshot.pages.insert(id.clone(), page);
+
+        let mut nested = Vec::new();
+        nested_blocks(&trees, &mut nested);
+        snapshot.blocks.insert(id.clone(), trees);
+
+        for block in nested {
+            match block {
+                BlockObject::ChildPage { id: child, .. } => {
+                    if snapshot.pages.contains_key(&child) {
+                        continue;
+                    }
+                    let child = client.get_page(&child).await?.into_page()?;
+                    read_page(client, child, snapshot).await?;
+                }
+                BlockObject::ChildDatabase { id: child, .. } => {
+                    if snapshot.databases.contains_key(&child) {
+                        continue;
+                    }
+                    let mut database = client.get_database(&child).await?.into_database()?;
+                    database.parent = Some(Parent::PageId {
+                        page_id: id.clone(),
+                    });
+                    snapshot.databases.insert(child.clone(), database);
+                    for row in client.query_database_all(&child, None, None).await? {
+                        read_page(client, row.into_page()?, snapshot).await?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Child page and database blocks anywhere in the trees, e.g. inside a toggle
+fn nested_blocks(trees: &[BlockTree], nested: &mut Vec<BlockObject>) {
+    for tree in trees {
+        match tree.block {
+            BlockObject::ChildPage { .. } | BlockObject::ChildDatabase { .. } => {
+                nested.push(tree.block.clone())
+            }
+            _ => nested_blocks(&tree.children, nested),
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! The request building shared by [`Client`](crate::Client) and `blocking::Client`. Every API
+//! call is described once here as an [`Endpoint`] and [`ClientCore`] turns it into a
+//! [`Request`] and the [`Response`] back into a [`NotionObject`], so the clients only have to
+//! send it.
+use crate::{
+    block::{BlockObjectInput, BlockObjectUpdate},
+    cassette::Cassette,
+    database::{DatabaseSchema, DatabaseSchemaChanges, QueryDatabaseFilter, QueryDatabaseSort},
+    middleware::Middleware,
+    page::{PagePropertyValue, Parent},
+    rich_text::RichTextObject,
+    search::{SearchFilter, SearchSort},
+    transport::{Method, Request, Response},
+    NotionObject,
+};
+use serde::Serialize;
+use std::{collections::HashMap, error::Error, sync::Arc, time::Duration};
+
+pub(crate) const BASE_URL: &str = "https://api.notion.com/v1/";
+pub(crate) const NOTION_VERSION: &str = "2021-05-13";
+
+/// A call to the API before the base url and headers are added
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoint {
+    /// The client method that makes this call e.g. `query_database`
+    pub name: &'static str,
+    pub method: Method,
+    /// The url after the base url e.g. `databases/<id>/query`
+    pub path: String,
+    pub body: Option<Vec<u8>>,
+}
+
+/// Building the body can fail, which is reported when the request is sent
+pub(crate) type Built = Result<Endpoint, serde_json::Error>;
+
+fn get(name: &'static str, path: String) -> Built {
+    Ok(Endpoint {
+        name,
+        method: Method::Get,
+        path,
+        body: None,
+    })
+}
+
+fn with_body(name: &'static str, method: Method, path: String, body: &impl Serialize) -> Built {
+    Ok(Endpoint {
+        name,
+        method,
+        path,
+        body: Some(serde_json::to_vec(body)?),
+    })
+}
+
+fn paging(start_cursor: Option<String>, page_size: Option<i32>) -> String {
+    match (start_cursor, page_size) {
+        (Some(c), Some(p)) => format!("?start_cursor={}&page_size={}", c, p),
+        (Some(c), None) => format!("?start_cursor={}", c),
+        (None, Some(p)) => format!("?page_size={}", p),
+        (None, None) => String::new(),
+    }
+}
+
+pub(crate) fn get_database(id: &str) -> Built {
+    get("get_database", format!("databases/{}", id))
+}
+
+pub(crate) fn list_databases(start_cursor: Option<String>, page_size: Option<i32>) -> Built {
+    get(
+        "list_databases",
+        format!("databases{}", paging(start_cursor, page_size)),
+    )
+}
+
+pub(crate) fn get_user(id: &str) -> Built {
+    get("get_user", format!("users/{}", id))
+}
+
+pub(crate) fn list_users(start_cursor: Option<String>, page_size: Option<i32>) -> Built {
+    get(
+        "list_users",
+        format!("users{}", paging(start_cursor, page_size)),
+    )
+}
+
+pub(crate) fn get_block_children(
+    id: &str,
+    start_cursor: Option<String>,
+    page_size: Option<i32>,
+) -> Built {
+    get(
+        "get_block_children",
+        format!("blocks/{}/children{}", id, paging(start_cursor, page_size)),
+    )
+}
+
+pub(crate) fn get_block(id: &str) -> Built {
+    get("get_block", format!("blocks/{}", id))
+}
+
+pub(crate) fn get_page(id: &str) -> Built {
+    get("get_page", format!("pages/{}", id))
+}
+
+pub(crate) fn update_page_properties(
+    id: &str,
+    properties: HashMap<String, PagePropertyValue>,
+) -> Built {
+    #[derive(Serialize)]
+    struct PageProperties {
+        properties: HashMap<String, PagePropertyValue>,
+    }
+
+    with_body(
+        "update_page_properties",
+        Method::Patch,
+        format!("pages/{}", id),
+        &PageProperties { properties },
+    )
+}
+
+pub(crate) fn update_database(
+    id: &str,
+    title: Option<Vec<RichTextObject>>,
+    schema_changes: Option<DatabaseSchemaChanges>,
+) -> Built {
+    #[derive(Serialize)]
+    struct DatabaseUpdate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<Vec<RichTextObject>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        properties: Option<DatabaseSchemaChanges>,
+    }
+
+    with_body(
+        "update_database",
+        Method::Patch,
+        format!("databases/{}", id),
+        &DatabaseUpdate {
+            title,
+            properties: schema_changes,
+        },
+    )
+}
+
+pub(crate) fn append_block_children(id: &str, children: Vec<BlockObjectInput>) -> Built {
+    #[derive(Serialize)]
+    struct BlockChildren {
+        children: Vec<BlockObjectInput>,
+    }
+
+    with_body(
+        "append_block_children",
+        Method::Patch,
+        format!("blocks/{}/children", id),
+        &BlockChildren { children },
+    )
+}
+
+pub(crate) fn append_block_children_after(
+    id: &str,
+    children: Vec<BlockObjectInput>,
+    after: &str,
+) -> Built {
+    #[derive(Serialize)]
+    struct BlockChildren<'a> {
+        children: Vec<BlockObjectInput>,
+        after: &'a str,
+    }
+
+    with_body(
+        "append_block_children_after",
+        Method::Patch,
+        format!("blocks/{}/children", id),
+        &BlockChildren { children, after },
+    )
+}
+
+pub(crate) fn update_block(id: &str, content: BlockObjectUpdate) -> Built {
+    with_body(
+        "update_block",
+        Method::Patch,
+        format!("blocks/{}", id),
+        &content,
+    )
+}
+
+pub(crate) fn delete_block(id: &str) -> Built {
+    Ok(Endpoint {
+        name: "delete_block",
+        method: Method::Delete,
+        path: format!("blocks/{}", id),
+        body: None,
+    })
+}
+
+pub(crate) fn create_page(
+    parent: Parent,
+    properties: HashMap<String, PagePropertyValue>,
+    children_blocks: Option<Vec<BlockObjectInput>>,
+) -> Built {
+    #[derive(Serialize)]
+    struct NewPage {
+        parent: Parent,
+        properties: HashMap<String, PagePropertyValue>,
+        #[serde(rename = "children", skip_serializing_if = "Option::is_none")]
+        children_blocks: Option<Vec<BlockObjectInput>>,
+    }
+
+    with_body(
+        "create_page",
+        Method::Post,
+        "pages".to_string(),
+        &NewPage {
+            parent,
+            properties,
+            children_blocks,
+        },
+    )
+}
+
+pub(crate) fn create_database(
+    parent_page: &str,
+    title: Vec<RichTextObject>,
+    schema: DatabaseSchema,
+) -> Built {
+    #[derive(Serialize)]
+    struct NewDatabase {
+        parent: Parent,
+        title: Vec<RichTextObject>,
+        properties: DatabaseSchema,
+    }
+
+    with_body(
+        "create_database",
+        Method::Post,
+        "databases".to_string(),
+        &NewDatabase {
+            parent: Parent::PageId {
+                page_id: parent_page.to_string(),
+            },
+            title,
+            properties: schema,
+        },
+    )
+}
+
+pub(crate) fn query_database(
+    id: &str,
+    filter: Option<QueryDatabaseFilter>,
+    sorts: Option<Vec<QueryDatabaseSort>>,
+    start_cursor: Option<String>,
+    page_size: Option<i32>,
+) -> Built {
+    #[derive(Serialize)]
+    struct DatabaseQuery {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filter: Option<QueryDatabaseFilter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sorts: Option<Vec<QueryDatabaseSort>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_cursor: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page_size: Option<i32>,
+    }
+
+    with_body(
+        "query_database",
+        Method::Post,
+        format!("databases/{}/query", id),
+        &DatabaseQuery {
+            filter,
+            sorts,
+            start_cursor,
+            page_size,
+        },
+    )
+}
+
+pub(crate) fn search(
+    query: Option<String>,
+    filter: Option<SearchFilter>,
+    sort: Option<SearchSort>,
+    start_cursor: Option<String>,
+    page_size: Option<i32>,
+) -> Built {
+    #[derive(Serialize)]
+    struct Search {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        query: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filter: Option<SearchFilter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sort: Option<SearchSort>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_cursor: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page_size: Option<i32>,
+    }
+
+    with_body(
+        "search",
+        Method::Post,
+        "search".to_string(),
+        &Search {
+            query,
+            filter,
+            sort,
+            start_cursor,
+            page_size,
+        },
+    )
+}
+
+/// Everything about a client except how it sends requests
+pub(crate) struct ClientCore {
+    pub auth_token: String,
+    pub base_url: String,
+    pub cassette: Option<Cassette>,
+    pub middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl ClientCore {
+    pub fn new(auth_token: String) -> Self {
+        Self {
+            auth_token,
+            base_url: BASE_URL.to_string(),
+            cassette: None,
+            middleware: Vec::new(),
+        }
+    }
+
+    /// The recorded answer to a request when replaying a cassette
+    pub fn replay(&self, endpoint: &Endpoint) -> Result<Option<NotionObject>, Box<dyn Error>> {
+        let cassette = match &self.cassette {
+            Some(cassette) => cassette,
+            None => return Ok(None),
+        };
+        match cassette.play(
+            endpoint.method.as_str(),
+            &endpoint.path,
+            endpoint.body.as_deref(),
+        )? {
+            Some(text) => Ok(Some(serde_json::from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The request to send after the middleware has seen it
+    pub fn request(&self, endpoint: &Endpoint) -> Request {
+        let mut request = Request {
+            method: endpoint.method,
+            url: self.base_url.clone() + &endpoint.path,
+            headers: vec![
+                ("Notion-Version".to_string(), NOTION_VERSION.to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+                (
+                    "Authorization".to_string(),
+                    format!("Bearer {}", self.auth_token),
+                ),
+            ],
+            body: endpoint.body.clone(),
+        };
+        for layer in &self.middleware {
+            layer.on_request(endpoint.name, &mut request);
+        }
+        request
+    }
+
+    /// The spans the middleware wants the request sent in, outermost first
+    #[cfg(feature = "tracing")]
+    pub fn spans(&self, endpoint: &Endpoint, request: &Request) -> Vec<tracing::Span> {
+        self.middleware
+            .iter()
+            .filter_map(|layer| layer.span(endpoint.name, request))
+            .collect()
+    }
+
+    /// Pass the response back through the middleware, record it if there's a cassette and parse
+    /// it
+    pub fn response(
+        &self,
+        endpoint: &Endpoint,
+        request: &Request,
+        mut response: Response,
+        latency: Duration,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        for layer in self.middleware.iter().rev() {
+            layer.on_response(endpoint.name, request, &mut response, latency);
+        }
+        let text = String::from_utf8_lossy(&response.body).into_owned();
+        if let Some(cassette) = &self.cassette {
+            cassette.record(
+                endpoint.method.as_str(),
+                &endpoint.path,
+                endpoint.body.as_deref(),
+                response.status,
+                &text,
+                &self.auth_token,
+            )?;
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn failed(
+        &self,
+        endpoint: &Endpoint,
+        request: &Request,
+        error: &dyn Error,
+        latency: Duration,
+    ) {
+        for layer in self.middleware.iter().rev() {
+            layer.on_error(endpoint.name, request, error, latency);
+        }
+    }
+}
+
+/// A request for a file, e.g. one hosted by Notion, without the token
+pub(crate) fn download(url: &str) -> Request {
+    Request {
+        method: Method::Get,
+        url: url.to_string(),
+        headers: Vec::new(),
+        body: None,
+    }
+}
+
+pub(crate) fn downloaded(url: &str, response: Response) -> Result<Vec<u8>, Box<dyn Error>> {
+    if response.status >= 400 {
+        return Err(format!("Downloading {} failed with status {}", url, response.status).into());
+    }
+    Ok(response.body)
+}
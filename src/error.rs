@@ -1,11 +1,14 @@
+use crate::NotionObject;
 use serde::{Deserialize, Serialize};
-use std::{error, fmt};
+use std::{collections::HashMap, error, fmt};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ErrorObject {
     pub status: u16,
     pub code: ErrorCode,
     pub message: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 impl fmt::Display for ErrorObject {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -18,7 +21,7 @@ impl fmt::Display for ErrorObject {
 }
 impl error::Error for ErrorObject {}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
     InvalidJson,
@@ -32,6 +35,15 @@ pub enum ErrorCode {
     RateLimited,
     InternalServerError,
     ServiceUnavailable,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
 impl fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -47,6 +59,32 @@ impl fmt::Display for ErrorCode {
             Self::RateLimited => write!(f, "Rate Limited"),
             Self::InternalServerError => write!(f, "Internal Server Error"),
             Self::ServiceUnavailable => write!(f, "Service Unavailable"),
+            Self::Unknown { type_name, .. } => write!(f, "{}", type_name),
         }
     }
 }
+
+/// Notion sent back a different kind of object than the one that was asked for
+#[derive(Debug)]
+pub struct UnexpectedObject {
+    pub expected: &'static str,
+    pub found: String,
+}
+impl UnexpectedObject {
+    pub fn new(expected: &'static str, found: &NotionObject) -> Self {
+        Self {
+            expected,
+            found: found.kind().to_string(),
+        }
+    }
+}
+impl fmt::Display for UnexpectedObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected a {} object from Notion but got a {}",
+            self.expected, self.found
+        )
+    }
+}
+impl error::Error for UnexpectedObject {}
@@ -0,0 +1,379 @@
+//! Writing database rows out as CSV or JSON Lines. Every property is rendered the same way for
+//! both formats, JSON Lines just keeps numbers, checkboxes and lists as their JSON types.
+//!
+//! Notion doesn't say what order a database's properties are in, so unless columns are picked
+//! with [`ExportOptions::columns`] the title comes first followed by the rest sorted by name.
+//!
+//! In CSV, lists such as multi-selects are joined with a delimiter, and values that contain it
+//! or a `"` are quoted with `"` the way CSV fields are, so [`import`](crate::import) can split
+//! them up again. Dates are ISO 8601 dates or times, and ranges are written `start/end`.
+use crate::{
+    database::{DatabaseObject, DatabaseProperty},
+    page::{PageDate, PageFormula, PageObject, PageProperty, PageRollup, PageRollupProperty},
+    rich_text::RichTextObject,
+    time::Time,
+    user::UserObject,
+};
+use serde_json::{Map, Value};
+use std::{borrow::Borrow, collections::HashMap, error::Error, io::Write};
+
+/// What separates the values of a list in a CSV cell unless another delimiter is picked
+pub const DEFAULT_DELIMITER: &str = ",";
+
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    /// The properties to export and their order, every property when empty
+    pub columns: Vec<String>,
+    /// Header names to use instead of property names
+    pub rename: HashMap<String, String>,
+    /// Add the page id as the first column, called `id`
+    pub include_id: bool,
+    /// Render people by email instead of name. Bots have no email so their name is used.
+    pub people_emails: bool,
+    /// Titles of related pages by id, relations are rendered as ids when a page isn't in here
+    pub relation_titles: HashMap<String, String>,
+    /// What separates the values of multi-select, relation, people and other list cells in CSV,
+    /// [`DEFAULT_DELIMITER`] when `None`
+    pub delimiter: Option<String>,
+}
+
+pub struct Exporter {
+    options: ExportOptions,
+    columns: Vec<String>,
+}
+
+impl Exporter {
+    pub fn new(database: &DatabaseObject, options: ExportOptions) -> Self {
+        let columns = if options.columns.is_empty() {
+            let mut columns = database.properties.iter().collect::<Vec<_>>();
+            columns.sort_by_key(|(name, property)| {
+                (!matches!(property, DatabaseProperty::Title { .. }), *name)
+            });
+            columns.into_iter().map(|(name, _)| name.clone()).collect()
+        } else {
+            options.columns.clone()
+        };
+        Self { options, columns }
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        let id = Some("id".to_string()).filter(|_| self.options.include_id);
+        id.into_iter()
+            .chain(
+                self.columns
+                    .iter()
+                    .map(|name| self.options.rename.get(name).unwrap_or(name).to_string()),
+            )
+            .collect()
+    }
+
+    /// The row's values in column order, properties the row doesn't have are `null`
+    pub fn values(&self, row: &PageObject) -> Vec<Value> {
+        let id = Some(Value::String(row.id.clone())).filter(|_| self.options.include_id);
+        id.into_iter()
+            .chain(
+                self.columns
+                    .iter()
+                    .map(|name| match row.properties.get(name) {
+                        Some(property) => self.property(property),
+                        None => Value::Null,
+                    }),
+            )
+            .collect()
+    }
+
+    pub fn write_csv<I>(&self, rows: I, writer: impl Write) -> Result<(), Box<dyn Error>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<PageObject>,
+    {
+        let delimiter = delimiter(self.options.delimiter.as_deref());
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(self.headers())?;
+        for row in rows {
+            let values = self.values(row.borrow());
+            writer.write_record(values.iter().map(|value| join(value, delimiter)))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// One JSON object per line keyed by header
+    pub fn write_jsonl<I>(&self, rows: I, mut writer: impl Write) -> Result<(), Box<dyn Error>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<PageObject>,
+    {
+        let headers = self.headers();
+        for row in rows {
+            let object = headers
+                .iter()
+                .cloned()
+                .zip(self.values(row.borrow()))
+                .collect::<Map<_, _>>();
+            serde_json::to_writer(&mut writer, &object)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn property(&self, property: &PageProperty) -> Value {
+        match property {
+            PageProperty::Title { title: text, .. }
+            | PageProperty::RichText {
+                rich_text: text, ..
+            } => Value::String(plain_text(text)),
+            PageProperty::Number { number, .. } => number.map_or(Value::Null, number_value),
+            PageProperty::Select { select, .. } => select
+                .as_ref()
+                .map_or(Value::Null, |option| Value::String(option.name.clone())),
+            PageProperty::MultiSelect { multi_select, .. } => multi_select
+                .iter()
+                .map(|option| Value::String(option.name.clone()))
+                .collect(),
+            PageProperty::Date { date, .. } => date_value(date.as_ref()),
+            PageProperty::Formula { formula, .. } => formula_value(formula),
+            PageProperty::Relation { relation, .. } => relation
+                .iter()
+                .map(|related| self.relation(&related.id))
+                .collect(),
+            PageProperty::Rollup { rollup, .. } => self.rollup(rollup),
+            PageProperty::People { people, .. } => {
+                people.iter().map(|user| self.user(user)).collect()
+            }
+            PageProperty::Files { files, .. } => files
+                .iter()
+                .map(|file| Value::String(file.name.clone()))
+                .collect(),
+            PageProperty::Checkbox { checkbox, .. } => Value::Bool(*checkbox),
+            PageProperty::Url { url: text, .. }
+            | PageProperty::Email { email: text, .. }
+            | PageProperty::PhoneNumber {
+                phone_number: text, ..
+            } => text.clone().map_or(Value::Null, Value::String),
+            PageProperty::CreatedTime {
+                created_time: time, ..
+            }
+            | PageProperty::LastEditedTime {
+                last_edited_time: time,
+                ..
+            } => Value::String(time.to_string()),
+            PageProperty::CreatedBy {
+                created_by: user, ..
+            }
+            | PageProperty::LastEditedBy {
+                last_edited_by: user,
+                ..
+            } => self.user(user),
+            PageProperty::Unknown { raw, .. } => raw.clone(),
+        }
+    }
+
+    fn rollup(&self, rollup: &PageRollup) -> Value {
+        match rollup {
+            PageRollup::Number { number } => number.map_or(Value::Null, number_value),
+            PageRollup::Date { date } => date_value(date.as_ref()),
+            // Nested lists are flattened so every rollup is at most one list of values
+            PageRollup::Array { array } => array
+                .iter()
+                .flat_map(|property| match self.rollup_property(property) {
+                    Value::Array(values) => values,
+                    value => vec![value],
+                })
+                .collect(),
+            PageRollup::Unknown { raw, .. } => raw.clone(),
+        }
+    }
+
+    fn rollup_property(&self, property: &PageRollupProperty) -> Value {
+        match property {
+            PageRollupProperty::Title { title: text }
+            | PageRollupProperty::RichText { rich_text: text } => Value::String(plain_text(text)),
+            PageRollupProperty::Number { number } => number.map_or(Value::Null, number_value),
+            PageRollupProperty::Select { select } => select
+                .as_ref()
+                .map_or(Value::Null, |option| Value::String(option.name.clone())),
+            PageRollupProperty::MultiSelect { multi_select } => multi_select
+                .iter()
+                .map(|option| Value::String(option.name.clone()))
+                .collect(),
+            PageRollupProperty::Date { date } => date_value(date.as_ref()),
+            PageRollupProperty::Formula { formula } => formula_value(formula),
+            PageRollupProperty::Relation { relation } => relation
+                .iter()
+                .map(|related| self.relation(&related.id))
+                .collect(),
+            PageRollupProperty::Rollup { rollup } => self.rollup(rollup),
+            PageRollupProperty::People { people } => {
+                people.iter().map(|user| self.user(user)).collect()
+            }
+            PageRollupProperty::Files { files } => files
+                .iter()
+                .map(|file| Value::String(file.name.clone()))
+                .collect(),
+            PageRollupProperty::Checkbox { checkbox } => Value::Bool(*checkbox),
+            PageRollupProperty::Url { url: text }
+            | PageRollupProperty::Email { email: text }
+            | PageRollupProperty::PhoneNumber { phone_number: text } => {
+                text.clone().map_or(Value::Null, Value::String)
+            }
+            PageRollupProperty::CreatedTime { created_time: time }
+            | PageRollupProperty::LastEditedTime {
+                last_edited_time: time,
+            } => Value::String(time.to_string()),
+            PageRollupProperty::CreatedBy { created_by: user }
+            | PageRollupProperty::LastEditedBy {
+                last_edited_by: user,
+            } => self.user(user),
+            PageRollupProperty::Unknown { raw, .. } => raw.clone(),
+        }
+    }
+
+    fn relation(&self, id: &str) -> Value {
+        Value::String(
+            self.options
+                .relation_titles
+                .get(id)
+                .map_or(id, String::as_str)
+                .to_string(),
+        )
+    }
+
+    fn user(&self, user: &UserObject) -> Value {
+        let value = match user {
+            UserObject::Person { person, .. } if self.options.people_emails => {
+                Some(person.email.clone())
+            }
+            UserObject::Person { name, .. } | UserObject::Bot { name, .. } => name.clone(),
+            UserObject::Unknown { .. } | UserObject::Partial { .. } => None,
+        };
+        // Without a name the id is the only thing to go on
+        value
+            .or_else(|| user.id().map(str::to_string))
+            .map_or(Value::Null, Value::String)
+    }
+}
+
+/// A property value as text, the way it would be exported with the default options
+pub(crate) fn render(property: &PageProperty) -> String {
+    let exporter = Exporter {
+        options: ExportOptions::default(),
+        columns: Vec::new(),
+    };
+    cell(&exporter.property(property))
+}
+
+/// How a value is written in a CSV cell, lists are joined with [`DEFAULT_DELIMITER`]
+pub(crate) fn cell(value: &Value) -> String {
+    join(value, DEFAULT_DELIMITER)
+}
+
+/// A value as text with the values of lists separated by `delimiter` and a space, quoting the
+/// ones that would be split up or lose spaces when read back with [`split`]
+pub(crate) fn join(value: &Value, delimiter: &str) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| {
+                let text = join(value, delimiter);
+                if text.contains(delimiter) || text.contains('"') || text.trim() != text {
+                    format!("\"{}\"", text.replace('"', "\"\""))
+                } else {
+                    text
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&format!("{} ", delimiter)),
+        value => value.to_string(),
+    }
+}
+
+/// The values of a list written by [`join`], empty values are left out
+pub(crate) fn split(text: &str, delimiter: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let value = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((index, c)) = chars.next() {
+                    if c != '"' {
+                        value.push(c);
+                    } else if quoted[index + 1..].starts_with('"') {
+                        value.push('"');
+                        chars.next();
+                    } else {
+                        end = index + 1;
+                        break;
+                    }
+                }
+                // Anything between the closing quote and the delimiter is ignored
+                rest = &quoted[end..];
+                value
+            }
+            None => {
+                let end = rest.find(delimiter).unwrap_or(rest.len());
+                let value = rest[..end].trim().to_string();
+                rest = &rest[end..];
+                value
+            }
+        };
+        if !value.is_empty() {
+            values.push(value);
+        }
+        match rest.find(delimiter) {
+            Some(index) => rest = &rest[index + delimiter.len()..],
+            None => return values,
+        }
+    }
+}
+
+/// The delimiter to use, falling back to [`DEFAULT_DELIMITER`] for `None` or an empty one
+pub(crate) fn delimiter(delimiter: Option<&str>) -> &str {
+    delimiter
+        .filter(|delimiter| !delimiter.is_empty())
+        .unwrap_or(DEFAULT_DELIMITER)
+}
+
+fn plain_text(text: &[RichTextObject]) -> String {
+    text.iter().map(RichTextObject::plain_text).collect()
+}
+
+/// Whole numbers are written without a trailing `.0`
+fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        Value::from(number as i64)
+    } else {
+        serde_json::Number::from_f64(number).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn formula_value(formula: &PageFormula) -> Value {
+    match formula {
+        PageFormula::String { string } => string.clone().map_or(Value::Null, Value::String),
+        PageFormula::Number { number } => number.map_or(Value::Null, number_value),
+        PageFormula::Boolean { boolean } => boolean.map_or(Value::Null, Value::Bool),
+        PageFormula::Date { date } => date_value(date.as_ref()),
+        PageFormula::Unknown { raw, .. } => raw.clone(),
+    }
+}
+
+fn date_value(date: Option<&PageDate>) -> Value {
+    date.map_or(Value::Null, |date| {
+        Value::String(date_range(&date.start, date.end.as_ref()))
+    })
+}
+
+/// An ISO 8601 interval `start/end`, or just the start when there's no end
+fn date_range(start: &Time, end: Option<&Time>) -> String {
+    match end {
+        Some(end) => format!("{}/{}", start, end),
+        None => start.to_string(),
+    }
+}
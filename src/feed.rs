@@ -0,0 +1,463 @@
+//! Polling for edited pages. A [`ChangeFeed`] repeatedly asks for pages sorted by
+//! `last_edited_time`, newest first, and compares them with the copies it saw last time to
+//! produce [`ChangeEvent`]s.
+//!
+//! Notion rounds edit times to the minute, so several edits can share a timestamp and an edit can
+//! be made in the same minute as the last poll. Pages are fetched back to `overlap` before the
+//! newest edit time seen so far and only reported when they actually differ from the stored
+//! copy, which makes ties harmless. The high-water mark only ever comes from Notion's timestamps,
+//! never the local clock, so a skewed local clock can't make the feed skip edits.
+//!
+//! Search and database queries leave out archived pages, so archiving a page usually just makes
+//! it stop showing up. With [`FeedOptions::full_scan_every`] set the feed lists every page now
+//! and then and reports the ones that are gone as [`ChangeKind::Archived`]. Pages that were
+//! deleted or are no longer shared with the integration look the same.
+pub use crate::diff::PropertyChange;
+use crate::{
+    database::{Direction, QueryDatabaseSort, QueryDatabaseTime},
+    diff::diff_properties,
+    page::PageObject,
+    search::{self, SearchFilter, SearchSort, SearchTime},
+    Client,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    error::Error,
+    fs,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Archived,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    /// The page as it was last seen, `None` for new pages
+    pub old: Option<PageObject>,
+    pub new: PageObject,
+    /// Properties whose values differ between `old` and `new`, sorted by name. Empty when only
+    /// the content was edited
+    pub changes: Vec<PropertyChange>,
+}
+
+/// What the feed remembers between polls
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct FeedState {
+    /// The newest `last_edited_time` seen
+    pub high_water: Option<DateTime<Utc>>,
+    /// The last seen copy of every page
+    pub pages: BTreeMap<String, PageObject>,
+}
+
+/// Where the feed keeps its state so it can carry on after a restart
+pub trait FeedStore {
+    fn load(&mut self) -> Result<Option<FeedState>, Box<dyn Error>>;
+    fn save(&mut self, state: &FeedState) -> Result<(), Box<dyn Error>>;
+}
+
+/// Keeps the state for as long as the feed is running
+#[derive(Debug, Default)]
+pub struct MemoryStore(pub Option<FeedState>);
+
+impl FeedStore for MemoryStore {
+    fn load(&mut self) -> Result<Option<FeedState>, Box<dyn Error>> {
+        Ok(self.0.clone())
+    }
+
+    fn save(&mut self, state: &FeedState) -> Result<(), Box<dyn Error>> {
+        self.0 = Some(state.clone());
+        Ok(())
+    }
+}
+
+/// Keeps the state in a JSON file
+#[derive(Debug)]
+pub struct FileStore(pub PathBuf);
+
+impl FeedStore for FileStore {
+    fn load(&mut self) -> Result<Option<FeedState>, Box<dyn Error>> {
+        if self.0.exists() {
+            Ok(Some(serde_json::from_str(&fs::read_to_string(&self.0)?)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn save(&mut self, state: &FeedState) -> Result<(), Box<dyn Error>> {
+        fs::write(&self.0, serde_json::to_string(state)?)?;
+        Ok(())
+    }
+}
+
+/// Pages sorted by `last_edited_time`, newest first
+#[async_trait(?Send)]
+pub trait PageSource {
+    /// One page of results starting at `cursor`, along with the cursor of the next one
+    async fn edited_pages(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<(Vec<PageObject>, Option<String>), Box<dyn Error>>;
+}
+
+/// Lets a fake source be kept around to change between polls
+#[async_trait(?Send)]
+impl<T: PageSource + ?Sized> PageSource for &T {
+    async fn edited_pages(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<(Vec<PageObject>, Option<String>), Box<dyn Error>> {
+        (**self).edited_pages(cursor).await
+    }
+}
+
+/// Every page the integration can see
+pub struct SearchSource<'a>(pub &'a Client);
+
+#[async_trait(?Send)]
+impl PageSource for SearchSource<'_> {
+    async fn edited_pages(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<(Vec<PageObject>, Option<String>), Box<dyn Error>> {
+        let list = self
+            .0
+            .search(
+                None,
+                Some(SearchFilter {
+                    value: Some("page".to_string()),
+                    property: Some("object".to_string()),
+                }),
+                Some(SearchSort {
+                    direction: Some(search::Direction::Descending),
+                    timestamp: Some(SearchTime::LastEditedTime),
+                }),
+                cursor,
+                None,
+            )
+            .await?
+            .into_list()?;
+        let pages = list
+            .results
+            .into_iter()
+            .map(|result| result.into_page())
+            .collect::<Result<_, _>>()?;
+        let next = if list.has_more {
+            list.next_cursor
+        } else {
+            None
+        };
+        Ok((pages, next))
+    }
+}
+
+/// The rows of a database
+pub struct DatabaseSource<'a> {
+    pub client: &'a Client,
+    pub database_id: String,
+}
+
+#[async_trait(?Send)]
+impl PageSource for DatabaseSource<'_> {
+    async fn edited_pages(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<(Vec<PageObject>, Option<String>), Box<dyn Error>> {
+        let sorts = vec![QueryDatabaseSort {
+            property: None,
+            timestamp: Some(QueryDatabaseTime::LastEditedTime),
+            direction: Some(Direction::Descending),
+        }];
+        let list = self
+            .client
+            .query_database(&self.database_id, None, Some(sorts), cursor, None)
+            .await?
+            .into_list()?;
+        let pages = list
+            .results
+            .into_iter()
+            .map(|result| result.into_page())
+            .collect::<Result<_, _>>()?;
+        let next = if list.has_more {
+            list.next_cursor
+        } else {
+            None
+        };
+        Ok((pages, next))
+    }
+}
+
+/// When polls happen, swapped out for a [`MockClock`] in tests
+#[async_trait(?Send)]
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+    async fn sleep(&self, duration: Duration);
+}
+
+/// Lets a mock clock be kept around to advance by hand
+#[async_trait(?Send)]
+impl<T: Clock + ?Sized> Clock for &T {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration).await
+    }
+}
+
+pub struct SystemClock;
+
+#[async_trait(?Send)]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+/// A clock that only moves when slept on or advanced by hand
+pub struct MockClock(Cell<DateTime<Utc>>);
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Cell::new(now))
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.0
+            .set(self.0.get() + chrono::Duration::from_std(duration).unwrap_or_default());
+    }
+}
+
+#[async_trait(?Send)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0.get()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[derive(Debug)]
+pub struct FeedOptions {
+    /// How long to wait between polls
+    pub interval: Duration,
+    /// How far before the high-water mark to look again for edits that share its timestamp
+    pub overlap: Duration,
+    /// Report every page as created on the first poll instead of only recording them
+    pub emit_initial: bool,
+    /// List every page on every this many polls to find the ones that have been archived
+    pub full_scan_every: Option<u32>,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            overlap: Duration::from_secs(120),
+            emit_initial: false,
+            full_scan_every: None,
+        }
+    }
+}
+
+pub struct ChangeFeed<S, St, C = SystemClock> {
+    source: S,
+    store: St,
+    clock: C,
+    options: FeedOptions,
+    state: Option<FeedState>,
+    pending: VecDeque<ChangeEvent>,
+    last_poll: Option<DateTime<Utc>>,
+    polls: u32,
+}
+
+impl<'a, St: FeedStore> ChangeFeed<SearchSource<'a>, St> {
+    /// Watch every page the client can see
+    pub fn search(client: &'a Client, store: St, options: FeedOptions) -> Self {
+        Self::new(SearchSource(client), store, SystemClock, options)
+    }
+}
+
+impl<'a, St: FeedStore> ChangeFeed<DatabaseSource<'a>, St> {
+    /// Watch the rows of a database
+    pub fn database(
+        client: &'a Client,
+        database_id: &str,
+        store: St,
+        options: FeedOptions,
+    ) -> Self {
+        let source = DatabaseSource {
+            client,
+            database_id: database_id.to_string(),
+        };
+        Self::new(source, store, SystemClock, options)
+    }
+}
+
+impl<S: PageSource, St: FeedStore, C: Clock> ChangeFeed<S, St, C> {
+    pub fn new(source: S, store: St, clock: C, options: FeedOptions) -> Self {
+        Self {
+            source,
+            store,
+            clock,
+            options,
+            state: None,
+            pending: VecDeque::new(),
+            last_poll: None,
+            polls: 0,
+        }
+    }
+
+    /// Wait for the next change, polling every `interval` until there is one
+    pub async fn next(&mut self) -> Result<ChangeEvent, Box<dyn Error>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            if let Some(last) = self.last_poll {
+                let elapsed = (self.clock.now() - last).to_std().unwrap_or_default();
+                if let Some(wait) = self.options.interval.checked_sub(elapsed) {
+                    self.clock.sleep(wait).await;
+                }
+            }
+            let events = self.poll().await?;
+            self.pending.extend(events);
+        }
+    }
+
+    /// Fetch the pages edited since the last poll and return what changed, oldest edit first
+    pub async fn poll(&mut self) -> Result<Vec<ChangeEvent>, Box<dyn Error>> {
+        self.last_poll = Some(self.clock.now());
+        self.polls = self.polls.wrapping_add(1);
+        let full_scan = self
+            .options
+            .full_scan_every
+            .is_some_and(|every| every > 0 && self.polls.is_multiple_of(every));
+        // Only the very first poll, with nothing stored yet, is initial, even if it finds no pages
+        let (mut state, initial) = match self.state.take() {
+            Some(state) => (state, false),
+            None => match self.store.load()? {
+                Some(state) => (state, false),
+                None => (FeedState::default(), true),
+            },
+        };
+        let since = state.high_water.map(|high_water| {
+            high_water - chrono::Duration::from_std(self.options.overlap).unwrap_or_default()
+        });
+
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        'fetch: loop {
+            let (results, next) = self.source.edited_pages(cursor).await?;
+            for page in results {
+                let edited = page.last_edited_time.to_utc();
+                if !full_scan && since.is_some_and(|since| edited < since) {
+                    break 'fetch;
+                }
+                pages.push((edited, page));
+            }
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let mut events = Vec::new();
+        let mut seen = BTreeSet::new();
+        // Oldest first so events come out in the order the edits happened
+        for (edited, page) in pages.into_iter().rev() {
+            if !seen.insert(page.id.clone()) {
+                continue;
+            }
+            if state
+                .high_water
+                .is_none_or(|high_water| edited > high_water)
+            {
+                state.high_water = Some(edited);
+            }
+            let old = state.pages.insert(page.id.clone(), page.clone());
+            if initial && !self.options.emit_initial {
+                continue;
+            }
+            if let Some(event) = change(old, page) {
+                events.push(event);
+            }
+        }
+        if full_scan {
+            let gone: Vec<_> = state
+                .pages
+                .keys()
+                .filter(|id| !seen.contains(*id))
+                .cloned()
+                .collect();
+            for id in gone {
+                if let Some(old) = state.pages.remove(&id) {
+                    let mut new = old.clone();
+                    new.archived = true;
+                    events.push(ChangeEvent {
+                        kind: ChangeKind::Archived,
+                        old: Some(old),
+                        new,
+                        changes: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        self.store.save(&state)?;
+        self.state = Some(state);
+        Ok(events)
+    }
+}
+
+/// The event for a page compared to its last seen copy, `None` if nothing changed
+fn change(old: Option<PageObject>, new: PageObject) -> Option<ChangeEvent> {
+    let old = match old {
+        Some(old) => old,
+        None => {
+            let kind = if new.archived {
+                ChangeKind::Archived
+            } else {
+                ChangeKind::Created
+            };
+            let changes = diff_properties(&HashMap::new(), &new.properties);
+            return Some(ChangeEvent {
+                kind,
+                old: None,
+                new,
+                changes,
+            });
+        }
+    };
+
+    let changes = diff_properties(&old.properties, &new.properties);
+    // A newer edit time with the same properties is an edit to the page's content
+    let edited = new.last_edited_time.to_utc() > old.last_edited_time.to_utc();
+    let kind = match (old.archived, new.archived) {
+        (false, true) => ChangeKind::Archived,
+        _ if changes.is_empty() && old.archived == new.archived && !edited => return None,
+        _ => ChangeKind::Updated,
+    };
+    Some(ChangeEvent {
+        kind,
+        old: Some(old),
+        new,
+        changes,
+    })
+}
@@ -0,0 +1,284 @@
+use crate::{
+    block::{BlockObject, BlockTree, LinkToPage},
+    database::DatabaseObject,
+    page::{PageObject, PageProperty, Parent},
+    rich_text::{Mention, RichTextObject},
+    search::SearchFilter,
+    Client,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    error::Error,
+    fmt::Write,
+};
+
+/// A directed graph of which pages and databases reference each other, keyed by id
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PageGraph {
+    pub nodes: BTreeMap<String, Node>,
+    pub edges: BTreeSet<Edge>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Node {
+    pub kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Page,
+    Database,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A page or database mention in rich text
+    Mention,
+    /// A `child_page` block
+    ChildPage,
+    /// A `link_to_page` block
+    LinkToPage,
+    /// A value of a relation property
+    Relation,
+    /// From a page to the page or database it lives in
+    Parent,
+}
+
+impl PageGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Walk every page and database the client can see and add them to a new graph
+    pub async fn build(client: &Client) -> Result<Self, Box<dyn Error>> {
+        let mut graph = Self::new();
+        let filter = |value: &str| SearchFilter {
+            value: Some(value.to_string()),
+            property: Some("object".to_string()),
+        };
+
+        for result in client.search_all(None, Some(filter("database"))).await? {
+            graph.add_database(&result.into_database()?);
+        }
+        for result in client.search_all(None, Some(filter("page"))).await? {
+            let page = result.into_page()?;
+            let blocks = client.get_block_tree(&page.id).await?;
+            graph.add_page(&page, &blocks);
+        }
+
+        Ok(graph)
+    }
+
+    pub fn add_database(&mut self, database: &DatabaseObject) {
+        let title = database
+            .title
+            .iter()
+            .map(RichTextObject::plain_text)
+            .collect();
+        self.add_node(&database.id, NodeKind::Database, Some(title));
+        self.add_rich_text(&database.id, &database.title);
+    }
+
+    /// Add a page along with the links found in its properties and block tree
+    pub fn add_page(&mut self, page: &PageObject, blocks: &[BlockTree]) {
+        self.add_node(&page.id, NodeKind::Page, page.title());
+
+        match &page.parent {
+            Parent::PageId { page_id } => self.add_edge(&page.id, page_id, EdgeKind::Parent),
+            Parent::DatabaseId { database_id } => {
+                self.add_edge(&page.id, database_id, EdgeKind::Parent)
+            }
+            _ => {}
+        }
+
+        for property in page.properties.values() {
+            match property {
+                PageProperty::Relation { relation, .. } => {
+                    for related in relation {
+                        self.add_edge(&page.id, &related.id, EdgeKind::Relation);
+                    }
+                }
+                PageProperty::Title { title: text, .. }
+                | PageProperty::RichText {
+                    rich_text: text, ..
+                } => self.add_rich_text(&page.id, text),
+                _ => {}
+            }
+        }
+
+        self.add_blocks(&page.id, blocks);
+    }
+
+    fn add_blocks(&mut self, page_id: &str, blocks: &[BlockTree]) {
+        for tree in blocks {
+            match &tree.block {
+                BlockObject::ChildPage { id, child_page, .. } => {
+                    self.add_node(id, NodeKind::Page, Some(child_page.title.clone()));
+                    self.add_edge(page_id, id, EdgeKind::ChildPage);
+                    // Anything under it belongs to the sub-page, which is added on its own
+                    continue;
+                }
+                BlockObject::LinkToPage { link_to_page, .. } => match link_to_page {
+                    LinkToPage::PageId { page_id: to }
+                    | LinkToPage::DatabaseId { database_id: to } => {
+                        self.add_edge(page_id, to, EdgeKind::LinkToPage)
+                    }
+                    LinkToPage::Unknown { .. } => {}
+                },
+                block => self.add_rich_text(page_id, block.text().unwrap_or_default()),
+            }
+            self.add_blocks(page_id, &tree.children);
+        }
+    }
+
+    fn add_rich_text(&mut self, from: &str, text: &[RichTextObject]) {
+        for text in text {
+            match text {
+                RichTextObject::Mention {
+                    mention: Mention::Page { page },
+                    ..
+                } => self.add_edge(from, &page.id, EdgeKind::Mention),
+                RichTextObject::Mention {
+                    mention: Mention::Database { database },
+                    ..
+                } => self.add_edge(from, &database.id, EdgeKind::Mention),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_node(&mut self, id: &str, kind: NodeKind, title: Option<String>) {
+        let node = self
+            .nodes
+            .entry(id.to_string())
+            .or_insert(Node { kind, title: None });
+        if title.is_some() {
+            node.title = title;
+        }
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, kind: EdgeKind) {
+        self.edges.insert(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+        });
+    }
+
+    /// Edges pointing at the page
+    pub fn backlinks(&self, id: &str) -> Vec<&Edge> {
+        self.edges.iter().filter(|e| e.to == id).collect()
+    }
+
+    /// Edges going out of the page
+    pub fn links(&self, id: &str) -> Vec<&Edge> {
+        self.edges.iter().filter(|e| e.from == id).collect()
+    }
+
+    /// Pages that nothing links to and that don't live inside another page or database
+    pub fn orphans(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.kind == NodeKind::Page)
+            .filter(|(id, _)| {
+                !self
+                    .edges
+                    .iter()
+                    .any(|e| &e.to == *id || (&e.from == *id && e.kind == EdgeKind::Parent))
+            })
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
+    /// The fewest hops needed to get from one page to another following links, including both ends
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from(vec![from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![to.to_string()];
+                let mut step = to;
+                while let Some(prev) = previous.get(step) {
+                    path.push(prev.to_string());
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in self.edges.iter().filter(|e| e.from == current) {
+                if edge.to != from && !previous.contains_key(edge.to.as_str()) {
+                    previous.insert(&edge.to, current);
+                    queue.push_back(&edge.to);
+                }
+            }
+        }
+        None
+    }
+
+    /// Render the graph in Graphviz's DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph notion {\n");
+        for (id, node) in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Page => "box",
+                NodeKind::Database => "cylinder",
+            };
+            let _ = writeln!(
+                dot,
+                "  {} [label={}, shape={}];",
+                quote(id),
+                quote(node.title.as_deref().unwrap_or(id)),
+                shape
+            );
+        }
+        for edge in &self.edges {
+            let kind = match edge.kind {
+                EdgeKind::Mention => "mention",
+                EdgeKind::ChildPage => "child_page",
+                EdgeKind::LinkToPage => "link_to_page",
+                EdgeKind::Relation => "relation",
+                EdgeKind::Parent => "parent",
+            };
+            let _ = writeln!(
+                dot,
+                "  {} -> {} [label={}];",
+                quote(&edge.from),
+                quote(&edge.to),
+                quote(kind)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// A quoted DOT id. Backslashes are escaped too since they start escape sequences in labels.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
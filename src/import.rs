@@ -0,0 +1,528 @@
+//! Loading rows from CSV into a database. Each column is matched to a property by name and its
+//! values parsed according to the property's type. Rows that fail to parse or that Notion
+//! rejects are recorded in the [`ImportReport`] and the rest of the file is still imported.
+//!
+//! Columns the database doesn't have can be added with [`ImportOptions::add_columns`], and
+//! [`infer_schema`] works out a schema for a new database, both guessing each property's type
+//! from the values in the file.
+use crate::{
+    database::{
+        DatabaseObject, DatabaseProperty, DatabasePropertyInput, DatabaseSchema,
+        DatabaseSchemaChanges, Empty, Number, NumberFormat, SelectInput, SelectOptionsInput,
+    },
+    export::{self, cell, ExportOptions, Exporter},
+    page::{
+        PageDate, PageMultiSelectOptions, PagePropertyValue, PageRelation, PageSelectOptions,
+        Parent,
+    },
+    rich_text::RichTextObject,
+    user::UserObject,
+    Client,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    io::Read,
+};
+
+/// Stripped from around numbers
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥', '₽', '₹', '₩'];
+/// Columns with more distinct values than this are inferred as text rather than a select
+const MAX_INFERRED_OPTIONS: usize = 20;
+
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    /// CSV headers mapped to the property they fill, headers not in here are used as is
+    pub rename: HashMap<String, String>,
+    /// What separates the values of multi-select, relation and people columns, the same as
+    /// [`ExportOptions::delimiter`]. Values in `"` can contain it.
+    pub delimiter: Option<String>,
+    /// Add select and multi-select options the database doesn't have yet instead of rejecting
+    /// the rows that use them
+    pub add_options: bool,
+    /// Add properties for the headers the database doesn't have, with their types inferred from
+    /// the values in the file, instead of ignoring those columns
+    pub add_columns: bool,
+    /// Update the row whose value for this property matches instead of creating a new one
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub created: usize,
+    pub updated: usize,
+    /// Headers that don't match a property Notion lets us write to
+    pub ignored_columns: Vec<String>,
+    pub errors: Vec<RowError>,
+}
+
+#[derive(Debug)]
+pub struct RowError {
+    /// The line in the file the row starts on
+    pub line: u64,
+    pub message: String,
+}
+
+/// Import the rows of a CSV file with a header line into a database. Empty cells are skipped,
+/// they don't clear the value of a row that's being updated.
+pub async fn import_csv(
+    client: &Client,
+    database_id: &str,
+    reader: impl Read,
+    options: &ImportOptions,
+) -> Result<ImportReport, Box<dyn Error>> {
+    let mut database = client.get_database(database_id).await?.into_database()?;
+    let delimiter = export::delimiter(options.delimiter.as_deref());
+    let mut report = ImportReport::default();
+
+    let (headers, records) = read(reader, &mut report)?;
+
+    if options.add_columns {
+        let mut changes = DatabaseSchemaChanges::new();
+        for (index, header) in headers.iter().enumerate() {
+            let name = options.rename.get(header).unwrap_or(header);
+            if !name.is_empty() && !database.properties.contains_key(name) {
+                let values = records
+                    .iter()
+                    .map(|record| record.get(index).unwrap_or_default());
+                changes = changes.property(name, infer(values, delimiter));
+            }
+        }
+        if !changes.is_empty() {
+            database = client
+                .update_database(database_id, None, Some(changes))
+                .await?
+                .into_database()?;
+        }
+    }
+
+    let mut columns = Vec::new();
+    for (index, header) in headers.iter().enumerate() {
+        let name = options.rename.get(header).unwrap_or(header);
+        match database.properties.get(name) {
+            Some(property) if is_writable(property) => columns.push((index, name.clone())),
+            _ => report.ignored_columns.push(header.clone()),
+        }
+    }
+    let key_column = match &options.key {
+        Some(key) => match columns.iter().find(|(_, name)| name == key) {
+            Some((index, _)) => Some(*index),
+            None => return Err(format!("The key {:?} isn't one of the columns", key).into()),
+        },
+        None => None,
+    };
+
+    if options.add_options {
+        let changes = missing_options(&database, &columns, &records, delimiter);
+        if !changes.is_empty() {
+            database = client
+                .update_database(database_id, None, Some(changes))
+                .await?
+                .into_database()?;
+        }
+    }
+
+    let mut existing = HashMap::new();
+    if let Some(key) = &options.key {
+        let exporter = Exporter::new(
+            &database,
+            ExportOptions {
+                columns: vec![key.clone()],
+                ..ExportOptions::default()
+            },
+        );
+        for row in client.query_database_all(database_id, None, None).await? {
+            let row = row.into_page()?;
+            let key = cell(&exporter.values(&row)[0]);
+            if !key.is_empty() {
+                existing.insert(key, row.id);
+            }
+        }
+    }
+
+    for record in &records {
+        let line = record.position().map_or(0, |p| p.line());
+        let key = key_column.map(|index| record[index].trim().to_string());
+        // Without a key the row can't be matched, and creating it would add it again every time
+        // the file is imported
+        if key.as_ref().is_some_and(String::is_empty) {
+            report.errors.push(RowError {
+                line,
+                message: format!(
+                    "The key {:?} is empty",
+                    options.key.as_deref().unwrap_or_default()
+                ),
+            });
+            continue;
+        }
+        let properties = match parse_row(&database, &columns, record, delimiter) {
+            Ok(properties) => properties,
+            Err(message) => {
+                report.errors.push(RowError { line, message });
+                continue;
+            }
+        };
+
+        let result = match key.as_ref().and_then(|key| existing.get(key)) {
+            Some(id) => client
+                .update_page_properties(id, properties)
+                .await
+                .and_then(|obj| obj.into_result())
+                .map(|_| report.updated += 1),
+            None => client
+                .create_page(
+                    Parent::DatabaseId {
+                        database_id: database_id.to_string(),
+                    },
+                    properties,
+                    None,
+                )
+                .await
+                .and_then(|obj| obj.into_page())
+                .map(|page| {
+                    // Later rows with the same key update this one
+                    if let Some(key) = key {
+                        existing.insert(key, page.id);
+                    }
+                    report.created += 1;
+                }),
+        };
+        if let Err(e) = result {
+            report.errors.push(RowError {
+                line,
+                message: e.to_string(),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// A schema for a new database to import a CSV file into. The first column is the title and
+/// the types of the others are inferred from their values, see [`ImportOptions::add_columns`].
+pub fn infer_schema(
+    reader: impl Read,
+    options: &ImportOptions,
+) -> Result<DatabaseSchema, Box<dyn Error>> {
+    let delimiter = export::delimiter(options.delimiter.as_deref());
+    let (headers, records) = read(reader, &mut ImportReport::default())?;
+    let mut schema = DatabaseSchema::new();
+    for (index, header) in headers.iter().enumerate() {
+        let name = options.rename.get(header).unwrap_or(header);
+        schema = if index == 0 {
+            schema.title(name)
+        } else {
+            let values = records
+                .iter()
+                .map(|record| record.get(index).unwrap_or_default());
+            schema.property(name, infer(values, delimiter))
+        };
+    }
+    Ok(schema)
+}
+
+/// The headers and records of a CSV file, records that can't be read are reported
+fn read(
+    reader: impl Read,
+    report: &mut ImportReport,
+) -> Result<(Vec<String>, Vec<csv::StringRecord>), Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.iter().map(str::to_string).collect();
+    let mut records = Vec::new();
+    for record in reader.records() {
+        match record {
+            Ok(record) => records.push(record),
+            Err(e) => report.errors.push(RowError {
+                line: e.position().map_or(0, |p| p.line()),
+                message: e.to_string(),
+            }),
+        }
+    }
+    Ok((headers, records))
+}
+
+/// The type of property that fits every value in a column. Numbers, checkboxes, dates, emails
+/// and urls are recognized, columns that repeat a few values become selects and anything else is
+/// text. Multi-selects, people and relations are never guessed.
+fn infer<'a>(values: impl Iterator<Item = &'a str>, delimiter: &str) -> DatabasePropertyInput {
+    let values: Vec<_> = values.map(str::trim).filter(|v| !v.is_empty()).collect();
+    let all = |check: &dyn Fn(&str) -> bool| !values.is_empty() && values.iter().all(|v| check(v));
+    if all(&|v| parse_number(v, &NumberFormat::Percent).is_ok()) {
+        let format = if all(&|v| v.ends_with('%')) {
+            NumberFormat::Percent
+        } else {
+            NumberFormat::Number
+        };
+        return DatabasePropertyInput::Number(Number {
+            format,
+            extra: HashMap::new(),
+        });
+    }
+    if all(&|v| parse_checkbox(v).is_ok()) {
+        return DatabasePropertyInput::Checkbox(Empty {});
+    }
+    if all(&|v| parse_date(v).is_ok()) {
+        return DatabasePropertyInput::Date(Empty {});
+    }
+    if all(&|v| v.contains('@') && !v.contains(char::is_whitespace)) {
+        return DatabasePropertyInput::Email(Empty {});
+    }
+    if all(&|v| v.starts_with("https://") || v.starts_with("http://")) {
+        return DatabasePropertyInput::Url(Empty {});
+    }
+    let distinct: BTreeSet<_> = values.iter().copied().collect();
+    if distinct.len() <= MAX_INFERRED_OPTIONS
+        && values.len() >= 2 * distinct.len()
+        && distinct
+            .iter()
+            .all(|v| export::split(v, delimiter).len() == 1)
+    {
+        return DatabasePropertyInput::Select(SelectInput {
+            options: distinct
+                .into_iter()
+                .map(|name| SelectOptionsInput {
+                    name: name.to_string(),
+                    color: None,
+                })
+                .collect(),
+        });
+    }
+    DatabasePropertyInput::RichText(Empty {})
+}
+
+/// Properties that Notion computes itself can't be imported
+fn is_writable(property: &DatabaseProperty) -> bool {
+    !matches!(
+        property,
+        DatabaseProperty::Formula { .. }
+            | DatabaseProperty::Rollup { .. }
+            | DatabaseProperty::Files { .. }
+            | DatabaseProperty::CreatedTime { .. }
+            | DatabaseProperty::CreatedBy { .. }
+            | DatabaseProperty::LastEditedTime { .. }
+            | DatabaseProperty::LastEditedBy { .. }
+            | DatabaseProperty::Unknown { .. }
+    )
+}
+
+fn parse_row(
+    database: &DatabaseObject,
+    columns: &[(usize, String)],
+    record: &csv::StringRecord,
+    delimiter: &str,
+) -> Result<HashMap<String, PagePropertyValue>, String> {
+    let mut properties = HashMap::new();
+    for (index, name) in columns {
+        let text = record.get(*index).unwrap_or_default().trim();
+        if text.is_empty() {
+            continue;
+        }
+        let value = parse_value(&database.properties[name], text, delimiter)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        properties.insert(name.clone(), value);
+    }
+    Ok(properties)
+}
+
+fn parse_value(
+    property: &DatabaseProperty,
+    text: &str,
+    delimiter: &str,
+) -> Result<PagePropertyValue, String> {
+    let split = || export::split(text, delimiter).into_iter();
+    Ok(match property {
+        DatabaseProperty::Title { .. } => PagePropertyValue::Title {
+            title: vec![RichTextObject::text(text)],
+        },
+        DatabaseProperty::RichText { .. } => PagePropertyValue::RichText {
+            rich_text: vec![RichTextObject::text(text)],
+        },
+        DatabaseProperty::Number { number, .. } => PagePropertyValue::Number {
+            number: Some(parse_number(text, &number.format)?),
+        },
+        DatabaseProperty::Select { select, .. } => {
+            if !select.options.iter().any(|option| option.name == text) {
+                return Err(format!("{:?} isn't one of the options", text));
+            }
+            PagePropertyValue::Select {
+                select: Some(PageSelectOptions::named(text)),
+            }
+        }
+        DatabaseProperty::MultiSelect { multi_select, .. } => PagePropertyValue::MultiSelect {
+            multi_select: split()
+                .map(|name| {
+                    if multi_select
+                        .options
+                        .iter()
+                        .any(|option| option.name == name)
+                    {
+                        Ok(PageMultiSelectOptions::named(&name))
+                    } else {
+                        Err(format!("{:?} isn't one of the options", name))
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        DatabaseProperty::Date { .. } => PagePropertyValue::Date {
+            date: Some(parse_date(text)?),
+        },
+        DatabaseProperty::Checkbox { .. } => PagePropertyValue::Checkbox {
+            checkbox: parse_checkbox(text)?,
+        },
+        DatabaseProperty::Url { .. } => PagePropertyValue::Url {
+            url: Some(text.to_string()),
+        },
+        DatabaseProperty::Email { .. } => PagePropertyValue::Email {
+            email: Some(text.to_string()),
+        },
+        DatabaseProperty::PhoneNumber { .. } => PagePropertyValue::PhoneNumber {
+            phone_number: Some(text.to_string()),
+        },
+        DatabaseProperty::People { .. } => PagePropertyValue::People {
+            people: split().map(|id| UserObject::Partial { id }).collect(),
+        },
+        DatabaseProperty::Relation { .. } => PagePropertyValue::Relation {
+            relation: split()
+                .map(|id| PageRelation {
+                    id,
+                    extra: HashMap::new(),
+                })
+                .collect(),
+        },
+        property => return Err(format!("{} properties can't be imported", property.kind())),
+    })
+}
+
+/// Currency symbols around the number and commas between groups of three digits are allowed,
+/// and percentages are stored as fractions the way Notion does. Commas anywhere else, like the
+/// decimal comma in `1,5`, make the number ambiguous so it's rejected rather than guessed at.
+fn parse_number(text: &str, format: &NumberFormat) -> Result<f64, String> {
+    let error = || format!("{:?} isn't a number", text);
+    let symbols = |c: char| c.is_whitespace() || CURRENCY_SYMBOLS.contains(&c);
+    let (body, percent) = match text.trim().strip_suffix('%') {
+        Some(body) => (body, true),
+        None => (text, false),
+    };
+    let body = body.trim_matches(symbols);
+    // The sign can come before the currency symbol, as in `-$5`
+    let (sign, body) = match body.strip_prefix('-') {
+        Some(rest) => ("-", rest.trim_start_matches(symbols)),
+        None => ("", body),
+    };
+    if body.is_empty()
+        || !body
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '+' | '-' | 'e' | 'E'))
+    {
+        return Err(error());
+    }
+    if body.contains(',') {
+        let (whole, fraction) = body.split_once('.').unwrap_or((body, ""));
+        let whole = whole.strip_prefix('+').unwrap_or(whole);
+        let mut groups = whole.split(',');
+        let first = groups.next().unwrap_or_default();
+        let grouped = (1..=3).contains(&first.len())
+            && groups.all(|group| group.len() == 3)
+            && whole.chars().all(|c| c.is_ascii_digit() || c == ',');
+        if !grouped || fraction.contains(',') {
+            return Err(format!(
+                "{:?} is ambiguous, commas can only separate thousands",
+                text
+            ));
+        }
+    }
+    let number = format!("{}{}", sign, body.replace(',', ""))
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(error)?;
+    Ok(match format {
+        NumberFormat::Percent if percent => number / 100.0,
+        _ => number,
+    })
+}
+
+fn parse_checkbox(text: &str) -> Result<bool, String> {
+    match text.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "x" | "checked" => Ok(true),
+        "false" | "no" | "n" | "0" | "unchecked" => Ok(false),
+        _ => Err(format!("{:?} isn't a checkbox value", text)),
+    }
+}
+
+/// A date, a time or a range written `start/end` the same as the exporter does
+fn parse_date(text: &str) -> Result<PageDate, String> {
+    let (start, end) = match text.split_once('/') {
+        Some((start, end)) => (start, Some(end)),
+        None => (text, None),
+    };
+    Ok(PageDate {
+        start: parse_time(start)?,
+        end: end.map(parse_time).transpose()?,
+        extra: HashMap::new(),
+    })
+}
+
+fn parse_time(text: &str) -> Result<crate::time::Time, String> {
+    serde_json::from_value(serde_json::Value::String(text.trim().to_string()))
+        .map_err(|_| format!("{:?} isn't a date", text))
+}
+
+/// Options used in the file that the database's select and multi-select properties don't have
+fn missing_options(
+    database: &DatabaseObject,
+    columns: &[(usize, String)],
+    records: &[csv::StringRecord],
+    delimiter: &str,
+) -> DatabaseSchemaChanges {
+    let mut missing = BTreeMap::<&str, BTreeSet<String>>::new();
+    for (index, name) in columns {
+        let (known, multi) = match &database.properties[name] {
+            DatabaseProperty::Select { select, .. } => (
+                select.options.iter().map(|o| o.name.as_str()).collect(),
+                false,
+            ),
+            DatabaseProperty::MultiSelect { multi_select, .. } => (
+                multi_select
+                    .options
+                    .iter()
+                    .map(|o| o.name.as_str())
+                    .collect::<BTreeSet<_>>(),
+                true,
+            ),
+            _ => continue,
+        };
+        for record in records {
+            let text = record.get(*index).unwrap_or_default().trim();
+            let values = if multi {
+                export::split(text, delimiter)
+            } else {
+                vec![text.to_string()]
+            };
+            for value in values {
+                if !value.is_empty() && !known.contains(value.as_str()) {
+                    missing.entry(name.as_str()).or_default().insert(value);
+                }
+            }
+        }
+    }
+
+    let mut changes = DatabaseSchemaChanges::new();
+    for (name, added) in missing {
+        let added = added
+            .into_iter()
+            .map(|name| SelectOptionsInput { name, color: None });
+        changes = match database.properties[name].to_input() {
+            Some(DatabasePropertyInput::Select(mut input)) => {
+                input.options.extend(added);
+                changes.property(name, DatabasePropertyInput::Select(input))
+            }
+            Some(DatabasePropertyInput::MultiSelect(mut input)) => {
+                input.options.extend(added);
+                changes.property(name, DatabasePropertyInput::MultiSelect(input))
+            }
+            _ => changes,
+        };
+    }
+    changes
+}
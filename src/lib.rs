@@ -1,60 +1,133 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod backup;
 pub mod block;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cassette;
 pub mod database;
+pub mod diff;
+pub mod duplicate;
+mod endpoint;
 pub mod error;
+pub mod export;
+pub mod feed;
+pub mod graph;
+pub mod import;
+pub mod middleware;
+pub mod migration;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod oauth;
 pub mod page;
+pub mod pool;
+pub mod reconcile;
+pub mod restore;
 pub mod rich_text;
 pub mod search;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod template;
 pub mod time;
+pub mod transport;
+mod unknown;
 pub mod user;
 
 use self::{
-    block::{BlockObject, BlockObjectInput},
-    database::{DatabaseObject, QueryDatabaseFilter, QueryDatabaseSort},
-    error::ErrorObject,
+    block::{BlockObject, BlockObjectInput, BlockObjectUpdate, BlockTree},
+    cassette::Cassette,
+    database::{
+        DatabaseObject, DatabaseSchema, DatabaseSchemaChanges, QueryDatabaseFilter,
+        QueryDatabaseSort,
+    },
+    endpoint::ClientCore,
+    error::{ErrorObject, UnexpectedObject},
+    middleware::Middleware,
     page::{PageObject, PagePropertyValue, Parent},
+    rich_text::RichTextObject,
     search::{SearchFilter, SearchSort},
+    transport::Transport,
     user::UserObject,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, error::Error, future::Future};
+use std::{collections::HashMap, error::Error, future::Future, pin::Pin, sync::Arc, time::Instant};
+
+/// The future of a recursive async fn, which has to be boxed to have a known size
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
 
-const BASE_URL: &'static str = "https://api.notion.com/v1/";
 pub struct Client {
-    reqwest: reqwest::Client,
-    auth_token: String,
+    transport: Arc<dyn Transport>,
+    core: ClientCore,
 }
 
 impl Client {
-    const NOTION_VERSION: &'static str = "2021-05-13";
+    #[cfg(feature = "reqwest")]
     pub fn new(token: impl Into<String>) -> Self {
+        Self::with_transport(token, transport::ReqwestTransport::new())
+    }
+
+    /// A client that sends its requests through `transport` instead of reqwest
+    pub fn with_transport(token: impl Into<String>, transport: impl Transport + 'static) -> Self {
         Self {
-            reqwest: reqwest::Client::new(),
-            auth_token: token.into(),
+            transport: Arc::new(transport),
+            core: ClientCore::new(token.into()),
         }
     }
 
-    fn get(&self, url: String) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        async move {
-            Ok(serde_json::from_str(
-                &self
-                    .reqwest
-                    .get(url)
-                    .header("Notion-Version", Self::NOTION_VERSION)
-                    .header("Content-Type", "application/json")
-                    .bearer_auth(&self.auth_token)
-                    .send()
-                    .await?
-                    .text()
-                    .await?,
-            )?)
+    /// Send requests somewhere other than `https://api.notion.com/v1/`, e.g. a mock server. The
+    /// url should end with a `/`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.core.base_url = base_url.into();
+        self
+    }
+
+    /// Record every request and response to a cassette, or answer requests from one
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.core.cassette = Some(cassette);
+        self
+    }
+
+    /// Add a layer to the end of the middleware chain
+    pub fn with_middleware(mut self, layer: impl Middleware + 'static) -> Self {
+        self.core.middleware.push(Arc::new(layer));
+        self
+    }
+
+    async fn execute(&self, endpoint: endpoint::Built) -> Result<NotionObject, Box<dyn Error>> {
+        let endpoint = endpoint?;
+        if let Some(object) = self.core.replay(&endpoint)? {
+            return Ok(object);
         }
+        let request = self.core.request(&endpoint);
+        let call = async {
+            let started = Instant::now();
+            let response = match self.transport.send(request.clone()).await {
+                Ok(response) => response,
+                Err(error) => {
+                    self.core
+                        .failed(&endpoint, &request, &*error, started.elapsed());
+                    return Err(error);
+                }
+            };
+            self.core
+                .response(&endpoint, &request, response, started.elapsed())
+        };
+        #[cfg(feature = "tracing")]
+        let call = middleware::InSpans::new(self.core.spans(&endpoint, &request), call);
+        call.await
+    }
+
+    /// Fetch a file, e.g. one hosted by Notion, without sending the token along
+    pub(crate) async fn download(&self, url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let response = self.transport.send(endpoint::download(url)).await?;
+        endpoint::downloaded(url, response)
     }
 
     pub fn get_database(
         &self,
         id: &str,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        self.get(BASE_URL.to_string() + "databases/" + id)
+        self.execute(endpoint::get_database(id))
     }
 
     /// Currently broken on Notion's end and will return only an empty list as of 05-17-2021
@@ -63,20 +136,14 @@ impl Client {
         start_cursor: Option<String>,
         page_size: Option<i32>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        let query = match (start_cursor, page_size) {
-            (Some(c), Some(p)) => format!("?start_cursor={}&page_size={}", c, p),
-            (Some(c), None) => format!("?start_cursor={}", c),
-            (None, Some(p)) => format!("?page_size={}", p),
-            (None, None) => String::new(),
-        };
-        self.get(BASE_URL.to_string() + "databases" + &query)
+        self.execute(endpoint::list_databases(start_cursor, page_size))
     }
 
     pub fn get_user(
         &self,
         id: &str,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        self.get(BASE_URL.to_string() + "users/" + id)
+        self.execute(endpoint::get_user(id))
     }
 
     pub fn list_users(
@@ -84,13 +151,7 @@ impl Client {
         start_cursor: Option<String>,
         page_size: Option<i32>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        let query = match (start_cursor, page_size) {
-            (Some(c), Some(p)) => format!("?start_cursor={}&page_size={}", c, p),
-            (Some(c), None) => format!("?start_cursor={}", c),
-            (None, Some(p)) => format!("?page_size={}", p),
-            (None, None) => String::new(),
-        };
-        self.get(BASE_URL.to_string() + "users" + &query)
+        self.execute(endpoint::list_users(start_cursor, page_size))
     }
 
     pub fn get_block_children(
@@ -99,42 +160,21 @@ impl Client {
         start_cursor: Option<String>,
         page_size: Option<i32>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        let query = match (start_cursor, page_size) {
-            (Some(c), Some(p)) => format!("?start_cursor={}&page_size={}", c, p),
-            (Some(c), None) => format!("?start_cursor={}", c),
-            (None, Some(p)) => format!("?page_size={}", p),
-            (None, None) => String::new(),
-        };
-        self.get(BASE_URL.to_string() + "blocks/" + id + "/children" + &query)
+        self.execute(endpoint::get_block_children(id, start_cursor, page_size))
     }
 
-    pub fn get_page(
+    pub fn get_block(
         &self,
         id: &str,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        self.get(BASE_URL.to_string() + "pages/" + id)
+        self.execute(endpoint::get_block(id))
     }
 
-    fn patch(
+    pub fn get_page(
         &self,
-        url: String,
-        obj: Vec<u8>,
+        id: &str,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        async move {
-            Ok(serde_json::from_str(
-                &self
-                    .reqwest
-                    .patch(url)
-                    .header("Notion-Version", Self::NOTION_VERSION)
-                    .header("Content-Type", "application/json")
-                    .bearer_auth(&self.auth_token)
-                    .body(obj)
-                    .send()
-                    .await?
-                    .text()
-                    .await?,
-            )?)
-        }
+        self.execute(endpoint::get_page(id))
     }
 
     pub fn update_page_properties(
@@ -142,20 +182,16 @@ impl Client {
         id: &str,
         properties: HashMap<String, PagePropertyValue>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        #[derive(Serialize)]
-        struct PageProperties {
-            properties: HashMap<String, PagePropertyValue>,
-        }
+        self.execute(endpoint::update_page_properties(id, properties))
+    }
 
-        let id_str = id.to_string();
-        async move {
-            Ok(self
-                .patch(
-                    BASE_URL.to_string() + "pages/" + &id_str,
-                    serde_json::to_vec(&PageProperties { properties })?,
-                )
-                .await?)
-        }
+    pub fn update_database(
+        &self,
+        id: &str,
+        title: Option<Vec<RichTextObject>>,
+        schema_changes: Option<DatabaseSchemaChanges>,
+    ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
+        self.execute(endpoint::update_database(id, title, schema_changes))
     }
 
     pub fn append_block_children(
@@ -163,71 +199,51 @@ impl Client {
         id: &str,
         children: Vec<BlockObjectInput>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        #[derive(Serialize)]
-        struct BlockChildren {
-            children: Vec<BlockObjectInput>,
-        }
+        self.execute(endpoint::append_block_children(id, children))
+    }
 
-        let id_str = id.to_string();
-        async move {
-            Ok(self
-                .patch(
-                    BASE_URL.to_string() + "blocks/" + &id_str + "/children",
-                    serde_json::to_vec(&BlockChildren { children })?,
-                )
-                .await?)
-        }
+    /// Insert blocks after the child block `after` instead of at the end
+    pub fn append_block_children_after(
+        &self,
+        id: &str,
+        children: Vec<BlockObjectInput>,
+        after: &str,
+    ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
+        self.execute(endpoint::append_block_children_after(id, children, after))
     }
 
-    fn post(
+    pub fn update_block(
         &self,
-        url: String,
-        obj: Vec<u8>,
+        id: &str,
+        content: BlockObjectUpdate,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        async move {
-            Ok(serde_json::from_str(
-                &self
-                    .reqwest
-                    .post(url)
-                    .header("Notion-Version", Self::NOTION_VERSION)
-                    .header("Content-Type", "application/json")
-                    .bearer_auth(&self.auth_token)
-                    .body(obj)
-                    .send()
-                    .await?
-                    .text()
-                    .await?,
-            )?)
-        }
+        self.execute(endpoint::update_block(id, content))
     }
 
-    pub fn create_page(
+    /// Notion doesn't remove the block outright, it archives it and returns the archived block
+    pub fn delete_block(
         &self,
         id: &str,
+    ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
+        self.execute(endpoint::delete_block(id))
+    }
+
+    pub fn create_page(
+        &self,
         parent: Parent,
         properties: HashMap<String, PagePropertyValue>,
         children_blocks: Option<Vec<BlockObjectInput>>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        #[derive(Serialize)]
-        struct NewPage {
-            parent: Parent,
-            properties: HashMap<String, PagePropertyValue>,
-            children_blocks: Option<Vec<BlockObjectInput>>,
-        }
+        self.execute(endpoint::create_page(parent, properties, children_blocks))
+    }
 
-        let id_str = id.to_string();
-        async move {
-            Ok(self
-                .post(
-                    BASE_URL.to_string() + "pages/" + &id_str,
-                    serde_json::to_vec(&NewPage {
-                        parent,
-                        properties,
-                        children_blocks,
-                    })?,
-                )
-                .await?)
-        }
+    pub fn create_database(
+        &self,
+        parent_page: &str,
+        title: Vec<RichTextObject>,
+        schema: DatabaseSchema,
+    ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
+        self.execute(endpoint::create_database(parent_page, title, schema))
     }
 
     pub fn query_database(
@@ -238,28 +254,13 @@ impl Client {
         start_cursor: Option<String>,
         page_size: Option<i32>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        #[derive(Serialize)]
-        struct DatabaseQuery {
-            filter: Option<QueryDatabaseFilter>,
-            sorts: Option<Vec<QueryDatabaseSort>>,
-            start_cursor: Option<String>,
-            page_size: Option<i32>,
-        }
-
-        let id_str = id.to_string();
-        async move {
-            Ok(self
-                .post(
-                    BASE_URL.to_string() + "pages/" + &id_str,
-                    serde_json::to_vec(&DatabaseQuery {
-                        filter,
-                        sorts,
-                        start_cursor,
-                        page_size,
-                    })?,
-                )
-                .await?)
-        }
+        self.execute(endpoint::query_database(
+            id,
+            filter,
+            sorts,
+            start_cursor,
+            page_size,
+        ))
     }
 
     pub fn search(
@@ -270,35 +271,94 @@ impl Client {
         start_cursor: Option<String>,
         page_size: Option<i32>,
     ) -> impl Future<Output = Result<NotionObject, Box<dyn Error>>> + '_ {
-        #[derive(Serialize)]
-        struct Search {
-            query: Option<String>,
-            filter: Option<SearchFilter>,
-            sort: Option<SearchSort>,
-            start_cursor: Option<String>,
-            page_size: Option<i32>,
+        self.execute(endpoint::search(
+            query,
+            filter,
+            sort,
+            start_cursor,
+            page_size,
+        ))
+    }
+
+    /// Run a search and follow `next_cursor` until every result has been fetched
+    pub async fn search_all(
+        &self,
+        query: Option<String>,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<NotionObject>, Box<dyn Error>> {
+        let mut results = Vec::new();
+        let mut start_cursor = None;
+        loop {
+            let list = self
+                .search(query.clone(), filter.clone(), None, start_cursor, None)
+                .await?
+                .into_list()?;
+            results.extend(list.results);
+            match list.next_cursor {
+                Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                _ => return Ok(results),
+            }
         }
+    }
 
-        async move {
-            Ok(self
-                .post(
-                    BASE_URL.to_string() + "search",
-                    serde_json::to_vec(&Search {
-                        query,
-                        filter,
-                        sort,
-                        start_cursor,
-                        page_size,
-                    })?,
-                )
-                .await?)
+    /// Query a database and follow `next_cursor` until every row has been fetched
+    pub async fn query_database_all(
+        &self,
+        id: &str,
+        filter: Option<QueryDatabaseFilter>,
+        sorts: Option<Vec<QueryDatabaseSort>>,
+    ) -> Result<Vec<NotionObject>, Box<dyn Error>> {
+        let mut results = Vec::new();
+        let mut start_cursor = None;
+        loop {
+            let list = self
+                .query_database(id, filter.clone(), sorts.clone(), start_cursor, None)
+                .await?
+                .into_list()?;
+            results.extend(list.results);
+            match list.next_cursor {
+                Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                _ => return Ok(results),
+            }
         }
     }
+
+    /// Fetch every child of a block following `next_cursor`, along with all of their children.
+    /// Child pages and databases are included but not what's inside them.
+    pub fn get_block_tree(
+        &self,
+        id: &str,
+    ) -> BoxFuture<'_, Result<Vec<BlockTree>, Box<dyn Error>>> {
+        let id_str = id.to_string();
+        Box::pin(async move {
+            let mut tree = Vec::new();
+            let mut start_cursor = None;
+            loop {
+                let list = self
+                    .get_block_children(&id_str, start_cursor, None)
+                    .await?
+                    .into_list()?;
+                for result in list.results {
+                    let block = result.into_block()?;
+                    let children = match block.id() {
+                        Some(id) if block.has_nested_blocks() => self.get_block_tree(id).await?,
+                        _ => Vec::new(),
+                    };
+                    tree.push(BlockTree { block, children });
+                }
+                match list.next_cursor {
+                    Some(cursor) if list.has_more => start_cursor = Some(cursor),
+                    _ => return Ok(tree),
+                }
+            }
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "object")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum NotionObject {
     Database(DatabaseObject),
     Page(PageObject),
@@ -306,11 +366,74 @@ pub enum NotionObject {
     User(UserObject),
     List(List),
     Error(ErrorObject),
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(
+    NotionObject,
+    "object",
+    ["database", "page", "block", "user", "list", "error"]
+);
+
+impl NotionObject {
+    /// The value of the `object` field e.g. `page`
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::Database(_) => "database",
+            Self::Page(_) => "page",
+            Self::Block(_) => "block",
+            Self::User(_) => "user",
+            Self::List(_) => "list",
+            Self::Error(_) => "error",
+            Self::Unknown { type_name, .. } => type_name,
+        }
+    }
+
+    /// Turn an error response from Notion into an `Err`
+    pub fn into_result(self) -> Result<Self, Box<dyn Error>> {
+        match self {
+            Self::Error(e) => Err(Box::new(e)),
+            obj => Ok(obj),
+        }
+    }
+
+    pub fn into_list(self) -> Result<List, Box<dyn Error>> {
+        match self.into_result()? {
+            Self::List(list) => Ok(list),
+            obj => Err(Box::new(UnexpectedObject::new("list", &obj))),
+        }
+    }
+
+    pub fn into_page(self) -> Result<PageObject, Box<dyn Error>> {
+        match self.into_result()? {
+            Self::Page(page) => Ok(page),
+            obj => Err(Box::new(UnexpectedObject::new("page", &obj))),
+        }
+    }
+
+    pub fn into_database(self) -> Result<DatabaseObject, Box<dyn Error>> {
+        match self.into_result()? {
+            Self::Database(database) => Ok(database),
+            obj => Err(Box::new(UnexpectedObject::new("database", &obj))),
+        }
+    }
+
+    pub fn into_block(self) -> Result<BlockObject, Box<dyn Error>> {
+        match self.into_result()? {
+            Self::Block(block) => Ok(block),
+            obj => Err(Box::new(UnexpectedObject::new("block", &obj))),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct List {
     pub results: Vec<NotionObject>,
     pub next_cursor: Option<String>,
     pub has_more: bool,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
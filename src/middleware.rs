@@ -0,0 +1,342 @@
+//! Layers that see every API call a client makes. Each [`Middleware`] added with
+//! [`Client::with_middleware`](crate::Client::with_middleware) can look at and change a request
+//! before it's sent and the response before it's parsed. Requests pass through the layers in the
+//! order they were added and responses in the reverse order.
+//!
+//! Calls are named after the client method that made them e.g. `query_database`. Downloads of
+//! files and responses played back from a cassette don't go through the layers.
+use crate::transport::{Request, Response};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+pub trait Middleware: Send + Sync {
+    fn on_request(&self, _endpoint: &str, _request: &mut Request) {}
+
+    /// `latency` is the time from sending the request to getting the whole response
+    fn on_response(
+        &self,
+        _endpoint: &str,
+        _request: &Request,
+        _response: &mut Response,
+        _latency: Duration,
+    ) {
+    }
+
+    /// The transport failed to send the request or get a response
+    fn on_error(
+        &self,
+        _endpoint: &str,
+        _request: &Request,
+        _error: &dyn Error,
+        _latency: Duration,
+    ) {
+    }
+
+    /// A span to send the request in. It's entered while the transport sends the request and
+    /// while the layers see the response or error, so anything the transport logs lands inside it.
+    #[cfg(feature = "tracing")]
+    fn span(&self, _endpoint: &str, _request: &Request) -> Option<tracing::Span> {
+        None
+    }
+}
+
+/// Add headers to every request, replacing any with the same name
+#[derive(Debug, Clone, Default)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.push((name.into(), value.into()));
+        self
+    }
+}
+
+impl Middleware for Headers {
+    fn on_request(&self, _endpoint: &str, request: &mut Request) {
+        for (name, value) in &self.0 {
+            request
+                .headers
+                .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+            request.headers.push((name.clone(), value.clone()));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointMetrics {
+    pub requests: u64,
+    /// Responses with a 4xx or 5xx status and requests that got no response
+    pub errors: u64,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl EndpointMetrics {
+    pub fn mean_latency(&self) -> Duration {
+        match self.requests {
+            0 => Duration::default(),
+            requests => self.total_latency / requests as u32,
+        }
+    }
+
+    fn add(&mut self, latency: Duration, error: bool) {
+        self.requests += 1;
+        self.errors += error as u64;
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+    }
+}
+
+/// Counts requests, errors and latency per endpoint. Clones share their counts, so keep one and
+/// give a clone to the client.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<BTreeMap<String, EndpointMetrics>>>);
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn endpoint(&self, endpoint: &str) -> Option<EndpointMetrics> {
+        self.lock().get(endpoint).cloned()
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.lock().clone()
+    }
+
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, EndpointMetrics>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn add(&self, endpoint: &str, latency: Duration, error: bool) {
+        self.lock()
+            .entry(endpoint.to_string())
+            .or_default()
+            .add(latency, error);
+    }
+}
+
+impl Middleware for Metrics {
+    fn on_response(
+        &self,
+        endpoint: &str,
+        _request: &Request,
+        response: &mut Response,
+        latency: Duration,
+    ) {
+        self.add(endpoint, latency, response.status >= 400);
+    }
+
+    fn on_error(&self, endpoint: &str, _request: &Request, _error: &dyn Error, latency: Duration) {
+        self.add(endpoint, latency, true);
+    }
+}
+
+/// A `notion_request` span around every call with the endpoint and method, and the status and
+/// latency once the response is in. It holds an event at `info`, or `warn` for error statuses and
+/// failed requests.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tracing;
+
+#[cfg(feature = "tracing")]
+impl Middleware for Tracing {
+    fn span(&self, endpoint: &str, request: &Request) -> Option<tracing::Span> {
+        Some(tracing::info_span!(
+            "notion_request",
+            endpoint,
+            method = request.method.as_str(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        ))
+    }
+
+    fn on_response(
+        &self,
+        _endpoint: &str,
+        _request: &Request,
+        response: &mut Response,
+        latency: Duration,
+    ) {
+        let span = tracing::Span::current();
+        span.record("status", response.status);
+        span.record("latency_ms", latency.as_millis() as u64);
+        if response.status >= 400 {
+            tracing::warn!("Notion returned an error");
+        } else {
+            tracing::info!("request finished");
+        }
+    }
+
+    fn on_error(&self, _endpoint: &str, _request: &Request, error: &dyn Error, latency: Duration) {
+        tracing::Span::current().record("latency_ms", latency.as_millis() as u64);
+        tracing::warn!(error = %error, "request failed");
+    }
+}
+
+/// A future that enters the spans from the middleware every time it's polled
+#[cfg(feature = "tracing")]
+pub(crate) struct InSpans<F> {
+    spans: Vec<tracing::Span>,
+    future: std::pin::Pin<Box<F>>,
+}
+
+#[cfg(feature = "tracing")]
+impl<F: std::future::Future> InSpans<F> {
+    pub fn new(spans: Vec<tracing::Span>, future: F) -> Self {
+        Self {
+            spans,
+            future: Box::pin(future),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<F: std::future::Future> std::future::Future for InSpans<F> {
+    type Output = F::Output;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<F::Output> {
+        let this = self.get_mut();
+        let _entered = this
+            .spans
+            .iter()
+            .map(tracing::Span::enter)
+            .collect::<Vec<_>>();
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// Log requests and responses with their bodies at `debug`. The token and the values of
+/// `access_token`, `refresh_token` and `client_secret` fields are redacted, and bodies are cut
+/// off after `max_body` bytes.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct DebugLog {
+    pub max_body: usize,
+}
+
+#[cfg(feature = "tracing")]
+impl Default for DebugLog {
+    fn default() -> Self {
+        Self { max_body: 4096 }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl DebugLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn body(&self, request: &Request, body: &[u8]) -> String {
+        let token = request
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+            .and_then(|(_, value)| value.strip_prefix("Bearer "))
+            .filter(|token| !token.is_empty());
+        let mut text = match serde_json::from_slice(body) {
+            Ok(value) => redact_fields(value).to_string(),
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        };
+        if let Some(token) = token {
+            text = text.replace(token, REDACTED);
+        }
+        if text.len() > self.max_body {
+            let mut end = self.max_body;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push_str("...");
+        }
+        text
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Middleware for DebugLog {
+    fn on_request(&self, endpoint: &str, request: &mut Request) {
+        let headers = request
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                if name.eq_ignore_ascii_case("Authorization") {
+                    format!("{}: {}", name, REDACTED)
+                } else {
+                    format!("{}: {}", name, value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let body = request
+            .body
+            .as_deref()
+            .map(|body| self.body(request, body))
+            .unwrap_or_default();
+        tracing::debug!(
+            endpoint,
+            method = request.method.as_str(),
+            url = %request.url,
+            headers = %headers,
+            body = %body,
+            "sending request"
+        );
+    }
+
+    fn on_response(
+        &self,
+        endpoint: &str,
+        request: &Request,
+        response: &mut Response,
+        latency: Duration,
+    ) {
+        tracing::debug!(
+            endpoint,
+            status = response.status,
+            latency_ms = latency.as_millis() as u64,
+            body = %self.body(request, &response.body),
+            "received response"
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+const REDACTED: &str = "[redacted]";
+
+#[cfg(feature = "tracing")]
+fn redact_fields(value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        Value::Array(items) => items.into_iter().map(redact_fields).collect(),
+        Value::Object(fields) => fields
+            .into_iter()
+            .map(|(key, value)| match key.as_str() {
+                "access_token" | "refresh_token" | "client_secret" => {
+                    (key, Value::String(REDACTED.to_string()))
+                }
+                _ => (key, redact_fields(value)),
+            })
+            .collect(),
+        value => value,
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{
+    database::{
+        DatabaseColor, DatabaseObject, DatabaseProperty, DatabasePropertyInput,
+        DatabaseSchemaChanges, SelectInput, SelectOptions, SelectOptionsInput,
+    },
+    Client, NotionObject,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error, fmt};
+
+/// The schema a database should have. It can be built in code or loaded from a file, e.g.
+///
+/// ```json
+/// { "properties": [
+///     { "name": "Name", "title": {} },
+///     { "name": "Status", "renamed_from": ["State"], "select": { "options": [{ "name": "Done", "color": "green" }] } }
+/// ] }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DesiredSchema {
+    pub properties: Vec<DesiredProperty>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DesiredProperty {
+    pub name: String,
+    /// Older names of this property, if the live database uses one of them it gets renamed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed_from: Vec<String>,
+    #[serde(flatten)]
+    pub property: DatabasePropertyInput,
+}
+
+impl DesiredSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn property(mut self, name: impl Into<String>, property: DatabasePropertyInput) -> Self {
+        self.properties.push(DesiredProperty {
+            name: name.into(),
+            renamed_from: Vec::new(),
+            property,
+        });
+        self
+    }
+
+    pub fn renamed_property(
+        mut self,
+        name: impl Into<String>,
+        renamed_from: Vec<String>,
+        property: DatabasePropertyInput,
+    ) -> Self {
+        self.properties.push(DesiredProperty {
+            name: name.into(),
+            renamed_from,
+            property,
+        });
+        self
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Compare this schema with the live one and work out what needs to change. Properties in the
+    /// live database that aren't listed here are removed, apart from the title property which
+    /// every database has to keep.
+    pub fn diff(&self, live: &DatabaseObject) -> MigrationPlan {
+        let mut steps = Vec::new();
+        let mut matched = Vec::new();
+
+        for desired in &self.properties {
+            // A database has exactly one title property, so it's matched by kind and renamed to
+            // the desired name rather than added next to the live one
+            let current = match desired.property {
+                DatabasePropertyInput::Title(_) => live
+                    .properties
+                    .iter()
+                    .find(|(_, property)| matches!(property, DatabaseProperty::Title { .. }))
+                    .map(|(name, _)| name),
+                _ => Some(&desired.name)
+                    .into_iter()
+                    .chain(desired.renamed_from.iter())
+                    .find(|name| live.properties.contains_key(*name)),
+            };
+
+            let current = match current {
+                Some(current) => current,
+                None => {
+                    steps.push(MigrationStep::AddProperty {
+                        name: desired.name.clone(),
+                        property: desired.property.clone(),
+                    });
+                    continue;
+                }
+            };
+            matched.push(current.clone());
+
+            if current != &desired.name {
+                steps.push(MigrationStep::RenameProperty {
+                    name: current.clone(),
+                    new_name: desired.name.clone(),
+                });
+            }
+
+            let live_property = &live.properties[current];
+            if live_property.kind() != desired.property.kind() {
+                steps.push(MigrationStep::RetypeProperty {
+                    name: current.clone(),
+                    from: live_property.kind().to_string(),
+                    property: desired.property.clone(),
+                });
+                continue;
+            }
+
+            match (live_property, &desired.property) {
+                (
+                    DatabaseProperty::Select { select, .. },
+                    DatabasePropertyInput::Select(SelectInput { options }),
+                ) => {
+                    steps.extend(diff_options(current, &select.options, options, false));
+                }
+                (
+                    DatabaseProperty::MultiSelect { multi_select, .. },
+                    DatabasePropertyInput::MultiSelect(SelectInput { options }),
+                ) => {
+                    let live_options = multi_select
+                        .options
+                        .iter()
+                        .map(|o| SelectOptions {
+                            name: o.name.clone(),
+                            id: o.id.clone(),
+                            color: o.color.clone(),
+                            extra: HashMap::new(),
+                        })
+                        .collect::<Vec<_>>();
+                    steps.extend(diff_options(current, &live_options, options, true));
+                }
+                (DatabaseProperty::Number { number, .. }, DatabasePropertyInput::Number(n))
+                    if number.format != n.format =>
+                {
+                    steps.push(MigrationStep::ReconfigureProperty {
+                        name: current.clone(),
+                        property: desired.property.clone(),
+                    });
+                }
+                (DatabaseProperty::Formula { formula, .. }, DatabasePropertyInput::Formula(f))
+                    if formula.expression != f.expression =>
+                {
+                    steps.push(MigrationStep::ReconfigureProperty {
+                        name: current.clone(),
+                        property: desired.property.clone(),
+                    });
+                }
+                (
+                    DatabaseProperty::Relation { relation, .. },
+                    DatabasePropertyInput::Relation(r),
+                ) if relation.database_id != r.database_id => {
+                    steps.push(MigrationStep::ReconfigureProperty {
+                        name: current.clone(),
+                        property: desired.property.clone(),
+                    });
+                }
+                (DatabaseProperty::Rollup { rollup, .. }, DatabasePropertyInput::Rollup(r))
+                    if rollup.relation_property_name != r.relation_property_name
+                        || rollup.rollup_property_name != r.rollup_property_name
+                        || rollup.function != r.function =>
+                {
+                    steps.push(MigrationStep::ReconfigureProperty {
+                        name: current.clone(),
+                        property: desired.property.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let mut removed = live
+            .properties
+            .iter()
+            .filter(|(name, property)| {
+                !matched.contains(name) && !matches!(property, DatabaseProperty::Title { .. })
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        removed.sort();
+        steps.extend(
+            removed
+                .into_iter()
+                .map(|name| MigrationStep::RemoveProperty { name }),
+        );
+
+        MigrationPlan {
+            database_id: live.id.clone(),
+            steps,
+        }
+    }
+}
+
+fn diff_options(
+    name: &str,
+    live: &[SelectOptions],
+    desired: &[SelectOptionsInput],
+    multi_select: bool,
+) -> Vec<MigrationStep> {
+    let mut added = Vec::new();
+    let mut recolored = Vec::new();
+    for option in desired {
+        match live.iter().find(|o| o.name == option.name) {
+            None => added.push(option.clone()),
+            Some(current) => match &option.color {
+                Some(color) if *color != current.color => recolored.push(OptionRecolor {
+                    option: option.name.clone(),
+                    from: current.color.clone(),
+                    to: color.clone(),
+                }),
+                _ => {}
+            },
+        }
+    }
+
+    if added.is_empty() && recolored.is_empty() {
+        return Vec::new();
+    }
+
+    // Notion replaces the whole option list so the untouched options have to be sent as well
+    let options = live
+        .iter()
+        .map(|o| SelectOptionsInput {
+            name: o.name.clone(),
+            color: Some(
+                recolored
+                    .iter()
+                    .find(|r| r.option == o.name)
+                    .map_or_else(|| o.color.clone(), |r| r.to.clone()),
+            ),
+        })
+        .chain(added.iter().cloned())
+        .collect();
+    let options = SelectInput { options };
+
+    vec![MigrationStep::UpdateOptions {
+        name: name.to_string(),
+        added,
+        recolored,
+        property: if multi_select {
+            DatabasePropertyInput::MultiSelect(options)
+        } else {
+            DatabasePropertyInput::Select(options)
+        },
+    }]
+}
+
+/// A list of changes needed to bring a database in line with a [`DesiredSchema`]. Applying an
+/// empty plan does nothing so it's safe to diff and apply on every run.
+#[derive(Debug)]
+pub struct MigrationPlan {
+    pub database_id: String,
+    pub steps: Vec<MigrationStep>,
+}
+
+#[derive(Debug)]
+pub enum MigrationStep {
+    AddProperty {
+        name: String,
+        property: DatabasePropertyInput,
+    },
+    RenameProperty {
+        name: String,
+        new_name: String,
+    },
+    /// Changing a property's type can throw away the values stored in it
+    RetypeProperty {
+        name: String,
+        from: String,
+        property: DatabasePropertyInput,
+    },
+    ReconfigureProperty {
+        name: String,
+        property: DatabasePropertyInput,
+    },
+    UpdateOptions {
+        name: String,
+        added: Vec<SelectOptionsInput>,
+        recolored: Vec<OptionRecolor>,
+        property: DatabasePropertyInput,
+    },
+    RemoveProperty {
+        name: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct OptionRecolor {
+    pub option: String,
+    pub from: DatabaseColor,
+    pub to: DatabaseColor,
+}
+
+impl MigrationStep {
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Self::RetypeProperty { .. } | Self::RemoveProperty { .. }
+        )
+    }
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn is_destructive(&self) -> bool {
+        self.steps.iter().any(MigrationStep::is_destructive)
+    }
+
+    /// The schema changes to send to Notion, every step is keyed by the property's current name
+    pub fn changes(&self) -> DatabaseSchemaChanges {
+        self.steps
+            .iter()
+            .fold(DatabaseSchemaChanges::new(), |changes, step| match step {
+                MigrationStep::AddProperty { name, property }
+                | MigrationStep::RetypeProperty { name, property, .. }
+                | MigrationStep::ReconfigureProperty { name, property }
+                | MigrationStep::UpdateOptions { name, property, .. } => {
+                    changes.property(name, property.clone())
+                }
+                MigrationStep::RenameProperty { name, new_name } => changes.rename(name, new_name),
+                MigrationStep::RemoveProperty { name } => changes.remove(name),
+            })
+    }
+
+    /// Run the plan against the database. Plans that retype or remove properties are refused
+    /// unless `allow_destructive` is set. Returns `None` if there was nothing to do.
+    pub async fn apply(
+        &self,
+        client: &Client,
+        allow_destructive: bool,
+    ) -> Result<Option<NotionObject>, Box<dyn error::Error>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        if self.is_destructive() && !allow_destructive {
+            return Err(Box::new(MigrationError {
+                database_id: self.database_id.clone(),
+                steps: self
+                    .steps
+                    .iter()
+                    .filter(|s| s.is_destructive())
+                    .map(ToString::to_string)
+                    .collect(),
+            }));
+        }
+
+        match client
+            .update_database(&self.database_id, None, Some(self.changes()))
+            .await?
+        {
+            NotionObject::Error(e) => Err(Box::new(e)),
+            obj => Ok(Some(obj)),
+        }
+    }
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddProperty { name, property } => {
+                write!(f, "+ add {:?} ({})", name, property.kind())
+            }
+            Self::RenameProperty { name, new_name } => {
+                write!(f, "~ rename {:?} -> {:?}", name, new_name)
+            }
+            Self::RetypeProperty {
+                name,
+                from,
+                property,
+            } => write!(f, "! retype {:?} {} -> {}", name, from, property.kind()),
+            Self::ReconfigureProperty { name, property } => {
+                write!(f, "~ reconfigure {:?} ({})", name, property.kind())
+            }
+            Self::UpdateOptions {
+                name,
+                added,
+                recolored,
+                ..
+            } => {
+                let mut changes =
+                    added
+                        .iter()
+                        .map(|o| format!("+ option {:?}", o.name))
+                        .chain(recolored.iter().map(|r| {
+                            format!("~ option {:?} {:?} -> {:?}", r.option, r.from, r.to)
+                        }));
+                write!(f, "~ options of {:?}:", name)?;
+                changes.try_for_each(|c| write!(f, "\n    {}", c))
+            }
+            Self::RemoveProperty { name } => write!(f, "- remove {:?}", name),
+        }
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Database {} is up to date", self.database_id);
+        }
+        writeln!(f, "Migration plan for database {}:", self.database_id)?;
+        for step in &self.steps {
+            writeln!(f, "  {}", step)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationError {
+    pub database_id: String,
+    pub steps: Vec<String>,
+}
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Refusing to apply destructive migration to database {} without confirmation:",
+            self.database_id
+        )?;
+        for step in &self.steps {
+            writeln!(f, "  {}", step)?;
+        }
+        Ok(())
+    }
+}
+impl error::Error for MigrationError {}
@@ -0,0 +1,1410 @@
+//! An in-process stand-in for the Notion API to test against. [`MockServer`] listens on
+//! localhost and serves the endpoints [`Client`] uses from an in-memory store that can be seeded
+//! from fixtures or a backup. Database queries evaluate filters and sorts, lists are paginated
+//! with cursors like Notion's, and every request is recorded so tests can check what was sent.
+//!
+//! Failures can be queued up with [`MockServer::fail_next`] to test how code copes with rate
+//! limits, outages and validation errors.
+//!
+//! The server also stands in for the OAuth token endpoint, answering with the tokens registered
+//! with [`MockServer::grant`].
+use crate::{
+    block::BlockTree,
+    database::DatabaseObject,
+    oauth::{OAuthClient, TokenResponse},
+    page::PageObject,
+    restore::Snapshot,
+    user::UserObject,
+    Client, NotionObject,
+};
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, VecDeque},
+    error::Error,
+    fs,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// Objects to start the server with
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub databases: Vec<DatabaseObject>,
+    #[serde(default)]
+    pub pages: Vec<PageObject>,
+    /// Block trees keyed by the page or block they're under
+    #[serde(default)]
+    pub blocks: BTreeMap<String, Vec<BlockTree>>,
+    #[serde(default)]
+    pub users: Vec<UserObject>,
+}
+
+impl Fixtures {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_json(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// What the server answers the next request with instead of handling it
+#[derive(Debug, Clone)]
+pub enum MockFailure {
+    /// A 429 with a `Retry-After` header of this many seconds
+    RateLimited { retry_after: u64 },
+    /// A 500
+    ServerError,
+    /// A 400 `validation_error` with this message
+    Validation(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The path after `/v1/` e.g. `databases/<id>/query`
+    pub path: String,
+    pub query: BTreeMap<String, String>,
+    /// Header names are lowercase
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Value>,
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct State {
+    databases: Vec<Value>,
+    pages: Vec<Value>,
+    blocks: BTreeMap<String, Value>,
+    /// The ids of the blocks under each page and block, in order
+    children: BTreeMap<String, Vec<String>>,
+    users: Vec<Value>,
+    /// OAuth codes that haven't been exchanged yet and the tokens they're for
+    grants: BTreeMap<String, Value>,
+    failures: VecDeque<MockFailure>,
+    requests: Vec<RecordedRequest>,
+    next_id: u64,
+}
+
+/// A status, error code and message to send back as an `ErrorObject`
+struct Failure(u16, &'static str, String);
+
+type Handled = Result<Value, Failure>;
+
+impl MockServer {
+    /// Start listening on a free port on localhost. The server stops when this is dropped.
+    pub async fn start() -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let shared = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = shared.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, state).await;
+                });
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            task,
+        })
+    }
+
+    /// The base url to point a client at
+    pub fn url(&self) -> String {
+        format!("http://{}/v1/", self.address)
+    }
+
+    /// A client that talks to this server
+    pub fn client(&self) -> Client {
+        Client::new("secret_mock").with_base_url(self.url())
+    }
+
+    /// An OAuth client whose codes are exchanged by this server
+    pub fn oauth_client(&self) -> OAuthClient {
+        OAuthClient::new("mock_client_id", "mock_client_secret").with_base_url(self.url())
+    }
+
+    /// Answer the first exchange of `code` with `token`
+    pub fn grant(&self, code: &str, token: &TokenResponse) {
+        let value = serde_json::to_value(token).unwrap_or(Value::Null);
+        self.lock().grants.insert(code.to_string(), value);
+    }
+
+    pub fn seed(&self, fixtures: &Fixtures) {
+        for database in &fixtures.databases {
+            self.seed_database(database);
+        }
+        for page in &fixtures.pages {
+            self.seed_page(page);
+        }
+        for (parent, trees) in &fixtures.blocks {
+            self.seed_blocks(parent, trees);
+        }
+        for user in &fixtures.users {
+            self.seed_user(user);
+        }
+    }
+
+    /// Serve everything in a backup
+    pub fn seed_snapshot(&self, snapshot: &Snapshot) {
+        for database in snapshot.databases.values() {
+            self.seed_database(database);
+        }
+        for page in snapshot.pages.values() {
+            self.seed_page(page);
+        }
+        for (parent, trees) in &snapshot.blocks {
+            self.seed_blocks(parent, trees);
+        }
+    }
+
+    pub fn seed_database(&self, database: &DatabaseObject) {
+        let value = object("database", database);
+        let mut state = self.lock();
+        state.databases.retain(|d| !same_id(&d["id"], &database.id));
+        state.databases.push(value);
+    }
+
+    pub fn seed_page(&self, page: &PageObject) {
+        let value = object("page", page);
+        let mut state = self.lock();
+        state.pages.retain(|p| !same_id(&p["id"], &page.id));
+        state.pages.push(value);
+    }
+
+    /// Add blocks and everything nested under them to the end of a page or block
+    pub fn seed_blocks(&self, parent: &str, trees: &[BlockTree]) {
+        let mut state = self.lock();
+        let parent = state
+            .canonical(parent)
+            .unwrap_or_else(|| parent.to_string());
+        state.seed_trees(&parent, trees);
+    }
+
+    pub fn seed_user(&self, user: &UserObject) {
+        let value = object("user", user);
+        let mut state = self.lock();
+        state.users.retain(|u| u["id"] != value["id"]);
+        state.users.push(value);
+    }
+
+    /// Answer the next request with a failure, queued failures are used up in order
+    pub fn fail_next(&self, failure: MockFailure) {
+        self.lock().failures.push_back(failure);
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    pub fn clear_requests(&self) {
+        self.lock().requests.clear();
+    }
+
+    /// The current state of a page, to check what a test changed
+    pub fn page(&self, id: &str) -> Option<PageObject> {
+        let state = self.lock();
+        let page = state.pages.iter().find(|p| same_id(&p["id"], id))?;
+        stored(page)?.into_page().ok()
+    }
+
+    pub fn database(&self, id: &str) -> Option<DatabaseObject> {
+        let state = self.lock();
+        let database = state.databases.iter().find(|d| same_id(&d["id"], id))?;
+        stored(database)?.into_database().ok()
+    }
+
+    /// The blocks under a page or block, with their children
+    pub fn blocks(&self, id: &str) -> Vec<BlockTree> {
+        let state = self.lock();
+        match state.canonical(id) {
+            Some(id) => state.trees(&id),
+            None => Vec::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Handle requests on a connection until the client closes it
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<State>>) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 8192];
+    loop {
+        let head_end = loop {
+            if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        };
+
+        let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default().to_string();
+        let target = request_line.next().unwrap_or_default().to_string();
+        let headers: BTreeMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let length: usize = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        while buffer.len() < head_end + length {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        let body = buffer[head_end..head_end + length].to_vec();
+        buffer.drain(..head_end + length);
+
+        let close = headers
+            .get("connection")
+            .is_some_and(|c| c.eq_ignore_ascii_case("close"));
+        let (status, extra, body) = {
+            let mut state = state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.respond(&method, &target, headers, &body)
+        };
+        let body = body.to_string();
+        let mut response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            status,
+            reason(status),
+            body.len()
+        );
+        for (name, value) in extra {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        response.push_str(&body);
+        stream.write_all(response.as_bytes()).await?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+impl State {
+    fn respond(
+        &mut self,
+        method: &str,
+        target: &str,
+        headers: BTreeMap<String, String>,
+        body: &[u8],
+    ) -> (u16, Vec<(String, String)>, Value) {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (target, ""),
+        };
+        let path = path.trim_start_matches('/');
+        let path = path.strip_prefix("v1/").unwrap_or(path).to_string();
+        let query: BTreeMap<String, String> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let parsed = if body.is_empty() {
+            Ok(None)
+        } else {
+            serde_json::from_slice::<Value>(body).map(Some)
+        };
+        self.requests.push(RecordedRequest {
+            method: method.to_string(),
+            path: path.clone(),
+            query: query.clone(),
+            headers: headers.clone(),
+            body: parsed.as_ref().ok().cloned().flatten(),
+        });
+
+        let scheme = if path == "oauth/token" {
+            "Basic "
+        } else {
+            "Bearer "
+        };
+        let result = if !headers
+            .get("authorization")
+            .is_some_and(|auth| auth.starts_with(scheme) && auth.len() > scheme.len())
+        {
+            Err(Failure(401, "unauthorized", "API token is invalid.".into()))
+        } else if let Some(failure) = self.failures.pop_front() {
+            let (status, code, message, extra) = match failure {
+                MockFailure::RateLimited { retry_after } => (
+                    429,
+                    "rate_limited",
+                    "You have been rate limited. Please try again in a few minutes.".to_string(),
+                    vec![("Retry-After".to_string(), retry_after.to_string())],
+                ),
+                MockFailure::ServerError => (
+                    500,
+                    "internal_server_error",
+                    "Unexpected error occurred.".to_string(),
+                    Vec::new(),
+                ),
+                MockFailure::Validation(message) => (400, "validation_error", message, Vec::new()),
+            };
+            return (status, extra, error(status, code, &message));
+        } else {
+            match parsed {
+                Ok(body) => self.route(method, &path, &query, body.unwrap_or(Value::Null)),
+                Err(e) => Err(Failure(400, "invalid_json", e.to_string())),
+            }
+        };
+        match result {
+            Ok(value) => (200, Vec::new(), value),
+            // The token endpoint answers with an OAuth error rather than an error object
+            Err(Failure(status, code, message)) if path == "oauth/token" => (
+                status,
+                Vec::new(),
+                json!({ "error": code, "error_description": message }),
+            ),
+            Err(Failure(status, code, message)) => {
+                (status, Vec::new(), error(status, code, &message))
+            }
+        }
+    }
+
+    fn route(
+        &mut self,
+        method: &str,
+        path: &str,
+        query: &BTreeMap<String, String>,
+        body: Value,
+    ) -> Handled {
+        let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("GET", ["databases"]) => {
+                let databases = self.databases.clone();
+                paginate(databases, query.get("start_cursor"), page_size(query))
+            }
+            ("POST", ["databases"]) => self.create_database(body),
+            ("GET", ["databases", id]) => self.find_database(id).cloned(),
+            ("PATCH", ["databases", id]) => self.update_database(id, body),
+            ("POST", ["databases", id, "query"]) => self.query_database(id, body),
+            ("POST", ["pages"]) => self.create_page(body),
+            ("GET", ["pages", id]) => self.find_page(id).cloned(),
+            ("PATCH", ["pages", id]) => self.update_page(id, body),
+            ("GET", ["blocks", id]) => self.find_block(id).cloned(),
+            ("PATCH", ["blocks", id]) => self.update_block(id, body),
+            ("DELETE", ["blocks", id]) => self.delete_block(id),
+            ("GET", ["blocks", id, "children"]) => {
+                let parent = self.parent_id(id)?;
+                let children = self.child_blocks(&parent);
+                paginate(children, query.get("start_cursor"), page_size(query))
+            }
+            ("PATCH", ["blocks", id, "children"]) => self.append_children(id, body),
+            ("GET", ["users"]) => {
+                let users = self.users.clone();
+                paginate(users, query.get("start_cursor"), page_size(query))
+            }
+            ("GET", ["users", id]) => self
+                .users
+                .iter()
+                .find(|u| same_id(&u["id"], id))
+                .cloned()
+                .ok_or_else(|| not_found(id)),
+            ("POST", ["search"]) => self.search(body),
+            ("POST", ["oauth", "token"]) => self.exchange_code(body),
+            _ => Err(Failure(
+                400,
+                "invalid_request_url",
+                "Invalid request URL.".into(),
+            )),
+        }
+    }
+
+    fn exchange_code(&mut self, body: Value) -> Handled {
+        if body["grant_type"] != "authorization_code" {
+            return Err(Failure(
+                400,
+                "unsupported_grant_type",
+                "grant_type should be `authorization_code`.".into(),
+            ));
+        }
+        let code = body["code"].as_str().unwrap_or_default();
+        self.grants.remove(code).ok_or_else(|| {
+            Failure(
+                400,
+                "invalid_grant",
+                "Invalid code or the code has already been used.".into(),
+            )
+        })
+    }
+
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("00000000-0000-4000-8000-{:012x}", self.next_id)
+    }
+
+    fn find_database(&self, id: &str) -> Result<&Value, Failure> {
+        self.databases
+            .iter()
+            .find(|d| same_id(&d["id"], id))
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn find_page(&self, id: &str) -> Result<&Value, Failure> {
+        self.pages
+            .iter()
+            .find(|p| same_id(&p["id"], id))
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn find_block(&self, id: &str) -> Result<&Value, Failure> {
+        self.canonical(id)
+            .and_then(|id| self.blocks.get(&id))
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// The stored form of an id of a page or block, which may have been given without dashes
+    fn canonical(&self, id: &str) -> Option<String> {
+        self.pages
+            .iter()
+            .map(|p| &p["id"])
+            .chain(self.blocks.values().map(|b| &b["id"]))
+            .find(|stored| same_id(stored, id))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    /// The page or block children can go under
+    fn parent_id(&self, id: &str) -> Result<String, Failure> {
+        self.canonical(id).ok_or_else(|| not_found(id))
+    }
+
+    fn child_blocks(&self, parent: &str) -> Vec<Value> {
+        self.children
+            .get(parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.blocks.get(id).cloned())
+            .collect()
+    }
+
+    fn trees(&self, parent: &str) -> Vec<BlockTree> {
+        self.children
+            .get(parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| {
+                Some(BlockTree {
+                    block: stored(self.blocks.get(id)?)?.into_block().ok()?,
+                    children: self.trees(id),
+                })
+            })
+            .collect()
+    }
+
+    fn seed_trees(&mut self, parent: &str, trees: &[BlockTree]) {
+        for tree in trees {
+            let mut value = object("block", &tree.block);
+            let id = value["id"].as_str().unwrap_or_default().to_string();
+            if !tree.children.is_empty() {
+                value["has_children"] = Value::Bool(true);
+            }
+            self.blocks.insert(id.clone(), value);
+            self.children
+                .entry(parent.to_string())
+                .or_default()
+                .push(id.clone());
+            self.seed_trees(&id, &tree.children);
+        }
+        self.set_has_children(parent);
+    }
+
+    fn set_has_children(&mut self, parent: &str) {
+        let has_children = self.children.get(parent).is_some_and(|c| !c.is_empty());
+        if let Some(block) = self.blocks.get_mut(parent) {
+            block["has_children"] = Value::Bool(has_children);
+        }
+    }
+
+    fn create_database(&mut self, body: Value) -> Handled {
+        let parent = body.get("parent").cloned().unwrap_or(Value::Null);
+        let page_id = parent["page_id"]
+            .as_str()
+            .ok_or_else(|| validation("body.parent.page_id should be defined"))?;
+        self.find_page(page_id)?;
+        let mut properties = Map::new();
+        for (name, input) in body["properties"].as_object().into_iter().flatten() {
+            let id = self.new_id()[24..].to_string();
+            properties.insert(name.clone(), database_property(id, input)?);
+        }
+        let now = now();
+        let mut title = body.get("title").cloned().unwrap_or(json!([]));
+        normalize_text(&mut title);
+        let database = json!({
+            "object": "database",
+            "id": self.new_id(),
+            "created_time": now,
+            "last_edited_time": now,
+            "parent": parent,
+            "title": title,
+            "properties": properties,
+        });
+        self.databases.push(database.clone());
+        Ok(database)
+    }
+
+    fn update_database(&mut self, id: &str, body: Value) -> Handled {
+        let mut ids = Vec::new();
+        for _ in body["properties"].as_object().into_iter().flatten() {
+            ids.push(self.new_id()[24..].to_string());
+        }
+        let database = self
+            .databases
+            .iter_mut()
+            .find(|d| same_id(&d["id"], id))
+            .ok_or_else(|| not_found(id))?;
+        if let Some(title) = body.get("title") {
+            let mut title = title.clone();
+            normalize_text(&mut title);
+            database["title"] = title;
+        }
+        let changes = body["properties"].as_object().cloned().unwrap_or_default();
+        let properties = database["properties"]
+            .as_object_mut()
+            .ok_or_else(|| validation("Database has no properties"))?;
+        for ((key, change), new_id) in changes.into_iter().zip(ids) {
+            // Properties can be referred to by name or id
+            let name = properties
+                .iter()
+                .find(|(name, property)| **name == key || property["id"] == key.as_str())
+                .map(|(name, _)| name.clone());
+            if change.is_null() {
+                let name = name.ok_or_else(|| {
+                    validation(&format!("Could not find property with name or id: {}", key))
+                })?;
+                properties.remove(&name);
+                continue;
+            }
+            let mut property = match &name {
+                Some(name) => properties.remove(name).unwrap_or_default(),
+                None => Value::Null,
+            };
+            let mut config = change.clone();
+            let new_name = config
+                .as_object_mut()
+                .and_then(|c| c.remove("name"))
+                .and_then(|n| n.as_str().map(str::to_string));
+            if config.as_object().is_some_and(|c| !c.is_empty()) {
+                let id = property["id"].as_str().unwrap_or(&new_id).to_string();
+                property = database_property(id, &config)?;
+            } else if property.is_null() {
+                return Err(validation(&format!(
+                    "Could not find property with name or id: {}",
+                    key
+                )));
+            }
+            let name = new_name.or(name).unwrap_or(key);
+            properties.insert(name, property);
+        }
+        database["last_edited_time"] = Value::String(now());
+        Ok(database.clone())
+    }
+
+    fn query_database(&mut self, id: &str, body: Value) -> Handled {
+        let database_id = self.find_database(id)?["id"].clone();
+        let mut rows = Vec::new();
+        for page in &self.pages {
+            if page["parent"]["database_id"] != database_id || page["archived"] == true {
+                continue;
+            }
+            let keep = match body.get("filter") {
+                Some(filter) if !filter.is_null() => matches(filter, page)?,
+                _ => true,
+            };
+            if keep {
+                rows.push(page.clone());
+            }
+        }
+        if let Some(sorts) = body.get("sorts").and_then(Value::as_array) {
+            for sort in sorts {
+                if sort.get("property").is_none() && sort.get("timestamp").is_none() {
+                    return Err(validation("Sorts need a property or a timestamp"));
+                }
+            }
+            rows.sort_by(|a, b| {
+                sorts
+                    .iter()
+                    .map(|sort| {
+                        let ordering = match sort.get("property").and_then(Value::as_str) {
+                            Some(name) => compare(
+                                &cell(&a["properties"][name]),
+                                &cell(&b["properties"][name]),
+                            ),
+                            None => {
+                                let field = sort["timestamp"].as_str().unwrap_or_default();
+                                compare(&Cell::Date(text(&a[field])), &Cell::Date(text(&b[field])))
+                            }
+                        };
+                        if sort["direction"] == "descending" {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        }
+                    })
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        paginate(
+            rows,
+            body.get("start_cursor").and_then(Value::as_str),
+            body.get("page_size").and_then(Value::as_u64),
+        )
+    }
+
+    fn create_page(&mut self, body: Value) -> Handled {
+        let parent = body.get("parent").cloned().unwrap_or(Value::Null);
+        let schema = if let Some(database_id) = parent["database_id"].as_str() {
+            Some(self.find_database(database_id)?["properties"].clone())
+        } else if let Some(page_id) = parent["page_id"].as_str() {
+            self.find_page(page_id)?;
+            None
+        } else {
+            return Err(validation("body.parent should be a database or a page"));
+        };
+        let id = self.new_id();
+        let now = now();
+        let mut page = json!({
+            "object": "page",
+            "id": id,
+            "created_time": now,
+            "last_edited_time": now,
+            "archived": false,
+            "parent": parent,
+            "properties": {},
+        });
+        self.set_properties(&mut page, &body["properties"], schema.as_ref())?;
+        if let Some(children) = body.get("children").and_then(Value::as_array) {
+            self.append(&id, children, None)?;
+        }
+        self.pages.push(page.clone());
+        Ok(page)
+    }
+
+    fn update_page(&mut self, id: &str, body: Value) -> Handled {
+        let mut page = self.find_page(id)?.clone();
+        let schema = match page["parent"]["database_id"].as_str() {
+            Some(database_id) => self
+                .find_database(database_id)
+                .ok()
+                .map(|d| d["properties"].clone()),
+            None => None,
+        };
+        self.set_properties(&mut page, &body["properties"], schema.as_ref())?;
+        if let Some(archived) = body.get("archived").and_then(Value::as_bool) {
+            page["archived"] = Value::Bool(archived);
+        }
+        page["last_edited_time"] = Value::String(now());
+        if let Some(stored) = self.pages.iter_mut().find(|p| same_id(&p["id"], id)) {
+            *stored = page.clone();
+        }
+        Ok(page)
+    }
+
+    fn set_properties(
+        &mut self,
+        page: &mut Value,
+        inputs: &Value,
+        schema: Option<&Value>,
+    ) -> Result<(), Failure> {
+        for (name, input) in inputs.as_object().into_iter().flatten() {
+            let kind = input
+                .as_object()
+                .and_then(|fields| fields.keys().find(|k| *k != "id" && *k != "type"))
+                .ok_or_else(|| validation(&format!("{} is not a property value", name)))?
+                .clone();
+            let id = match schema {
+                Some(schema) => {
+                    let property = &schema[name];
+                    if property.is_null() {
+                        return Err(validation(&format!(
+                            "{} is not a property that exists.",
+                            name
+                        )));
+                    }
+                    if property["type"] != kind.as_str() {
+                        return Err(validation(&format!(
+                            "{} is expected to be {}.",
+                            name,
+                            property["type"].as_str().unwrap_or_default()
+                        )));
+                    }
+                    property["id"].clone()
+                }
+                None if kind == "title" => Value::String("title".into()),
+                None => {
+                    return Err(validation(&format!(
+                        "{} can't be set on a page that isn't in a database.",
+                        name
+                    )))
+                }
+            };
+            let mut value = input[&kind].clone();
+            normalize_text(&mut value);
+            let options = schema.map(|schema| &schema[name][&kind]["options"]);
+            match kind.as_str() {
+                "select" if !value.is_null() => fill_option(&mut value, options),
+                "multi_select" => {
+                    for option in value.as_array_mut().into_iter().flatten() {
+                        fill_option(option, options);
+                    }
+                }
+                _ => {}
+            }
+            let mut property = json!({ "id": id, "type": kind });
+            property[&kind] = value;
+            page["properties"][name] = property;
+        }
+        Ok(())
+    }
+
+    fn update_block(&mut self, id: &str, body: Value) -> Handled {
+        let id = self.find_block(id)?["id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let block = self.blocks.get_mut(&id).ok_or_else(|| not_found(&id))?;
+        let kind = block["type"].as_str().unwrap_or_default().to_string();
+        for (key, value) in body.as_object().into_iter().flatten() {
+            if key == "archived" {
+                continue;
+            }
+            if *key != kind {
+                return Err(validation(&format!(
+                    "Block type {} doesn't match the block's type {}.",
+                    key, kind
+                )));
+            }
+            let mut value = value.clone();
+            normalize_text(&mut value);
+            block[&kind] = value;
+        }
+        block["last_edited_time"] = Value::String(now());
+        let block = block.clone();
+        if body.get("archived") == Some(&Value::Bool(true)) {
+            return self.delete_block(&id);
+        }
+        Ok(block)
+    }
+
+    fn delete_block(&mut self, id: &str) -> Handled {
+        let id = self.find_block(id)?["id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let mut parents = Vec::new();
+        for (parent, children) in &mut self.children {
+            if children.contains(&id) {
+                children.retain(|child| *child != id);
+                parents.push(parent.clone());
+            }
+        }
+        for parent in parents {
+            self.set_has_children(&parent);
+        }
+        let block = self.blocks.remove(&id).ok_or_else(|| not_found(&id))?;
+        let mut block = block;
+        block["archived"] = Value::Bool(true);
+        Ok(block)
+    }
+
+    fn append_children(&mut self, id: &str, body: Value) -> Handled {
+        let parent = self.parent_id(id)?;
+        let children = body["children"]
+            .as_array()
+            .ok_or_else(|| validation("body.children should be an array"))?
+            .clone();
+        if children.len() > 100 {
+            return Err(validation(&format!(
+                "body.children.length should be ≤ `100`, instead was `{}`.",
+                children.len()
+            )));
+        }
+        let after = body
+            .get("after")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let created = self.append(&parent, &children, after.as_deref())?;
+        Ok(json!({
+            "object": "list",
+            "results": created,
+            "next_cursor": null,
+            "has_more": false,
+        }))
+    }
+
+    /// Create blocks from their inputs under `parent`, after the block `after` or at the end
+    fn append(
+        &mut self,
+        parent: &str,
+        inputs: &[Value],
+        after: Option<&str>,
+    ) -> Result<Vec<Value>, Failure> {
+        let start = match after {
+            Some(after) => {
+                self.children
+                    .get(parent)
+                    .and_then(|c| c.iter().position(|child| same_id(&json!(child), after)))
+                    .ok_or_else(|| validation(&format!("{} is not a child of {}", after, parent)))?
+                    + 1
+            }
+            None => self.children.get(parent).map_or(0, Vec::len),
+        };
+        let mut created = Vec::new();
+        for (index, input) in (start..).zip(inputs) {
+            let kind = input["type"]
+                .as_str()
+                .or_else(|| {
+                    input
+                        .as_object()?
+                        .keys()
+                        .find(|k| *k != "object")
+                        .map(String::as_str)
+                })
+                .ok_or_else(|| validation("Block has no type"))?
+                .to_string();
+            let mut content = input[&kind].clone();
+            normalize_text(&mut content);
+            let nested = content
+                .as_object_mut()
+                .and_then(|c| c.remove("children"))
+                .and_then(|c| c.as_array().cloned());
+            let id = self.new_id();
+            let now = now();
+            let mut block = json!({
+                "object": "block",
+                "id": id,
+                "type": kind,
+                "created_time": now,
+                "last_edited_time": now,
+                "has_children": false,
+            });
+            block[&kind] = content;
+            self.blocks.insert(id.clone(), block);
+            self.children
+                .entry(parent.to_string())
+                .or_default()
+                .insert(index, id.clone());
+            if let Some(nested) = nested {
+                self.append(&id, &nested, None)?;
+            }
+            created.push(self.blocks[&id].clone());
+        }
+        self.set_has_children(parent);
+        Ok(created)
+    }
+
+    fn search(&mut self, body: Value) -> Handled {
+        let query = body["query"].as_str().unwrap_or_default().to_lowercase();
+        let kind = match (&body["filter"]["property"], &body["filter"]["value"]) {
+            (Value::String(property), Value::String(value)) if property == "object" => {
+                Some(value.clone())
+            }
+            (Value::Null, _) => None,
+            _ => return Err(validation("body.filter.property should be `object`")),
+        };
+        let mut results: Vec<Value> = self
+            .pages
+            .iter()
+            .filter(|page| page["archived"] != true)
+            .chain(&self.databases)
+            .filter(|object| {
+                kind.as_ref()
+                    .is_none_or(|kind| object["object"] == kind.as_str())
+            })
+            .filter(|object| query.is_empty() || title(object).to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+        if let Some(field) = body["sort"]["timestamp"].as_str() {
+            results.sort_by(|a, b| {
+                let ordering = compare(&Cell::Date(text(&a[field])), &Cell::Date(text(&b[field])));
+                if body["sort"]["direction"] == "descending" {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        paginate(
+            results,
+            body.get("start_cursor").and_then(Value::as_str),
+            body.get("page_size").and_then(Value::as_u64),
+        )
+    }
+}
+
+/// A seeded object with the `object` field Notion sends with it
+/// Stored objects keep their `object` field, which only `NotionObject` expects
+fn stored(value: &Value) -> Option<NotionObject> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+fn object(kind: &str, value: &impl Serialize) -> Value {
+    let mut value = serde_json::to_value(value).unwrap_or(Value::Null);
+    value["object"] = Value::String(kind.to_string());
+    value
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Notion accepts ids with or without dashes
+fn same_id(stored: &Value, id: &str) -> bool {
+    stored
+        .as_str()
+        .is_some_and(|stored| stored.replace('-', "") == id.replace('-', ""))
+}
+
+fn error(status: u16, code: &str, message: &str) -> Value {
+    json!({ "object": "error", "status": status, "code": code, "message": message })
+}
+
+fn not_found(id: &str) -> Failure {
+    Failure(
+        404,
+        "object_not_found",
+        format!(
+            "Could not find object with ID: {}. Make sure the relevant pages and databases are \
+             shared with your integration.",
+            id
+        ),
+    )
+}
+
+fn validation(message: &str) -> Failure {
+    Failure(400, "validation_error", message.to_string())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+fn page_size(query: &BTreeMap<String, String>) -> Option<u64> {
+    query.get("page_size").and_then(|size| size.parse().ok())
+}
+
+/// One page of results starting at the object with the id `cursor`, Notion's cursors are the
+/// id of the first result on the next page
+fn paginate(items: Vec<Value>, cursor: Option<impl AsRef<str>>, size: Option<u64>) -> Handled {
+    let size = size.unwrap_or(100).clamp(1, 100) as usize;
+    let start = match cursor {
+        Some(cursor) => items
+            .iter()
+            .position(|item| same_id(&item["id"], cursor.as_ref()))
+            .ok_or_else(|| validation("start_cursor should be a valid cursor"))?,
+        None => 0,
+    };
+    let end = (start + size).min(items.len());
+    let next_cursor = items.get(end).map(|item| item["id"].clone());
+    Ok(json!({
+        "object": "list",
+        "results": items[start..end],
+        "next_cursor": next_cursor,
+        "has_more": next_cursor.is_some(),
+    }))
+}
+
+/// A stored database property from its input e.g. `{ "number": { "format": "dollar" } }`
+fn database_property(id: String, input: &Value) -> Result<Value, Failure> {
+    let (kind, config) = input
+        .as_object()
+        .and_then(|fields| {
+            fields
+                .iter()
+                .find(|(key, _)| *key != "id" && *key != "type")
+        })
+        .ok_or_else(|| validation("Property schema should have a type"))?;
+    let mut config = config.clone();
+    let options = config.get_mut("options").and_then(Value::as_array_mut);
+    for option in options.into_iter().flatten() {
+        fill_option(option, None);
+    }
+    let mut property = json!({ "id": id, "type": kind });
+    property[kind] = config;
+    Ok(property)
+}
+
+/// Give a select option the id and color of the schema's option with the same name
+fn fill_option(option: &mut Value, options: Option<&Value>) {
+    let known = options
+        .and_then(Value::as_array)
+        .and_then(|options| options.iter().find(|o| o["name"] == option["name"]));
+    for field in ["id", "color"] {
+        if option[field].is_null() {
+            option[field] = match known {
+                Some(known) => known[field].clone(),
+                None if field == "color" => Value::String("default".into()),
+                None => Value::String(option["name"].as_str().unwrap_or_default().to_string()),
+            };
+        }
+    }
+}
+
+/// Fill in the `plain_text`, `href` and `annotations` of any rich text that only has its content
+fn normalize_text(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(normalize_text),
+        Value::Object(fields) => {
+            if fields.get("type") == Some(&json!("text")) && fields.contains_key("text") {
+                let content = fields["text"]["content"].clone();
+                let link = fields["text"]["link"]["url"].clone();
+                fields.entry("plain_text").or_insert(content);
+                fields.entry("href").or_insert(link);
+                fields.entry("annotations").or_insert(json!({
+                    "bold": false,
+                    "italic": false,
+                    "strikethrough": false,
+                    "underline": false,
+                    "code": false,
+                    "color": "default",
+                }));
+                if fields["text"].get("link").is_none() {
+                    fields["text"]["link"] = Value::Null;
+                }
+            } else {
+                fields.values_mut().for_each(normalize_text);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn plain_text(text: &Value) -> String {
+    text.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|span| span["plain_text"].as_str())
+        .collect()
+}
+
+fn title(object: &Value) -> String {
+    if object["object"] == "database" {
+        return plain_text(&object["title"]);
+    }
+    object["properties"]
+        .as_object()
+        .into_iter()
+        .flat_map(|properties| properties.values())
+        .find(|property| property["type"] == "title")
+        .map(|property| plain_text(&property["title"]))
+        .unwrap_or_default()
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+/// A property value reduced to what filters and sorts compare
+#[derive(Debug)]
+enum Cell {
+    Text(String),
+    Number(Option<f64>),
+    Bool(bool),
+    Date(Option<String>),
+    List(Vec<String>),
+}
+
+fn cell(property: &Value) -> Cell {
+    let kind = property["type"].as_str().unwrap_or_default();
+    let value = &property[kind];
+    match kind {
+        "title" | "rich_text" => Cell::Text(plain_text(value)),
+        "number" => Cell::Number(value.as_f64()),
+        "checkbox" => Cell::Bool(value.as_bool().unwrap_or(false)),
+        "select" => Cell::Text(value["name"].as_str().unwrap_or_default().to_string()),
+        "multi_select" => Cell::List(names(value, "name")),
+        "date" => Cell::Date(text(&value["start"])),
+        "created_time" | "last_edited_time" => Cell::Date(text(value)),
+        "people" | "relation" => Cell::List(names(value, "id")),
+        "created_by" | "last_edited_by" => Cell::List(text(&value["id"]).into_iter().collect()),
+        "files" => Cell::List(names(value, "name")),
+        "formula" | "rollup" => match value["type"].as_str().unwrap_or_default() {
+            "string" => Cell::Text(value["string"].as_str().unwrap_or_default().to_string()),
+            "number" => Cell::Number(value["number"].as_f64()),
+            "boolean" => Cell::Bool(value["boolean"].as_bool().unwrap_or(false)),
+            "date" => Cell::Date(text(&value["date"]["start"])),
+            "array" => Cell::List(
+                value["array"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|item| match cell(item) {
+                        Cell::Text(text) => text,
+                        other => format!("{:?}", other),
+                    })
+                    .collect(),
+            ),
+            _ => Cell::Text(String::new()),
+        },
+        _ => Cell::Text(value.as_str().unwrap_or_default().to_string()),
+    }
+}
+
+fn names(value: &Value, field: &str) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| text(&item[field]))
+        .collect()
+}
+
+/// Whether a page passes a filter in the shape Notion takes
+fn matches(filter: &Value, page: &Value) -> Result<bool, Failure> {
+    if let Some(filters) = filter.get("or").and_then(Value::as_array) {
+        for filter in filters {
+            if matches(filter, page)? {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+    if let Some(filters) = filter.get("and").and_then(Value::as_array) {
+        for filter in filters {
+            if !matches(filter, page)? {
+                return Ok(false);
+            }
+        }
+        return Ok(true);
+    }
+    let name = filter["property"]
+        .as_str()
+        .ok_or_else(|| validation("body.filter.property should be defined"))?;
+    let property = &page["properties"][name];
+    if property.is_null() {
+        return Err(validation(&format!(
+            "Could not find property with name or id: {}",
+            name
+        )));
+    }
+    let (kind, condition) = filter
+        .as_object()
+        .and_then(|fields| fields.iter().find(|(key, _)| *key != "property"))
+        .ok_or_else(|| validation("body.filter should have a condition"))?;
+    if property["type"] != kind.as_str() {
+        return Err(validation(&format!(
+            "Property {} is of type {}, not {}",
+            name, property["type"], kind
+        )));
+    }
+    let value = if kind == "formula" {
+        let (_, inner) = condition
+            .as_object()
+            .and_then(|fields| fields.iter().next())
+            .ok_or_else(|| validation("body.filter.formula should have a condition"))?;
+        return evaluate(&cell(property), inner);
+    } else {
+        cell(property)
+    };
+    evaluate(&value, condition)
+}
+
+fn evaluate(cell: &Cell, condition: &Value) -> Result<bool, Failure> {
+    let conditions = condition
+        .as_object()
+        .ok_or_else(|| validation("Filter conditions should be an object"))?;
+    for (operator, argument) in conditions {
+        let passed = match (cell, operator.as_str()) {
+            (_, "is_empty") => is_empty(cell),
+            (_, "is_not_empty") => !is_empty(cell),
+            (Cell::Text(text), operator) => {
+                let argument = argument.as_str().unwrap_or_default();
+                match operator {
+                    "equals" => text == argument,
+                    "does_not_equal" => text != argument,
+                    "contains" => text.to_lowercase().contains(&argument.to_lowercase()),
+                    "does_not_contain" => !text.to_lowercase().contains(&argument.to_lowercase()),
+                    "starts_with" => text.starts_with(argument),
+                    "ends_with" => text.ends_with(argument),
+                    _ => return Err(unknown_operator(operator)),
+                }
+            }
+            (Cell::Number(number), operator) => {
+                let argument = argument
+                    .as_f64()
+                    .or_else(|| argument.as_str()?.parse().ok())
+                    .ok_or_else(|| validation("Number filters need a number"))?;
+                let number = match number {
+                    Some(number) => *number,
+                    None => {
+                        if operator == "does_not_equal" {
+                            continue;
+                        }
+                        return Ok(false);
+                    }
+                };
+                match operator {
+                    "equals" => number == argument,
+                    "does_not_equal" => number != argument,
+                    "greater_than" => number > argument,
+                    "less_than" => number < argument,
+                    "greater_than_or_equal_to" => number >= argument,
+                    "less_than_or_equal_to" => number <= argument,
+                    _ => return Err(unknown_operator(operator)),
+                }
+            }
+            (Cell::Bool(checked), operator) => {
+                let argument = argument
+                    .as_bool()
+                    .or_else(|| argument.as_str()?.parse().ok())
+                    .ok_or_else(|| validation("Checkbox filters need a boolean"))?;
+                match operator {
+                    "equals" => *checked == argument,
+                    "does_not_equal" => *checked != argument,
+                    _ => return Err(unknown_operator(operator)),
+                }
+            }
+            (Cell::Date(date), operator) => {
+                let date = match date.as_deref().and_then(instant) {
+                    Some(date) => date,
+                    None => return Ok(false),
+                };
+                let now = Utc::now();
+                let relative = |days: i64| {
+                    let other = now + Duration::days(days);
+                    if days < 0 {
+                        other <= date && date <= now
+                    } else {
+                        now <= date && date <= other
+                    }
+                };
+                match operator {
+                    "past_week" => relative(-7),
+                    "past_month" => relative(-30),
+                    "past_year" => relative(-365),
+                    "next_week" => relative(7),
+                    "next_month" => relative(30),
+                    "next_year" => relative(365),
+                    operator => {
+                        let argument = argument
+                            .as_str()
+                            .and_then(instant)
+                            .ok_or_else(|| validation("Date filters need a date"))?;
+                        match operator {
+                            "equals" => date == argument,
+                            "before" => date < argument,
+                            "after" => date > argument,
+                            "on_or_before" => date <= argument,
+                            "on_or_after" => date >= argument,
+                            _ => return Err(unknown_operator(operator)),
+                        }
+                    }
+                }
+            }
+            (Cell::List(items), operator) => {
+                let argument = argument.as_str().unwrap_or_default();
+                let contains = items.iter().any(|item| {
+                    item == argument || item.replace('-', "") == argument.replace('-', "")
+                });
+                match operator {
+                    "contains" => contains,
+                    "does_not_contain" => !contains,
+                    _ => return Err(unknown_operator(operator)),
+                }
+            }
+        };
+        if !passed {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn unknown_operator(operator: &str) -> Failure {
+    validation(&format!("{} is not a supported filter condition", operator))
+}
+
+fn is_empty(cell: &Cell) -> bool {
+    match cell {
+        Cell::Text(text) => text.is_empty(),
+        Cell::Number(number) => number.is_none(),
+        Cell::Bool(_) => false,
+        Cell::Date(date) => date.is_none(),
+        Cell::List(items) => items.is_empty(),
+    }
+}
+
+/// Empty values sort last, like in Notion
+fn compare(a: &Cell, b: &Cell) -> Ordering {
+    match (is_empty(a), is_empty(b)) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+    match (a, b) {
+        (Cell::Number(Some(a)), Cell::Number(Some(b))) => {
+            a.partial_cmp(b).unwrap_or(Ordering::Equal)
+        }
+        (Cell::Bool(a), Cell::Bool(b)) => a.cmp(b),
+        (Cell::Date(Some(a)), Cell::Date(Some(b))) => match (instant(a), instant(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        },
+        (Cell::List(a), Cell::List(b)) => a.cmp(b),
+        (Cell::Text(a), Cell::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        _ => Ordering::Equal,
+    }
+}
+
+/// An RFC 3339 time, or midnight UTC for a plain date
+fn instant(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+            Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+        })
+}
@@ -0,0 +1,293 @@
+//! The OAuth flow for public integrations. Send the user to
+//! [`OAuthClient::authorization_url`], and when Notion redirects back with a `code` trade it for
+//! an access token with [`OAuthClient::exchange_code`]. The [`TokenResponse`] says which
+//! workspace the token is for, and [`OAuthClient::client`] makes a [`Client`] for it.
+//!
+//! Every workspace that installs the integration gets its own token, so they're kept in a
+//! [`TokenStore`] keyed by workspace id.
+use crate::{
+    endpoint::{BASE_URL, NOTION_VERSION},
+    transport::{Method, Request, Transport},
+    user::UserObject,
+    Client, NotionObject,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+pub struct OAuthClient {
+    transport: Arc<dyn Transport>,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: Option<String>,
+    base_url: String,
+}
+
+impl OAuthClient {
+    #[cfg(feature = "reqwest")]
+    pub fn new(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self::with_transport(
+            client_id,
+            client_secret,
+            crate::transport::ReqwestTransport::new(),
+        )
+    }
+
+    pub fn with_transport(
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        transport: impl Transport + 'static,
+    ) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            redirect_uri: None,
+            base_url: BASE_URL.to_string(),
+        }
+    }
+
+    /// Where Notion sends the user back to. Only needed when the integration has more than one
+    /// redirect uri, and has to be the same when authorizing and exchanging the code.
+    pub fn with_redirect_uri(mut self, redirect_uri: impl Into<String>) -> Self {
+        self.redirect_uri = Some(redirect_uri.into());
+        self
+    }
+
+    /// Talk to something other than `https://api.notion.com/v1/`, e.g. a local stand-in for the
+    /// token endpoint. The url should end with a `/`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// The page to send the user to so they can pick the pages to share. `state` is sent back
+    /// with the code and should be unguessable and checked when it comes back.
+    pub fn authorization_url(&self, state: &str) -> String {
+        let mut url = format!(
+            "{}oauth/authorize?client_id={}&response_type=code&owner=user",
+            self.base_url,
+            percent_encode(&self.client_id)
+        );
+        if let Some(redirect_uri) = &self.redirect_uri {
+            url.push_str("&redirect_uri=");
+            url.push_str(&percent_encode(redirect_uri));
+        }
+        url.push_str("&state=");
+        url.push_str(&percent_encode(state));
+        url
+    }
+
+    /// Trade the `code` Notion redirected back with for an access token
+    pub fn exchange_code(
+        &self,
+        code: &str,
+    ) -> impl Future<Output = Result<TokenResponse, Box<dyn Error>>> + '_ {
+        #[derive(Serialize)]
+        struct TokenRequest<'a> {
+            grant_type: &'a str,
+            code: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            redirect_uri: Option<&'a str>,
+        }
+
+        let body = serde_json::to_vec(&TokenRequest {
+            grant_type: "authorization_code",
+            code,
+            redirect_uri: self.redirect_uri.as_deref(),
+        });
+        async move {
+            let credentials = format!("{}:{}", self.client_id, self.client_secret);
+            let response = self
+                .transport
+                .send(Request {
+                    method: Method::Post,
+                    url: self.base_url.clone() + "oauth/token",
+                    headers: vec![
+                        ("Notion-Version".to_string(), NOTION_VERSION.to_string()),
+                        ("Content-Type".to_string(), "application/json".to_string()),
+                        (
+                            "Authorization".to_string(),
+                            format!("Basic {}", base64(credentials.as_bytes())),
+                        ),
+                    ],
+                    body: Some(body?),
+                })
+                .await?;
+            if response.status >= 400 {
+                if let Ok(error) = serde_json::from_slice::<OAuthError>(&response.body) {
+                    return Err(error.with_status(response.status).into());
+                }
+                serde_json::from_slice::<NotionObject>(&response.body)?.into_result()?;
+                return Err(
+                    format!("Token exchange failed with status {}", response.status).into(),
+                );
+            }
+            Ok(serde_json::from_slice::<TokenResponse>(&response.body)?)
+        }
+    }
+
+    /// A client that acts for the workspace with this token, sending through the same transport
+    /// and to the same base url as the OAuth client
+    pub fn client(&self, token: &TokenResponse) -> Client {
+        Client::with_transport(token.access_token.clone(), self.transport.clone())
+            .with_base_url(self.base_url.clone())
+    }
+}
+
+/// What Notion sends back for a code
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub bot_id: String,
+    pub workspace_id: String,
+    pub workspace_name: Option<String>,
+    /// A url, an emoji or `None`
+    pub workspace_icon: Option<String>,
+    pub owner: Owner,
+    /// The page the user duplicated from the integration's template, if there is one
+    #[serde(default)]
+    pub duplicated_template_id: Option<String>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl TokenResponse {
+    /// A client that acts for the workspace with this token and talks to
+    /// `https://api.notion.com/v1/` through `transport`
+    pub fn client_with_transport(&self, transport: impl Transport + 'static) -> Client {
+        Client::with_transport(self.access_token.clone(), transport)
+    }
+}
+
+/// Who can see the integration in the workspace
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
+pub enum Owner {
+    /// Everyone in the workspace
+    Workspace { workspace: bool },
+    /// Only the user who installed it
+    User { user: UserObject },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(Owner, "type", ["workspace", "user"]);
+
+/// The error the token endpoint answers with, e.g. `invalid_grant` for a used or expired code
+#[derive(Deserialize, Debug, Clone)]
+pub struct OAuthError {
+    #[serde(default)]
+    pub status: u16,
+    pub error: String,
+    #[serde(default)]
+    pub error_description: Option<String>,
+}
+
+impl OAuthError {
+    fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OAuth error {} ({})", self.error, self.status)?;
+        if let Some(description) = &self.error_description {
+            write!(f, ": {}", description)?;
+        }
+        Ok(())
+    }
+}
+impl Error for OAuthError {}
+
+/// Where the tokens for each workspace are kept
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn get(&self, workspace_id: &str) -> Result<Option<TokenResponse>, Box<dyn Error>>;
+    /// Save a token under its workspace id, replacing any older one
+    async fn put(&self, token: TokenResponse) -> Result<(), Box<dyn Error>>;
+    async fn remove(&self, workspace_id: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// Tokens kept in memory for as long as the process runs
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore(RwLock<HashMap<String, TokenResponse>>);
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn get(&self, workspace_id: &str) -> Result<Option<TokenResponse>, Box<dyn Error>> {
+        Ok(self
+            .0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(workspace_id)
+            .cloned())
+    }
+
+    async fn put(&self, token: TokenResponse) -> Result<(), Box<dyn Error>> {
+        self.0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(token.workspace_id.clone(), token);
+        Ok(())
+    }
+
+    async fn remove(&self, workspace_id: &str) -> Result<(), Box<dyn Error>> {
+        self.0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(workspace_id);
+        Ok(())
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
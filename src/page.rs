@@ -1,8 +1,8 @@
 use crate::{rich_text::RichTextObject, time::Time, user::UserObject};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageObject {
     pub id: String,
     pub created_time: Time,
@@ -10,20 +10,48 @@ pub struct PageObject {
     pub archived: bool,
     pub parent: Parent,
     pub properties: HashMap<String, PageProperty>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl PageObject {
+    /// The plain text of the page's title property
+    pub fn title(&self) -> Option<String> {
+        self.properties
+            .values()
+            .find_map(|property| match property {
+                PageProperty::Title { title, .. } => {
+                    Some(title.iter().map(RichTextObject::plain_text).collect())
+                }
+                _ => None,
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum Parent {
-    DatabaseId { database_id: String },
-    PageId { page_id: String },
+    DatabaseId {
+        database_id: String,
+    },
+    PageId {
+        page_id: String,
+    },
     Workspace,
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
+crate::unknown::tagged_enum!(Parent, "type", ["database_id", "page_id", "workspace"]);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum PageProperty {
     RichText {
         id: String,
@@ -31,11 +59,11 @@ pub enum PageProperty {
     },
     Number {
         id: String,
-        number: f64,
+        number: Option<f64>,
     },
     Select {
         id: String,
-        select: PageSelectOptions,
+        select: Option<PageSelectOptions>,
     },
     MultiSelect {
         id: String,
@@ -43,7 +71,7 @@ pub enum PageProperty {
     },
     Date {
         id: String,
-        date: PageDate,
+        date: Option<PageDate>,
     },
     Formula {
         id: String,
@@ -75,15 +103,15 @@ pub enum PageProperty {
     },
     Url {
         id: String,
-        url: String,
+        url: Option<String>,
     },
     Email {
         id: String,
-        email: String,
+        email: Option<String>,
     },
     PhoneNumber {
         id: String,
-        phone_number: String,
+        phone_number: Option<String>,
     },
     CreatedTime {
         id: String,
@@ -101,22 +129,161 @@ pub enum PageProperty {
         id: String,
         last_edited_by: UserObject,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(
+    PageProperty,
+    "type",
+    [
+        "rich_text",
+        "number",
+        "select",
+        "multi_select",
+        "date",
+        "formula",
+        "relation",
+        "rollup",
+        "title",
+        "people",
+        "files",
+        "checkbox",
+        "url",
+        "email",
+        "phone_number",
+        "created_time",
+        "created_by",
+        "last_edited_time",
+        "last_edited_by"
+    ]
+);
+
+impl PageProperty {
+    /// The value to send to Notion to set a property to this, properties Notion computes itself
+    /// like formulas, rollups and timestamps can't be written and return `None`
+    pub fn to_value(&self) -> Option<PagePropertyValue> {
+        Some(match self.clone() {
+            Self::RichText { rich_text, .. } => PagePropertyValue::RichText { rich_text },
+            Self::Number { number, .. } => PagePropertyValue::Number { number },
+            // Options are picked by name so the value can be written to another database that
+            // has options with the same names but different ids
+            Self::Select { select, .. } => PagePropertyValue::Select {
+                select: select.map(|option| PageSelectOptions::named(option.name)),
+            },
+            Self::MultiSelect { multi_select, .. } => PagePropertyValue::MultiSelect {
+                multi_select: multi_select
+                    .into_iter()
+                    .map(|option| PageMultiSelectOptions::named(option.name))
+                    .collect(),
+            },
+            Self::Date { date, .. } => PagePropertyValue::Date { date },
+            Self::Relation { relation, .. } => PagePropertyValue::Relation { relation },
+            Self::Title { title, .. } => PagePropertyValue::Title { title },
+            Self::People { people, .. } => PagePropertyValue::People {
+                people: people
+                    .iter()
+                    .filter_map(UserObject::id)
+                    .map(|id| UserObject::Partial { id: id.to_string() })
+                    .collect(),
+            },
+            Self::Checkbox { checkbox, .. } => PagePropertyValue::Checkbox { checkbox },
+            Self::Url { url, .. } => PagePropertyValue::Url { url },
+            Self::Email { email, .. } => PagePropertyValue::Email { email },
+            Self::PhoneNumber { phone_number, .. } => {
+                PagePropertyValue::PhoneNumber { phone_number }
+            }
+            Self::Files { .. }
+            | Self::Formula { .. }
+            | Self::Rollup { .. }
+            | Self::CreatedTime { .. }
+            | Self::CreatedBy { .. }
+            | Self::LastEditedTime { .. }
+            | Self::LastEditedBy { .. }
+            | Self::Unknown { .. } => return None,
+        })
+    }
+}
+
+impl PagePropertyValue {
+    /// Point relations and mentions at new ids, ids that aren't in the map are left alone
+    pub fn remap_ids(&mut self, ids: &BTreeMap<String, String>) {
+        match self {
+            Self::Relation { relation } => {
+                for related in relation {
+                    if let Some(new) = ids.get(&related.id) {
+                        related.id = new.clone();
+                    }
+                }
+            }
+            Self::RichText { rich_text: text } | Self::Title { title: text } => {
+                for text in text {
+                    text.remap_ids(ids);
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageFileReference {
     pub name: String,
+    #[serde(flatten)]
+    pub file: PageFile,
+}
+
+/// Where a file in a files property lives
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
+pub enum PageFile {
+    /// Uploaded to Notion, behind a link that expires
+    File {
+        file: HostedFile,
+    },
+    External {
+        external: ExternalFile,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(PageFile, "type", ["file", "external"]);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostedFile {
+    pub url: String,
+    pub expiry_time: Time,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExternalFile {
+    pub url: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageDate {
     pub start: Time,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end: Option<Time>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum PageFormula {
     String {
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -131,49 +298,65 @@ pub enum PageFormula {
         boolean: Option<bool>,
     },
     Date {
-        date: Time,
+        date: Option<PageDate>,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
     },
 }
+crate::unknown::tagged_enum!(PageFormula, "type", ["string", "number", "boolean", "date"]);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageRelation {
     pub id: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum PageRollup {
     Number {
         #[serde(skip_serializing_if = "Option::is_none")]
         number: Option<f64>,
     },
     Date {
-        date: Time,
+        date: Option<PageDate>,
     },
     Array {
         array: Vec<PageRollupProperty>,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
+crate::unknown::tagged_enum!(PageRollup, "type", ["number", "date", "array"]);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum PageRollupProperty {
     RichText {
         rich_text: Vec<RichTextObject>,
     },
     Number {
-        number: f64,
+        number: Option<f64>,
     },
     Select {
-        select: PageSelectOptions,
+        select: Option<PageSelectOptions>,
     },
     MultiSelect {
         multi_select: Vec<PageMultiSelectOptions>,
     },
     Date {
-        date: PageDate,
+        date: Option<PageDate>,
     },
     Formula {
         formula: PageFormula,
@@ -197,13 +380,13 @@ pub enum PageRollupProperty {
         checkbox: bool,
     },
     Url {
-        url: String,
+        url: Option<String>,
     },
     Email {
-        email: String,
+        email: Option<String>,
     },
     PhoneNumber {
-        phone_number: String,
+        phone_number: Option<String>,
     },
     CreatedTime {
         created_time: Time,
@@ -217,21 +400,85 @@ pub enum PageRollupProperty {
     LastEditedBy {
         last_edited_by: UserObject,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
+crate::unknown::tagged_enum!(
+    PageRollupProperty,
+    "type",
+    [
+        "rich_text",
+        "number",
+        "select",
+        "multi_select",
+        "date",
+        "formula",
+        "relation",
+        "rollup",
+        "title",
+        "people",
+        "files",
+        "checkbox",
+        "url",
+        "email",
+        "phone_number",
+        "created_time",
+        "created_by",
+        "last_edited_time",
+        "last_edited_by"
+    ]
+);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageSelectOptions {
     pub name: String,
-    pub id: String,
-    pub color: PageColor,
+    /// Not needed when writing, options can be picked by name alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<PageColor>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PageMultiSelectOptions {
     pub name: String,
-    pub id: String,
-    pub color: PageColor,
+    /// Not needed when writing, options can be picked by name alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<PageColor>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+impl PageSelectOptions {
+    /// The option with this name, without an id or color
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            id: None,
+            color: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl PageMultiSelectOptions {
+    /// The option with this name, without an id or color
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            id: None,
+            color: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum PageColor {
     Default,
@@ -244,24 +491,41 @@ pub enum PageColor {
     Purple,
     Pink,
     Red,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A property value when creating or updating a page, this serializes to just the type's key and
+/// value e.g. `{ "number": 3 }`. An empty number, select, date, url, email or phone number is
+/// `null` and clears the property, its key still has to be there when deserializing so other
+/// values don't match it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
 pub enum PagePropertyValue {
     RichText {
         rich_text: Vec<RichTextObject>,
     },
     Number {
-        number: f64,
+        #[serde(deserialize_with = "Option::deserialize")]
+        number: Option<f64>,
     },
     Select {
-        select: PageSelectOptions,
+        #[serde(deserialize_with = "Option::deserialize")]
+        select: Option<PageSelectOptions>,
     },
     MultiSelect {
         multi_select: Vec<PageMultiSelectOptions>,
     },
     Date {
-        date: PageDate,
+        #[serde(deserialize_with = "Option::deserialize")]
+        date: Option<PageDate>,
     },
     Formula {
         formula: PageFormula,
@@ -285,13 +549,16 @@ pub enum PagePropertyValue {
         checkbox: bool,
     },
     Url {
-        url: String,
+        #[serde(deserialize_with = "Option::deserialize")]
+        url: Option<String>,
     },
     Email {
-        email: String,
+        #[serde(deserialize_with = "Option::deserialize")]
+        email: Option<String>,
     },
     PhoneNumber {
-        phone_number: String,
+        #[serde(deserialize_with = "Option::deserialize")]
+        phone_number: Option<String>,
     },
     CreatedTime {
         created_time: Time,
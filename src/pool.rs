@@ -0,0 +1,368 @@
+//! Clients for many workspaces at once. A [`ClientPool`] makes a [`Client`] for a workspace the
+//! first time it's asked for one, using the token in a [`TokenStore`], and keeps it around for
+//! later. Every client sends through the same transport so they share one connection pool, but
+//! each workspace gets its own rate limit and backs off on its own when Notion says it's sending
+//! too much.
+//!
+//! Rate limited requests are retried after `Retry-After`. Server errors and failed requests are
+//! retried with exponential backoff, but only for `GET` requests since the others might have gone
+//! through.
+use crate::{
+    middleware::{EndpointMetrics, Metrics},
+    oauth::TokenStore,
+    transport::{Method, Request, Response, Transport},
+    Client,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Notion allows an average of three requests a second per integration
+const REQUESTS_PER_SECOND: f64 = 3.0;
+const BURST: u32 = 3;
+const MAX_RETRIES: u32 = 3;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The longest `Retry-After` that's waited for, so a bogus header can't block a workspace for good
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+pub struct ClientPool {
+    transport: Arc<dyn Transport>,
+    store: Arc<dyn TokenStore>,
+    base_url: Option<String>,
+    requests_per_second: f64,
+    burst: u32,
+    max_retries: u32,
+    max_clients: Option<usize>,
+    clients: Mutex<HashMap<String, Entry>>,
+    /// Outlive the clients, so one made again after an eviction doesn't get a fresh rate limit
+    /// while the old one might still be sending
+    workspaces: Mutex<HashMap<String, Arc<Workspace>>>,
+}
+
+struct Entry {
+    client: Arc<Client>,
+    last_used: Instant,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceMetrics {
+    pub endpoints: BTreeMap<String, EndpointMetrics>,
+    /// Requests sent again after a rate limit, server error or failure
+    pub retries: u64,
+    /// 429 responses from Notion
+    pub rate_limited: u64,
+    /// Time spent waiting for the rate limit before sending
+    pub throttled: Duration,
+}
+
+impl ClientPool {
+    #[cfg(feature = "reqwest")]
+    pub fn new(store: impl TokenStore + 'static) -> Self {
+        Self::with_transport(store, crate::transport::ReqwestTransport::new())
+    }
+
+    /// A pool whose clients all send through `transport`
+    pub fn with_transport(
+        store: impl TokenStore + 'static,
+        transport: impl Transport + 'static,
+    ) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            store: Arc::new(store),
+            base_url: None,
+            requests_per_second: REQUESTS_PER_SECOND,
+            burst: BURST,
+            max_retries: MAX_RETRIES,
+            max_clients: None,
+            clients: Mutex::new(HashMap::new()),
+            workspaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Point every client somewhere other than `https://api.notion.com/v1/`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// How many requests each workspace can send a second on average, and how many it can send
+    /// at once after being idle
+    ///
+    /// # Panics
+    ///
+    /// If `requests_per_second` isn't a positive, finite number
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second > 0.0 && requests_per_second.is_finite(),
+            "requests_per_second must be positive, got {}",
+            requests_per_second
+        );
+        self.requests_per_second = requests_per_second;
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Evict the least recently used client when a new one would go over `max_clients`
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = Some(max_clients.max(1));
+        self
+    }
+
+    pub fn store(&self) -> &dyn TokenStore {
+        &*self.store
+    }
+
+    /// The client for a workspace, made from the token store if there isn't one yet
+    pub async fn client(&self, workspace_id: &str) -> Result<Arc<Client>, Box<dyn Error>> {
+        if let Some(entry) = lock(&self.clients).get_mut(workspace_id) {
+            entry.last_used = Instant::now();
+            return Ok(entry.client.clone());
+        }
+
+        let token = self
+            .store
+            .get(workspace_id)
+            .await?
+            .ok_or_else(|| format!("No token stored for workspace {}", workspace_id))?;
+        let workspace = lock(&self.workspaces)
+            .entry(workspace_id.to_string())
+            .or_insert_with(|| {
+                Arc::new(Workspace::new(
+                    self.requests_per_second,
+                    self.burst,
+                    self.max_retries,
+                ))
+            })
+            .clone();
+        let mut client = Client::with_transport(
+            token.access_token,
+            WorkspaceTransport {
+                inner: self.transport.clone(),
+                workspace: workspace.clone(),
+            },
+        )
+        .with_middleware(workspace.metrics.clone());
+        if let Some(base_url) = &self.base_url {
+            client = client.with_base_url(base_url.clone());
+        }
+
+        let mut clients = lock(&self.clients);
+        // Another task might have made one while the token was being fetched
+        if let Some(entry) = clients.get_mut(workspace_id) {
+            entry.last_used = Instant::now();
+            return Ok(entry.client.clone());
+        }
+        if let Some(max_clients) = self.max_clients {
+            while clients.len() >= max_clients {
+                let oldest = clients
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(id, _)| id.clone());
+                match oldest {
+                    Some(id) => clients.remove(&id),
+                    None => break,
+                };
+            }
+        }
+        let client = Arc::new(client);
+        clients.insert(
+            workspace_id.to_string(),
+            Entry {
+                client: client.clone(),
+                last_used: Instant::now(),
+            },
+        );
+        Ok(client)
+    }
+
+    /// Drop the client for a workspace, e.g. after its token was revoked. Clients already handed
+    /// out keep working until they're dropped, and share the workspace's rate limit with the
+    /// client made in its place.
+    pub fn evict(&self, workspace_id: &str) -> bool {
+        lock(&self.clients).remove(workspace_id).is_some()
+    }
+
+    /// Drop every client that hasn't been asked for in `idle`, returning how many were dropped
+    pub fn evict_idle(&self, idle: Duration) -> usize {
+        let mut clients = lock(&self.clients);
+        let before = clients.len();
+        clients.retain(|_, entry| entry.last_used.elapsed() < idle);
+        before - clients.len()
+    }
+
+    /// The workspaces with a client in the pool
+    pub fn workspaces(&self) -> Vec<String> {
+        lock(&self.clients).keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        lock(&self.clients).is_empty()
+    }
+
+    /// Counts for a workspace since the pool first made a client for it, including clients that
+    /// were evicted since
+    pub fn metrics(&self, workspace_id: &str) -> Option<WorkspaceMetrics> {
+        lock(&self.workspaces)
+            .get(workspace_id)
+            .map(|w| w.metrics())
+    }
+
+    pub fn all_metrics(&self) -> BTreeMap<String, WorkspaceMetrics> {
+        lock(&self.workspaces)
+            .iter()
+            .map(|(id, workspace)| (id.clone(), workspace.metrics()))
+            .collect()
+    }
+}
+
+/// The rate limit and retry state of one workspace
+struct Workspace {
+    requests_per_second: f64,
+    burst: f64,
+    max_retries: u32,
+    bucket: Mutex<Bucket>,
+    counters: Mutex<Counters>,
+    metrics: Metrics,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Set from `Retry-After` when Notion rate limits the workspace
+    blocked_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Counters {
+    retries: u64,
+    rate_limited: u64,
+    throttled: Duration,
+}
+
+impl Workspace {
+    fn new(requests_per_second: f64, burst: u32, max_retries: u32) -> Self {
+        Self {
+            requests_per_second,
+            burst: burst as f64,
+            max_retries,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                updated: Instant::now(),
+                blocked_until: None,
+            }),
+            counters: Mutex::new(Counters::default()),
+            metrics: Metrics::new(),
+        }
+    }
+
+    fn metrics(&self) -> WorkspaceMetrics {
+        let counters = lock(&self.counters);
+        WorkspaceMetrics {
+            endpoints: self.metrics.snapshot(),
+            retries: counters.retries,
+            rate_limited: counters.rate_limited,
+            throttled: counters.throttled,
+        }
+    }
+
+    /// Wait until the workspace is allowed to send another request
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = lock(&self.bucket);
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens =
+                    (bucket.tokens + elapsed * self.requests_per_second).min(self.burst);
+                bucket.updated = now;
+                match bucket.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ if bucket.tokens >= 1.0 => {
+                        bucket.tokens -= 1.0;
+                        return;
+                    }
+                    _ => Duration::from_secs_f64((1.0 - bucket.tokens) / self.requests_per_second),
+                }
+            };
+            lock(&self.counters).throttled += wait;
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn rate_limited(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        let mut bucket = lock(&self.bucket);
+        bucket.blocked_until = Some(bucket.blocked_until.map_or(until, |at| at.max(until)));
+        lock(&self.counters).rate_limited += 1;
+    }
+}
+
+/// Sends a workspace's requests through the shared transport within its rate limit
+struct WorkspaceTransport {
+    inner: Arc<dyn Transport>,
+    workspace: Arc<Workspace>,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Transport for WorkspaceTransport {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        let workspace = &self.workspace;
+        let mut attempt = 0;
+        loop {
+            workspace.acquire().await;
+            let can_retry = attempt < workspace.max_retries;
+            let delay = match self.inner.send(request.clone()).await {
+                Ok(response) if response.status == 429 => {
+                    let retry_after = response
+                        .header("Retry-After")
+                        .and_then(|seconds| seconds.trim().parse::<f64>().ok())
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .map(|retry_after| retry_after.min(MAX_RETRY_AFTER))
+                        .unwrap_or_else(|| backoff(attempt));
+                    workspace.rate_limited(retry_after);
+                    if !can_retry {
+                        return Ok(response);
+                    }
+                    // `acquire` waits until the workspace isn't blocked anymore
+                    Duration::default()
+                }
+                Ok(response)
+                    if can_retry
+                        && request.method == Method::Get
+                        && matches!(response.status, 500 | 502 | 503 | 504) =>
+                {
+                    backoff(attempt)
+                }
+                Err(_) if can_retry && request.method == Method::Get => backoff(attempt),
+                result => return result,
+            };
+            attempt += 1;
+            lock(&workspace.counters).retries += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    (Duration::from_millis(500) * 2u32.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,449 @@
+//! Bringing a page's content in line with a desired tree of blocks. The current blocks are
+//! matched against the desired ones by content so that unchanged blocks keep their ids, blocks
+//! of the same type in between are edited in place, and everything else is inserted or deleted.
+//!
+//! Notion can only insert blocks after an existing one, so when new blocks go at the very start
+//! of a page the first block is recreated behind them. Child pages and databases are never
+//! touched.
+use crate::{
+    block::{BlockObject, BlockObjectInput, BlockTree},
+    diff::{describe, normalize_text},
+    restore::children,
+    template::{append_children, TemplateBlock},
+    Client,
+};
+use serde_json::Value;
+use std::{error::Error, fmt};
+
+/// The edits needed to turn a page's blocks into a desired tree. Applying an empty plan does
+/// nothing.
+#[derive(Debug)]
+pub struct ReconcilePlan {
+    pub page_id: String,
+    pub steps: Vec<ReconcileStep>,
+    /// How many blocks are kept as they are
+    pub unchanged: usize,
+}
+
+#[derive(Debug)]
+pub enum ReconcileStep {
+    /// New blocks under `parent`, after the block `after` or at the end when it's `None`
+    Insert {
+        parent: String,
+        after: Option<String>,
+        blocks: Vec<TemplateBlock>,
+    },
+    Update {
+        id: String,
+        old: BlockObject,
+        new: BlockObjectInput,
+    },
+    Delete {
+        id: String,
+        block: BlockObject,
+    },
+}
+
+/// Work out the edits that would turn the page's blocks into `desired`
+pub async fn plan(
+    client: &Client,
+    page_id: &str,
+    desired: &[TemplateBlock],
+) -> Result<ReconcilePlan, Box<dyn Error>> {
+    let current = client.get_block_tree(page_id).await?;
+    Ok(ReconcilePlan::new(page_id, &current, desired))
+}
+
+/// Make the page's blocks match `desired`. With `dry_run` the plan is only worked out and
+/// returned, so it can be shown with its `Display` impl before applying it.
+pub async fn reconcile(
+    client: &Client,
+    page_id: &str,
+    desired: &[TemplateBlock],
+    dry_run: bool,
+) -> Result<ReconcilePlan, Box<dyn Error>> {
+    let plan = plan(client, page_id, desired).await?;
+    if !dry_run {
+        plan.apply(client).await?;
+    }
+    Ok(plan)
+}
+
+impl ReconcilePlan {
+    pub fn new(page_id: &str, current: &[BlockTree], desired: &[TemplateBlock]) -> Self {
+        let mut plan = Self {
+            page_id: page_id.to_string(),
+            steps: Vec::new(),
+            unchanged: 0,
+        };
+        plan.level(page_id, current, desired);
+        plan
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Run the plan. Blocks are inserted first, since new blocks can be placed after ones that
+    /// are about to be deleted, then updated and finally deleted.
+    pub async fn apply(&self, client: &Client) -> Result<(), Box<dyn Error>> {
+        for step in &self.steps {
+            if let ReconcileStep::Insert {
+                parent,
+                after,
+                blocks,
+            } = step
+            {
+                insert(client, parent, after.as_deref(), blocks).await?;
+            }
+        }
+        for step in &self.steps {
+            if let ReconcileStep::Update { id, new, .. } = step {
+                let update = new.to_update().ok_or("Block can't be updated in place")?;
+                client.update_block(id, update).await?.into_result()?;
+            }
+        }
+        for step in &self.steps {
+            if let ReconcileStep::Delete { id, .. } = step {
+                client.delete_block(id).await?.into_result()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn level(&mut self, parent: &str, current: &[BlockTree], desired: &[TemplateBlock]) {
+        let mut anchor = None;
+        let mut group = Vec::new();
+        for entry in align(current, desired) {
+            let tree = match entry {
+                Entry::Insert(block) => {
+                    group.push(block.clone());
+                    continue;
+                }
+                Entry::Keep(tree, _)
+                | Entry::Anchor(tree)
+                | Entry::Update(tree, _)
+                | Entry::Delete(tree) => tree,
+            };
+            self.insert(parent, anchor.take(), &mut group);
+            let id = tree.block.id().unwrap_or_default().to_string();
+            anchor = Some(id.clone());
+            match entry {
+                Entry::Keep(tree, block) => {
+                    self.unchanged += 1;
+                    self.level(&id, &tree.children, &block.children);
+                }
+                Entry::Anchor(_) => self.unchanged += 1,
+                Entry::Update(tree, block) => {
+                    self.steps.push(ReconcileStep::Update {
+                        id: id.clone(),
+                        old: tree.block.clone(),
+                        new: block.block.clone(),
+                    });
+                    self.level(&id, &tree.children, &block.children);
+                }
+                Entry::Delete(tree) => self.steps.push(ReconcileStep::Delete {
+                    id,
+                    block: tree.block.clone(),
+                }),
+                Entry::Insert(_) => {}
+            }
+        }
+        self.insert(parent, anchor, &mut group);
+    }
+
+    fn insert(&mut self, parent: &str, after: Option<String>, group: &mut Vec<TemplateBlock>) {
+        if !group.is_empty() {
+            self.steps.push(ReconcileStep::Insert {
+                parent: parent.to_string(),
+                after,
+                blocks: std::mem::take(group),
+            });
+        }
+    }
+}
+
+impl fmt::Display for ReconcileStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insert {
+                parent,
+                after,
+                blocks,
+            } => {
+                match after {
+                    Some(after) => write!(f, "+ insert under {} after {}:", parent, after)?,
+                    None => write!(f, "+ append to {}:", parent)?,
+                }
+                write_blocks(f, blocks, 1)
+            }
+            Self::Update { id, old, new } => {
+                write!(f, "~ {} {} -> {}", id, describe(old), describe_input(new))
+            }
+            Self::Delete { id, block } => write!(f, "- {} {}", id, describe(block)),
+        }
+    }
+}
+
+impl fmt::Display for ReconcilePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Page {} is up to date", self.page_id);
+        }
+        writeln!(
+            f,
+            "Reconcile plan for page {} ({} blocks unchanged):",
+            self.page_id, self.unchanged
+        )?;
+        for step in &self.steps {
+            writeln!(f, "  {}", step)?;
+        }
+        Ok(())
+    }
+}
+
+enum Entry<'a> {
+    Keep(&'a BlockTree, &'a TemplateBlock),
+    /// A block that can't be written back, which stays where it is
+    Anchor(&'a BlockTree),
+    Update(&'a BlockTree, &'a TemplateBlock),
+    Delete(&'a BlockTree),
+    Insert(&'a TemplateBlock),
+}
+
+/// Line up the current blocks with the desired ones, in the order they should end up in
+fn align<'a>(current: &'a [BlockTree], desired: &'a [TemplateBlock]) -> Vec<Entry<'a>> {
+    let current: Vec<_> = current
+        .iter()
+        .filter(|tree| {
+            tree.block.id().is_some()
+                && !matches!(
+                    tree.block,
+                    BlockObject::ChildPage { .. } | BlockObject::ChildDatabase { .. }
+                )
+        })
+        .collect();
+    let old: Vec<_> = current
+        .iter()
+        .map(|tree| tree.block.to_input().map(|input| content(&input)))
+        .collect();
+    let new: Vec<_> = desired
+        .iter()
+        .map(|block| Some(content(&block.block)))
+        .collect();
+
+    // Longest common subsequence of blocks with the same content
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i].is_some() && old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].is_some() && old[i] == new[j] {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches.push((old.len(), new.len()));
+
+    let mut entries = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matches {
+        gap(&current[i..next_i], &desired[j..next_j], &mut entries);
+        if next_i < current.len() {
+            entries.push(Entry::Keep(current[next_i], &desired[next_j]));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+
+    // Nothing can be inserted before the first block. Deletes come first in every gap, so if
+    // blocks are inserted at the start the first block is kept and has to be recreated behind
+    // them, leaving the old copy to insert after. A block that can't be recreated stays first and
+    // the new blocks go right after it instead.
+    let first = entries
+        .iter()
+        .position(|entry| !matches!(entry, Entry::Insert(_)));
+    if let Some(first) = first.filter(|&first| first > 0) {
+        match entries[first] {
+            Entry::Keep(tree, block) | Entry::Update(tree, block) => {
+                entries[first] = Entry::Insert(block);
+                entries.insert(0, Entry::Delete(tree));
+            }
+            Entry::Anchor(_) => {
+                let anchor = entries.remove(first);
+                entries.insert(0, anchor);
+            }
+            Entry::Delete(_) | Entry::Insert(_) => {}
+        }
+    }
+    entries
+}
+
+/// Pair up blocks of the same type between two kept blocks, deleting the current blocks left
+/// over and inserting the desired ones. Blocks that can't be written back split the gap and are
+/// kept, with the desired blocks that aren't paired up before one going after it.
+fn gap<'a>(
+    mut current: &[&'a BlockTree],
+    mut desired: &'a [TemplateBlock],
+    entries: &mut Vec<Entry<'a>>,
+) {
+    while let Some(anchor) = current
+        .iter()
+        .position(|tree| tree.block.to_input().is_none())
+    {
+        let paired = pair(&current[..anchor], desired, entries);
+        entries.push(Entry::Anchor(current[anchor]));
+        current = &current[anchor + 1..];
+        desired = &desired[paired..];
+    }
+    let paired = pair(current, desired, entries);
+    entries.extend(desired[paired..].iter().map(Entry::Insert));
+}
+
+/// Pair up the blocks of one stretch of a gap, returning how many of the desired blocks were
+/// used. Desired blocks after the last current one are left for the caller.
+fn pair<'a>(
+    current: &[&'a BlockTree],
+    desired: &'a [TemplateBlock],
+    entries: &mut Vec<Entry<'a>>,
+) -> usize {
+    let mut deletes = Vec::new();
+    let mut rest = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < current.len() && j < desired.len() {
+        let old_kind = current[i]
+            .block
+            .to_input()
+            .and_then(|input| kind(&input))
+            .filter(|_| desired[j].block.to_update().is_some());
+        if old_kind.is_some() && old_kind == kind(&desired[j].block) {
+            rest.push(Entry::Update(current[i], &desired[j]));
+            i += 1;
+            j += 1;
+        } else if old_kind.is_some()
+            && desired[j..]
+                .iter()
+                .any(|block| kind(&block.block) == old_kind)
+        {
+            rest.push(Entry::Insert(&desired[j]));
+            j += 1;
+        } else {
+            deletes.push(Entry::Delete(current[i]));
+            i += 1;
+        }
+    }
+    deletes.extend(current[i..].iter().map(|tree| Entry::Delete(tree)));
+    entries.extend(deletes);
+    entries.extend(rest);
+    j
+}
+
+/// Insert the blocks and everything nested under them
+async fn insert(
+    client: &Client,
+    parent: &str,
+    after: Option<&str>,
+    blocks: &[TemplateBlock],
+) -> Result<(), Box<dyn Error>> {
+    let mut after = after.map(str::to_string);
+    for chunk in blocks.chunks(100) {
+        let inputs = chunk.iter().map(|block| block.block.clone()).collect();
+        match &after {
+            Some(after) => {
+                client
+                    .append_block_children_after(parent, inputs, after)
+                    .await?
+            }
+            None => client.append_block_children(parent, inputs).await?,
+        }
+        .into_result()?;
+
+        let listed = children(client, parent).await?;
+        let start = match &after {
+            Some(after) => {
+                listed
+                    .iter()
+                    .position(|block| block.id() == Some(after.as_str()))
+                    .ok_or("Block to insert after is gone")?
+                    + 1
+            }
+            None => listed.len().saturating_sub(chunk.len()),
+        };
+        after = listed
+            .get(start + chunk.len() - 1)
+            .and_then(BlockObject::id)
+            .map(str::to_string);
+        append_children(client, parent, chunk, start).await?;
+    }
+    Ok(())
+}
+
+/// An input's JSON with its text normalized the same way as when diffing, so that spans written
+/// as just `{ "text": { "content": .. } }` match the blocks Notion sends back
+fn content(input: &BlockObjectInput) -> Value {
+    let mut input = input.clone();
+    let text = input.text_mut().map(|text| normalize_text(text));
+    let mut value = serde_json::to_value(&input).unwrap_or(Value::Null);
+    if let (Some(text), Some(kind)) = (text, kind(&input)) {
+        if let Some(inner) = value.get_mut(kind.as_str()).and_then(Value::as_object_mut) {
+            inner.insert("text".to_string(), text);
+        }
+    }
+    value
+}
+
+fn span_text(span: &Value) -> String {
+    span.get("plain_text")
+        .and_then(Value::as_str)
+        .filter(|plain| !plain.is_empty())
+        .or_else(|| span.get("text")?.get("content")?.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn kind(input: &BlockObjectInput) -> Option<String> {
+    serde_json::to_value(input)
+        .ok()?
+        .get("type")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn describe_input(input: &BlockObjectInput) -> String {
+    let kind = kind(input).unwrap_or_else(|| "block".to_string());
+    let value = serde_json::to_value(input).unwrap_or(Value::Null);
+    match value[kind.as_str()].get("text").and_then(Value::as_array) {
+        Some(text) => format!(
+            "{} {:?}",
+            kind,
+            text.iter().map(span_text).collect::<String>()
+        ),
+        None => kind,
+    }
+}
+
+fn write_blocks(f: &mut fmt::Formatter<'_>, blocks: &[TemplateBlock], depth: usize) -> fmt::Result {
+    for block in blocks {
+        write!(
+            f,
+            "\n  {}{}",
+            "  ".repeat(depth),
+            describe_input(&block.block)
+        )?;
+        write_blocks(f, &block.children, depth + 1)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,557 @@
+//! Recreating a backup made with [`crate::backup`] under a page. Databases and pages are created
+//! first in parent before child order, then relations, property values and block content once
+//! every new id is known so that relations, mentions and page links point at the restored
+//! copies instead of the originals.
+//!
+//! Notion adds a new page or database to the end of the page it's created in, and blocks can't
+//! be moved through the API. Since the content is written after every page exists, restored
+//! child pages and databases end up above the rest of their parent's content rather than where
+//! they were among it.
+//!
+//! Progress and the mapping from old to new ids is saved to `restore-<parent id>.json` in the
+//! backup directory after every step. If a restore fails part way through running it again with
+//! the same parent picks up where it stopped.
+use crate::{
+    backup::{read_blocks, read_database, read_page, Manifest},
+    block::{BlockObject, BlockTree, LinkToPage},
+    database::{
+        DatabaseObject, DatabaseProperty, DatabasePropertyInput, DatabaseSchema,
+        DatabaseSchemaChanges,
+    },
+    page::{PageObject, PageProperty, PagePropertyValue, Parent},
+    rich_text::{Mention, RichTextObject},
+    BoxFuture, Client,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RestoreState {
+    /// The page everything is restored under
+    pub parent: String,
+    /// Old database ids to the ids of their restored copies
+    pub databases: BTreeMap<String, String>,
+    /// Old page ids to the ids of their restored copies
+    pub pages: BTreeMap<String, String>,
+    /// Databases whose relation and rollup properties have been added
+    pub schemas: BTreeSet<String>,
+    /// Pages whose property values have been written
+    pub properties: BTreeSet<String>,
+    /// Pages whose block content has been written
+    pub blocks: BTreeSet<String>,
+    /// Blocks that can't be created through the API, e.g. images and embeds, by the page they
+    /// were on. They're left out of the restored page.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<String, Vec<SkippedBlock>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkippedBlock {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The block's `type` e.g. `image`
+    pub kind: String,
+}
+
+impl RestoreState {
+    pub fn path(dir: impl AsRef<Path>, parent: &str) -> PathBuf {
+        dir.as_ref().join(format!("restore-{}.json", parent))
+    }
+
+    /// Every old id mapped to its new one, pages and databases alike
+    pub fn ids(&self) -> BTreeMap<String, String> {
+        self.databases
+            .iter()
+            .chain(self.pages.iter())
+            .map(|(old, new)| (old.clone(), new.clone()))
+            .collect()
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        fs::write(Self::path(dir, &self.parent), json)?;
+        Ok(())
+    }
+}
+
+/// Everything needed to recreate a set of pages and databases somewhere else, keyed by their
+/// original ids
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    pub databases: BTreeMap<String, DatabaseObject>,
+    pub pages: BTreeMap<String, PageObject>,
+    /// The block tree of each page
+    pub blocks: BTreeMap<String, Vec<BlockTree>>,
+}
+
+impl Snapshot {
+    /// Read every database, page and block tree in a backup
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.as_ref();
+        let manifest = Manifest::load(dir)?;
+        let mut snapshot = Self::default();
+        for id in manifest.databases.keys() {
+            snapshot
+                .databases
+                .insert(id.clone(), read_database(dir, id)?);
+        }
+        for id in manifest.pages.keys() {
+            snapshot.pages.insert(id.clone(), read_page(dir, id)?);
+            snapshot.blocks.insert(id.clone(), read_blocks(dir, id)?);
+        }
+        Ok(snapshot)
+    }
+
+    /// Remove references to anything outside of the snapshot so it can be written to another
+    /// workspace. Mentions become plain text, page links and relations to other databases are
+    /// dropped along with rollups through them, and people are cleared since users differ between
+    /// workspaces.
+    pub fn strip_external(&mut self) {
+        let ids = self
+            .pages
+            .keys()
+            .chain(self.databases.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let mut removed = BTreeMap::<String, BTreeSet<String>>::new();
+        for (id, database) in &mut self.databases {
+            let removed = removed.entry(id.clone()).or_default();
+            database.properties.retain(|name, property| match property {
+                DatabaseProperty::Relation { relation, .. }
+                    if !ids.contains(&relation.database_id) =>
+                {
+                    removed.insert(name.clone());
+                    false
+                }
+                _ => true,
+            });
+            database.properties.retain(|name, property| match property {
+                DatabaseProperty::Rollup { rollup, .. }
+                    if removed.contains(&rollup.relation_property_name) =>
+                {
+                    removed.insert(name.clone());
+                    false
+                }
+                _ => true,
+            });
+            strip_mentions(&mut database.title, &ids);
+        }
+
+        for page in self.pages.values_mut() {
+            if let Parent::DatabaseId { database_id } = &page.parent {
+                if let Some(removed) = removed.get(database_id) {
+                    page.properties.retain(|name, _| !removed.contains(name));
+                }
+            }
+            for property in page.properties.values_mut() {
+                match property {
+                    PageProperty::Relation { relation, .. } => {
+                        relation.retain(|related| ids.contains(&related.id))
+                    }
+                    PageProperty::People { people, .. } => people.clear(),
+                    PageProperty::Title { title: text, .. }
+                    | PageProperty::RichText {
+                        rich_text: text, ..
+                    } => strip_mentions(text, &ids),
+                    _ => {}
+                }
+            }
+        }
+
+        for trees in self.blocks.values_mut() {
+            strip_blocks(trees, &ids);
+        }
+    }
+}
+
+fn strip_mentions(text: &mut [RichTextObject], ids: &BTreeSet<String>) {
+    for text in text {
+        let (external, original) = match text {
+            RichTextObject::Mention {
+                mention,
+                annotations,
+                ..
+            } => (
+                match mention {
+                    Mention::Page { page } => !ids.contains(&page.id),
+                    Mention::Database { database } => !ids.contains(&database.id),
+                    Mention::User { .. } => true,
+                    _ => false,
+                },
+                annotations.clone(),
+            ),
+            _ => continue,
+        };
+        if external {
+            let mut plain = RichTextObject::text(text.plain_text());
+            if let RichTextObject::Text { annotations, .. } = &mut plain {
+                *annotations = original;
+            }
+            *text = plain;
+        }
+    }
+}
+
+fn strip_blocks(trees: &mut Vec<BlockTree>, ids: &BTreeSet<String>) {
+    trees.retain(|tree| match &tree.block {
+        BlockObject::LinkToPage {
+            link_to_page:
+                LinkToPage::PageId { page_id: id } | LinkToPage::DatabaseId { database_id: id },
+            ..
+        } => ids.contains(id),
+        _ => true,
+    });
+    for tree in trees {
+        if let Some(text) = tree.block.text_mut() {
+            strip_mentions(text, ids);
+        }
+        strip_blocks(&mut tree.children, ids);
+    }
+}
+
+/// Restore the backup in `dir` under the page `parent`
+pub async fn restore(
+    client: &Client,
+    dir: impl AsRef<Path>,
+    parent: &str,
+) -> Result<RestoreState, Box<dyn Error>> {
+    let dir = dir.as_ref();
+    let state_path = RestoreState::path(dir, parent);
+    let mut state = if state_path.exists() {
+        serde_json::from_str(&fs::read_to_string(&state_path)?)?
+    } else {
+        RestoreState {
+            parent: parent.to_string(),
+            ..RestoreState::default()
+        }
+    };
+    let snapshot = Snapshot::load(dir)?;
+    write_snapshot(client, &snapshot, &mut state, |state| state.save(dir)).await?;
+    Ok(state)
+}
+
+/// Create everything in the snapshot under `state.parent`, skipping whatever `state` says was
+/// already done and calling `save` after every step
+pub(crate) async fn write_snapshot(
+    client: &Client,
+    snapshot: &Snapshot,
+    state: &mut RestoreState,
+    mut save: impl FnMut(&RestoreState) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    // Databases and pages are created once whatever they live in exists. Relations and rollups
+    // need the other databases to exist so they're added afterwards.
+    let mut pending = snapshot
+        .databases
+        .keys()
+        .filter(|id| !state.databases.contains_key(*id))
+        .chain(
+            snapshot
+                .pages
+                .keys()
+                .filter(|id| !state.pages.contains_key(*id)),
+        )
+        .cloned()
+        .collect::<Vec<_>>();
+    while !pending.is_empty() {
+        let mut waiting = Vec::new();
+        let count = pending.len();
+        for id in pending {
+            if let Some(database) = snapshot.databases.get(&id) {
+                let parent_page = match &database.parent {
+                    Some(Parent::PageId { page_id }) if snapshot.pages.contains_key(page_id) => {
+                        match state.pages.get(page_id) {
+                            Some(new) => new.clone(),
+                            None => {
+                                waiting.push(id);
+                                continue;
+                            }
+                        }
+                    }
+                    _ => state.parent.clone(),
+                };
+                let schema = database
+                    .properties
+                    .iter()
+                    .filter(|(_, property)| !is_relation(property))
+                    .filter_map(|(name, property)| Some((name, property.to_input()?)))
+                    .fold(DatabaseSchema::new(), |schema, (name, input)| {
+                        schema.property(name, input)
+                    });
+                let new = client
+                    .create_database(&parent_page, database.title.clone(), schema)
+                    .await?
+                    .into_database()?;
+                state.databases.insert(id, new.id);
+                save(state)?;
+                continue;
+            }
+
+            let page = &snapshot.pages[&id];
+            let new_parent = match &page.parent {
+                Parent::DatabaseId { database_id }
+                    if snapshot.databases.contains_key(database_id) =>
+                {
+                    match state.databases.get(database_id) {
+                        Some(new) => Parent::DatabaseId {
+                            database_id: new.clone(),
+                        },
+                        None => {
+                            waiting.push(id);
+                            continue;
+                        }
+                    }
+                }
+                Parent::PageId { page_id } if snapshot.pages.contains_key(page_id) => {
+                    match state.pages.get(page_id) {
+                        Some(new) => Parent::PageId {
+                            page_id: new.clone(),
+                        },
+                        None => {
+                            waiting.push(id);
+                            continue;
+                        }
+                    }
+                }
+                _ => Parent::PageId {
+                    page_id: state.parent.clone(),
+                },
+            };
+            let in_database = matches!(new_parent, Parent::DatabaseId { .. });
+            let properties = page_properties(page, in_database, &state.ids(), false);
+            let new = client
+                .create_page(new_parent, properties, None)
+                .await?
+                .into_page()?;
+            state.pages.insert(id, new.id);
+            save(state)?;
+        }
+        if waiting.len() == count {
+            return Err(
+                format!("{} have parents that can't be created", waiting.join(", ")).into(),
+            );
+        }
+        pending = waiting;
+    }
+
+    let ids = state.ids();
+    for (id, new_id) in state.databases.clone() {
+        if state.schemas.contains(&id) {
+            continue;
+        }
+        let properties = snapshot.databases[&id]
+            .properties
+            .iter()
+            .filter(|(_, property)| is_relation(property))
+            .filter_map(|(name, property)| Some((name.clone(), property.to_input()?)))
+            .collect::<Vec<_>>();
+
+        // Rollups go through the relation they roll up so they're added in a second call
+        for rollups in [false, true] {
+            let changes = properties
+                .iter()
+                .filter(|(_, input)| matches!(input, DatabasePropertyInput::Rollup(_)) == rollups)
+                .fold(DatabaseSchemaChanges::new(), |changes, (name, input)| {
+                    changes.property(name, remap_schema(input, &ids))
+                });
+            if !changes.is_empty() {
+                client
+                    .update_database(&new_id, None, Some(changes))
+                    .await?
+                    .into_result()?;
+            }
+        }
+        state.schemas.insert(id);
+        save(state)?;
+    }
+
+    for (id, new_id) in state.pages.clone() {
+        if state.properties.contains(&id) {
+            continue;
+        }
+        let page = &snapshot.pages[&id];
+        let in_database = matches!(
+            &page.parent,
+            Parent::DatabaseId { database_id } if snapshot.databases.contains_key(database_id)
+        );
+        let properties = page_properties(page, in_database, &ids, true);
+        client
+            .update_page_properties(&new_id, properties)
+            .await?
+            .into_result()?;
+        state.properties.insert(id);
+        save(state)?;
+    }
+
+    for (id, new_id) in state.pages.clone() {
+        if state.blocks.contains(&id) {
+            continue;
+        }
+        // Child pages were created above this already, see the module docs. Anything else there
+        // is left over from a run that failed part way through.
+        for block in children(client, &new_id).await? {
+            if let Some(block_id) = block.id() {
+                client.delete_block(block_id).await?.into_result()?;
+            }
+        }
+        let mut skipped = Vec::new();
+        if let Some(trees) = snapshot.blocks.get(&id) {
+            append_tree(client, &new_id, trees, &ids, &mut skipped).await?;
+        }
+        if skipped.is_empty() {
+            state.skipped.remove(&id);
+        } else {
+            state.skipped.insert(id.clone(), skipped);
+        }
+        state.blocks.insert(id);
+        save(state)?;
+    }
+
+    Ok(())
+}
+
+fn is_relation(property: &DatabaseProperty) -> bool {
+    matches!(
+        property,
+        DatabaseProperty::Relation { .. } | DatabaseProperty::Rollup { .. }
+    )
+}
+
+fn remap_schema(
+    input: &DatabasePropertyInput,
+    ids: &BTreeMap<String, String>,
+) -> DatabasePropertyInput {
+    let mut input = input.clone();
+    match &mut input {
+        DatabasePropertyInput::Relation(relation) => {
+            if let Some(new) = ids.get(&relation.database_id) {
+                relation.database_id = new.clone();
+            }
+            relation.synced_property_id = None;
+            relation.synced_property_name = None;
+        }
+        DatabasePropertyInput::Rollup(rollup) => {
+            rollup.relation_property_id = None;
+            rollup.rollup_property_id = None;
+        }
+        _ => {}
+    }
+    input
+}
+
+/// The values to write for a restored page. Pages that aren't in a database can only have a
+/// title, relations are left out until every page exists.
+fn page_properties(
+    page: &PageObject,
+    in_database: bool,
+    ids: &BTreeMap<String, String>,
+    relations: bool,
+) -> HashMap<String, PagePropertyValue> {
+    page.properties
+        .iter()
+        .filter_map(|(name, property)| match (in_database, property) {
+            (true, PageProperty::Relation { .. }) if !relations => None,
+            (true, property) => Some((name.clone(), property.to_value()?)),
+            (false, PageProperty::Title { .. }) => {
+                Some(("title".to_string(), property.to_value()?))
+            }
+            _ => None,
+        })
+        .map(|(name, mut value)| {
+            value.remap_ids(ids);
+            (name, value)
+        })
+        .collect()
+}
+
+/// The children of a block other than child pages and databases, which are created on their own
+pub(crate) async fn children(
+    client: &Client,
+    id: &str,
+) -> Result<Vec<BlockObject>, Box<dyn Error>> {
+    let mut children = Vec::new();
+    let mut start_cursor = None;
+    loop {
+        let list = client
+            .get_block_children(id, start_cursor, None)
+            .await?
+            .into_list()?;
+        for result in list.results {
+            match result.into_block()? {
+                BlockObject::ChildPage { .. } | BlockObject::ChildDatabase { .. } => {}
+                block => children.push(block),
+            }
+        }
+        match list.next_cursor {
+            Some(cursor) if list.has_more => start_cursor = Some(cursor),
+            _ => return Ok(children),
+        }
+    }
+}
+
+/// Append copies of the blocks to `id`, and then their children to each new block in turn.
+/// Blocks that can't be created are added to `skipped` along with everything under them, except
+/// child pages and databases which are created on their own.
+pub(crate) fn append_tree<'a>(
+    client: &'a Client,
+    id: &'a str,
+    trees: &'a [BlockTree],
+    ids: &'a BTreeMap<String, String>,
+    skipped: &'a mut Vec<SkippedBlock>,
+) -> BoxFuture<'a, Result<(), Box<dyn Error>>> {
+    Box::pin(async move {
+        let trees = trees
+            .iter()
+            .filter_map(|tree| match tree.block.to_input() {
+                Some(mut input) => {
+                    input.remap_ids(ids);
+                    Some((input, tree))
+                }
+                None => {
+                    skip_tree(tree, skipped);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if trees.is_empty() {
+            return Ok(());
+        }
+
+        let existing = children(client, id).await?.len();
+        // Notion accepts at most 100 blocks per call
+        for chunk in trees.chunks(100) {
+            let inputs = chunk.iter().map(|(input, _)| input.clone()).collect();
+            client
+                .append_block_children(id, inputs)
+                .await?
+                .into_result()?;
+        }
+
+        let created = children(client, id).await?;
+        for (block, (_, tree)) in created.iter().skip(existing).zip(&trees) {
+            if let (Some(new_id), false) = (block.id(), tree.children.is_empty()) {
+                append_tree(client, new_id, &tree.children, ids, skipped).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn skip_tree(tree: &BlockTree, skipped: &mut Vec<SkippedBlock>) {
+    if let BlockObject::ChildPage { .. } | BlockObject::ChildDatabase { .. } = tree.block {
+        return;
+    }
+    skipped.push(SkippedBlock {
+        id: tree.block.id().map(str::to_string),
+        kind: tree.block.kind().to_string(),
+    });
+    for child in &tree.children {
+        skip_tree(child, skipped);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{time::Time, user::UserObject};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum RichTextObject {
     Text {
         plain_text: String,
@@ -26,47 +28,294 @@ pub enum RichTextObject {
         annotations: Annotations,
         equation: Equation,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(RichTextObject, "type", ["text", "mention", "equation"]);
+impl RichTextObject {
+    /// Plain unformatted text
+    pub fn text(content: impl Into<String>) -> Self {
+        let content = content.into();
+        Self::Text {
+            plain_text: content.clone(),
+            href: None,
+            annotations: Annotations::default(),
+            text: Text {
+                content,
+                link: None,
+                extra: HashMap::new(),
+            },
+        }
+    }
+
+    /// Point page and database mentions at new ids, ids that aren't in the map are left alone
+    pub fn remap_ids(&mut self, ids: &BTreeMap<String, String>) {
+        let id = match self {
+            Self::Mention {
+                mention:
+                    Mention::Page {
+                        page: Page { id, .. },
+                    },
+                ..
+            }
+            | Self::Mention {
+                mention:
+                    Mention::Database {
+                        database: Database { id, .. },
+                    },
+                ..
+            } => id,
+            _ => return,
+        };
+        if let Some(new) = ids.get(id) {
+            *id = new.clone();
+        }
+    }
+
+    pub fn plain_text(&self) -> &str {
+        match self {
+            Self::Text { plain_text, .. }
+            | Self::Mention { plain_text, .. }
+            | Self::Equation { plain_text, .. } => plain_text,
+            Self::Unknown { raw, .. } => raw
+                .get("plain_text")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn mention(mention: Mention) -> Self {
+        Self::Mention {
+            plain_text: String::new(),
+            href: None,
+            annotations: Annotations::default(),
+            mention,
+        }
+    }
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum Mention {
-    User { user: UserObject },
-    Page { page: Page },
-    Database { database: Database },
-    Date { page: MentionDate },
+    User {
+        user: UserObject,
+    },
+    Page {
+        page: Page,
+    },
+    Database {
+        database: Database,
+    },
+    Date {
+        date: MentionDate,
+    },
+    LinkPreview {
+        link_preview: LinkPreview,
+    },
+    TemplateMention {
+        template_mention: TemplateMention,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(
+    Mention,
+    "type",
+    [
+        "user",
+        "page",
+        "database",
+        "date",
+        "link_preview",
+        "template_mention"
+    ]
+);
+
+impl Mention {
+    /// Notion only needs the id of the user being mentioned
+    pub fn user(id: impl Into<String>) -> Self {
+        Self::User {
+            user: UserObject::Partial { id: id.into() },
+        }
+    }
+
+    pub fn page(id: impl Into<String>) -> Self {
+        Self::Page {
+            page: Page {
+                id: id.into(),
+                extra: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn database(id: impl Into<String>) -> Self {
+        Self::Database {
+            database: Database {
+                id: id.into(),
+                extra: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn date(start: Time, end: Option<Time>) -> Self {
+        Self::Date {
+            date: MentionDate {
+                start,
+                end,
+                time_zone: None,
+                extra: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn link_preview(url: impl Into<String>) -> Self {
+        Self::LinkPreview {
+            link_preview: LinkPreview {
+                url: url.into(),
+                extra: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn today() -> Self {
+        Self::TemplateMention {
+            template_mention: TemplateMention::TemplateMentionDate {
+                template_mention_date: TemplateMentionDate::Today,
+            },
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::TemplateMention {
+            template_mention: TemplateMention::TemplateMentionDate {
+                template_mention_date: TemplateMentionDate::Now,
+            },
+        }
+    }
+
+    pub fn me() -> Self {
+        Self::TemplateMention {
+            template_mention: TemplateMention::TemplateMentionUser {
+                template_mention_user: TemplateMentionUser::Me,
+            },
+        }
+    }
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Page {
     pub id: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Database {
     pub id: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MentionDate {
     pub start: Time,
-    pub end: Time,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkPreview {
+    pub url: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Mentions inside of templates that Notion fills in when the template is used
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
+pub enum TemplateMention {
+    TemplateMentionDate {
+        template_mention_date: TemplateMentionDate,
+    },
+    TemplateMentionUser {
+        template_mention_user: TemplateMentionUser,
+    },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+crate::unknown::tagged_enum!(
+    TemplateMention,
+    "type",
+    ["template_mention_date", "template_mention_user"]
+);
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateMentionDate {
+    Today,
+    Now,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateMentionUser {
+    Me,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Equation {
     pub expression: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Text {
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub link: Option<Link>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Link {
     pub r#type: String,
     pub url: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Annotations {
     pub bold: bool,
     pub italic: bool,
@@ -74,11 +323,14 @@ pub struct Annotations {
     pub underline: bool,
     pub code: bool,
     pub color: RichTextColor,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum RichTextColor {
+    #[default]
     Default,
     Gray,
     Brown,
@@ -98,4 +350,13 @@ pub enum RichTextColor {
     PurpleBackground,
     PinkBackground,
     RedBackground,
+    #[serde(
+        untagged,
+        deserialize_with = "crate::unknown::deserialize_name",
+        serialize_with = "crate::unknown::serialize"
+    )]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
 }
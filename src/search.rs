@@ -1,6 +1,6 @@
 use serde::Serialize;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SearchFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<String>,
@@ -0,0 +1,399 @@
+//! Mirroring a database into a SQLite file for reporting. Rows go in a `rows` table with a
+//! column per property, typed by the property's kind. Relations, multi-selects, people and files
+//! each get a `rows_<property>` table of `(row_id, value)` pairs. Dates are split into a start
+//! column named after the property and an end column with `:end` added to the name. Properties
+//! named like the `id` and `last_edited_time` columns get a `property:` prefix.
+//!
+//! After the first sync only rows edited since the last one are fetched. Notion doesn't say when
+//! rows are deleted, so every so often all rows are listed, rows that are gone are removed and
+//! rows whose edit time differs from the stored one are written.
+//! Properties added to the database get new columns, but changing a property's type needs a
+//! full resync into a new file.
+use crate::{
+    database::{DatabaseObject, DatabaseProperty, Direction, QueryDatabaseSort, QueryDatabaseTime},
+    export::{cell, ExportOptions, Exporter},
+    page::PageObject,
+    Client,
+};
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, Connection, OptionalExtension};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Default)]
+pub struct SyncOptions {
+    /// Fetch every row again instead of only the ones edited since the last sync
+    pub full: bool,
+    /// How often to list every row id to find deleted rows. Full syncs always do this, otherwise
+    /// deleted rows are only found by full syncs when this is `None`.
+    pub reconcile_every: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Rows that were new or changed
+    pub upserted: usize,
+    pub deleted: usize,
+    /// Whether every row id was listed to look for deleted rows
+    pub reconciled: bool,
+}
+
+pub struct Mirror {
+    connection: Connection,
+    database_id: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Scalar(&'static str),
+    Date,
+    List,
+}
+
+impl Mirror {
+    /// Open or create the SQLite file mirroring the database
+    pub fn open(path: impl AsRef<Path>, database_id: &str) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sync (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS rows (id TEXT PRIMARY KEY, last_edited_time TEXT NOT NULL);",
+        )?;
+        Ok(Self {
+            connection,
+            database_id: database_id.to_string(),
+        })
+    }
+
+    /// The connection to the mirror, e.g. for running reports
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Bring the mirror up to date with the database
+    pub async fn sync(
+        &mut self,
+        client: &Client,
+        options: &SyncOptions,
+    ) -> Result<SyncReport, Box<dyn Error>> {
+        let database = client
+            .get_database(&self.database_id)
+            .await?
+            .into_database()?;
+        let columns = self.update_schema(&database)?;
+        let mut report = SyncReport::default();
+
+        let since = if options.full {
+            None
+        } else {
+            self.get("last_edited_time")?
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let reconcile = options.full
+            || since.is_none()
+            || match (options.reconcile_every, self.get("reconciled_at")?) {
+                (Some(every), Some(last)) => now.saturating_sub(last.parse()?) >= every.as_secs(),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+        let (rows, live) = if reconcile {
+            let rows = client
+                .query_database_all(&self.database_id, None, None)
+                .await?
+                .into_iter()
+                .map(|row| row.into_page())
+                .collect::<Result<Vec<_>, _>>()?;
+            let live = rows
+                .iter()
+                .map(|row| row.id.clone())
+                .collect::<HashSet<_>>();
+            let stored = self.stored()?;
+            let mut changed = Vec::new();
+            for row in rows {
+                let edited = row.last_edited_time.to_string();
+                if options.full || stored.get(&row.id) != Some(&edited) {
+                    changed.push(row);
+                }
+            }
+            (changed, Some(live))
+        } else {
+            let rows = changed_rows(client, &self.database_id, since.as_deref()).await?;
+            (rows, None)
+        };
+
+        let exporter = Exporter::new(&database, ExportOptions::default());
+        let transaction = self.connection.transaction()?;
+        let mut latest = since;
+        for row in &rows {
+            write_row(&transaction, &exporter, &columns, row)?;
+            let edited = row.last_edited_time.to_string();
+            if latest.as_ref().is_none_or(|latest| &edited > latest) {
+                latest = Some(edited);
+            }
+            report.upserted += 1;
+        }
+        if let Some(live) = live {
+            let stored = transaction
+                .prepare("SELECT id FROM rows")?
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for id in stored.iter().filter(|id| !live.contains(*id)) {
+                delete_row(&transaction, &columns, id)?;
+                report.deleted += 1;
+            }
+            set(&transaction, "reconciled_at", &now.to_string())?;
+            report.reconciled = true;
+        }
+        if let Some(latest) = latest {
+            set(&transaction, "last_edited_time", &latest)?;
+        }
+        transaction.commit()?;
+
+        Ok(report)
+    }
+
+    /// Add columns and tables for properties the mirror doesn't have yet
+    fn update_schema(
+        &self,
+        database: &DatabaseObject,
+    ) -> Result<Vec<(String, ColumnKind)>, Box<dyn Error>> {
+        let existing = self
+            .connection
+            .prepare("SELECT name FROM pragma_table_info('rows')")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        let names = Exporter::new(database, ExportOptions::default()).headers();
+        let mut columns = Vec::new();
+        for name in names {
+            let kind = column_kind(&database.properties[&name]);
+            match kind {
+                ColumnKind::List => {
+                    self.connection.execute_batch(&format!(
+                        "CREATE TABLE IF NOT EXISTS {} (row_id TEXT NOT NULL, value TEXT NOT NULL);
+                         CREATE INDEX IF NOT EXISTS {} ON {} (row_id);",
+                        quote(&format!("rows_{}", name)),
+                        quote(&format!("rows_{}_row_id", name)),
+                        quote(&format!("rows_{}", name)),
+                    ))?;
+                }
+                ColumnKind::Date => {
+                    let start = column_name(&name);
+                    for column in [format!("{}:end", start), start] {
+                        if !existing.contains(&column) {
+                            self.add_column(&column, "TEXT")?;
+                        }
+                    }
+                }
+                ColumnKind::Scalar(sql_type) => {
+                    let column = column_name(&name);
+                    if !existing.contains(&column) {
+                        self.add_column(&column, sql_type)?;
+                    }
+                }
+            }
+            columns.push((name, kind));
+        }
+        Ok(columns)
+    }
+
+    fn add_column(&self, name: &str, sql_type: &str) -> Result<(), rusqlite::Error> {
+        self.connection.execute_batch(&format!(
+            "ALTER TABLE rows ADD COLUMN {} {}",
+            quote(name),
+            sql_type
+        ))
+    }
+
+    /// The id and edit time of every stored row
+    fn stored(&self) -> Result<HashMap<String, String>, rusqlite::Error> {
+        self.connection
+            .prepare("SELECT id, last_edited_time FROM rows")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, rusqlite::Error> {
+        self.connection
+            .query_row("SELECT value FROM sync WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+    }
+}
+
+/// Rows edited at or after `since`, newest first. Notion rounds edit times to the minute so rows
+/// edited in the same minute as the last sync are fetched again.
+async fn changed_rows(
+    client: &Client,
+    database_id: &str,
+    since: Option<&str>,
+) -> Result<Vec<PageObject>, Box<dyn Error>> {
+    let sorts = vec![QueryDatabaseSort {
+        property: None,
+        timestamp: Some(QueryDatabaseTime::LastEditedTime),
+        direction: Some(Direction::Descending),
+    }];
+    let mut rows = Vec::new();
+    let mut start_cursor = None;
+    loop {
+        let list = client
+            .query_database(database_id, None, Some(sorts.clone()), start_cursor, None)
+            .await?
+            .into_list()?;
+        for result in list.results {
+            let row = result.into_page()?;
+            if since.is_some_and(|since| row.last_edited_time.to_string().as_str() < since) {
+                return Ok(rows);
+            }
+            rows.push(row);
+        }
+        match list.next_cursor {
+            Some(cursor) if list.has_more => start_cursor = Some(cursor),
+            _ => return Ok(rows),
+        }
+    }
+}
+
+fn column_kind(property: &DatabaseProperty) -> ColumnKind {
+    match property {
+        DatabaseProperty::Number { .. } => ColumnKind::Scalar("REAL"),
+        DatabaseProperty::Checkbox { .. } => ColumnKind::Scalar("INTEGER"),
+        DatabaseProperty::Date { .. } => ColumnKind::Date,
+        DatabaseProperty::MultiSelect { .. }
+        | DatabaseProperty::Relation { .. }
+        | DatabaseProperty::People { .. }
+        | DatabaseProperty::Files { .. } => ColumnKind::List,
+        // Formulas and rollups can be any type so the column doesn't have one
+        DatabaseProperty::Formula { .. }
+        | DatabaseProperty::Rollup { .. }
+        | DatabaseProperty::Unknown { .. } => ColumnKind::Scalar(""),
+        _ => ColumnKind::Scalar("TEXT"),
+    }
+}
+
+fn write_row(
+    connection: &Connection,
+    exporter: &Exporter,
+    columns: &[(String, ColumnKind)],
+    row: &PageObject,
+) -> Result<(), Box<dyn Error>> {
+    let mut names = vec![quote("id"), quote("last_edited_time")];
+    let mut values = vec![
+        SqlValue::Text(row.id.clone()),
+        SqlValue::Text(row.last_edited_time.to_string()),
+    ];
+    let mut lists = Vec::new();
+    for ((name, kind), value) in columns.iter().zip(exporter.values(row)) {
+        match kind {
+            ColumnKind::List => lists.push((name, value)),
+            ColumnKind::Date => {
+                let text = cell(&value);
+                let (start, end) = match text.split_once('/') {
+                    Some((start, end)) => (Some(start), Some(end)),
+                    None => (Some(text.as_str()).filter(|t| !t.is_empty()), None),
+                };
+                let column = column_name(name);
+                names.push(quote(&column));
+                values.push(start.map_or(SqlValue::Null, |s| SqlValue::Text(s.to_string())));
+                names.push(quote(&format!("{}:end", column)));
+                values.push(end.map_or(SqlValue::Null, |e| SqlValue::Text(e.to_string())));
+            }
+            ColumnKind::Scalar(_) => {
+                names.push(quote(&column_name(name)));
+                values.push(sql_value(value));
+            }
+        }
+    }
+
+    connection.execute(
+        &format!(
+            "INSERT OR REPLACE INTO rows ({}) VALUES ({})",
+            names.join(", "),
+            vec!["?"; names.len()].join(", ")
+        ),
+        params_from_iter(values),
+    )?;
+    for (name, value) in lists {
+        let table = quote(&format!("rows_{}", name));
+        connection.execute(
+            &format!("DELETE FROM {} WHERE row_id = ?1", table),
+            [&row.id],
+        )?;
+        let items = match value {
+            Value::Array(items) => items,
+            Value::Null => Vec::new(),
+            value => vec![value],
+        };
+        for item in items {
+            connection.execute(
+                &format!("INSERT INTO {} (row_id, value) VALUES (?1, ?2)", table),
+                params![row.id, cell(&item)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn delete_row(
+    connection: &Connection,
+    columns: &[(String, ColumnKind)],
+    id: &str,
+) -> Result<(), rusqlite::Error> {
+    connection.execute("DELETE FROM rows WHERE id = ?1", [id])?;
+    for (name, kind) in columns {
+        if *kind == ColumnKind::List {
+            connection.execute(
+                &format!(
+                    "DELETE FROM {} WHERE row_id = ?1",
+                    quote(&format!("rows_{}", name))
+                ),
+                [id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn set(connection: &Connection, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+    connection.execute(
+        "INSERT OR REPLACE INTO sync (key, value) VALUES (?1, ?2)",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+fn sql_value(value: Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Integer(value as i64),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => SqlValue::Integer(integer),
+            None => number.as_f64().map_or(SqlValue::Null, SqlValue::Real),
+        },
+        Value::String(text) => SqlValue::Text(text),
+        value => SqlValue::Text(cell(&value)),
+    }
+}
+
+/// The column of a property in `rows`. SQLite doesn't tell identifiers apart by case, so neither
+/// does the check for the columns every row has.
+fn column_name(property: &str) -> String {
+    if ["id", "last_edited_time"]
+        .iter()
+        .any(|fixed| property.eq_ignore_ascii_case(fixed))
+    {
+        format!("property:{}", property)
+    } else {
+        property.to_string()
+    }
+}
+
+/// Property names can contain anything so every identifier is quoted
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,220 @@
+//! Pages created from a skeleton of property values and blocks. Any string in a template can
+//! contain `{{name}}` placeholders, including rich text, select options and urls, which are
+//! filled in from a map or a struct with [`Template::fill`].
+use crate::{
+    block::{BlockObjectInput, BlockTree},
+    page::{PagePropertyValue, Parent},
+    restore::children,
+    BoxFuture, Client, NotionObject,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Template {
+    #[serde(default)]
+    pub properties: HashMap<String, PagePropertyValue>,
+    #[serde(default)]
+    pub children: Vec<TemplateBlock>,
+}
+
+/// A block to create along with the blocks nested under it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TemplateBlock {
+    #[serde(flatten)]
+    pub block: BlockObjectInput,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TemplateBlock>,
+}
+
+impl Template {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Make a template out of an existing page's properties and content. Child pages and values
+    /// Notion computes itself like formulas aren't included.
+    pub async fn capture(client: &Client, page_id: &str) -> Result<Self, Box<dyn Error>> {
+        let page = client.get_page(page_id).await?.into_page()?;
+        let trees = client.get_block_tree(page_id).await?;
+        Ok(Self {
+            properties: page
+                .properties
+                .iter()
+                .filter_map(|(name, property)| Some((name.clone(), property.to_value()?)))
+                .collect(),
+            children: template_blocks(&trees),
+        })
+    }
+
+    /// The names of every placeholder used in the template
+    pub fn placeholders(&self) -> Result<BTreeSet<String>, serde_json::Error> {
+        let mut names = BTreeSet::new();
+        let mut value = serde_json::to_value(self)?;
+        substitute_value(&mut value, &Map::new(), &mut names);
+        Ok(names)
+    }
+
+    /// Fill in the placeholders with the fields of `values`, which can be a map or any struct
+    /// that serializes to one. Strings are inserted as is and anything else as JSON, placeholders
+    /// without a value are an error.
+    pub fn fill(&self, values: &impl Serialize) -> Result<Self, Box<dyn Error>> {
+        let values = match serde_json::to_value(values)? {
+            Value::Object(values) => values,
+            _ => return Err("Template values must serialize to a map".into()),
+        };
+        let mut missing = BTreeSet::new();
+        let mut value = serde_json::to_value(self)?;
+        substitute_value(&mut value, &values, &mut missing);
+        if !missing.is_empty() {
+            return Err(Box::new(TemplateError {
+                missing: missing.into_iter().collect(),
+            }));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Create a page from the template, blocks nested deeper than the top level are added once
+    /// their parents exist
+    pub async fn create(
+        &self,
+        client: &Client,
+        parent: Parent,
+    ) -> Result<NotionObject, Box<dyn Error>> {
+        // Notion accepts at most 100 blocks when creating a page
+        let (first, rest) = self.children.split_at(self.children.len().min(100));
+        let page = client
+            .create_page(
+                parent,
+                self.properties.clone(),
+                Some(first.iter().map(|b| b.block.clone()).collect()),
+            )
+            .await?
+            .into_page()?;
+        append_children(client, &page.id, first, 0).await?;
+        for chunk in rest.chunks(100) {
+            let existing = children(client, &page.id).await?.len();
+            client
+                .append_block_children(&page.id, chunk.iter().map(|b| b.block.clone()).collect())
+                .await?
+                .into_result()?;
+            append_children(client, &page.id, chunk, existing).await?;
+        }
+        Ok(NotionObject::Page(page))
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateError {
+    pub missing: Vec<String>,
+}
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No value for template placeholders: {}",
+            self.missing.join(", ")
+        )
+    }
+}
+impl Error for TemplateError {}
+
+fn template_blocks(trees: &[BlockTree]) -> Vec<TemplateBlock> {
+    trees
+        .iter()
+        .filter_map(|tree| {
+            Some(TemplateBlock {
+                block: tree.block.to_input()?,
+                children: template_blocks(&tree.children),
+            })
+        })
+        .collect()
+}
+
+/// Append the nested children of `blocks`, which were just created under `id` after `skip`
+/// existing blocks
+pub(crate) fn append_children<'a>(
+    client: &'a Client,
+    id: &'a str,
+    blocks: &'a [TemplateBlock],
+    skip: usize,
+) -> BoxFuture<'a, Result<(), Box<dyn Error>>> {
+    Box::pin(async move {
+        if blocks.iter().all(|b| b.children.is_empty()) {
+            return Ok(());
+        }
+        let created = children(client, id).await?;
+        for (block, template) in created.iter().skip(skip).zip(blocks) {
+            let new_id = match (block.id(), template.children.is_empty()) {
+                (Some(new_id), false) => new_id,
+                _ => continue,
+            };
+            for chunk in template.children.chunks(100) {
+                let existing = children(client, new_id).await?.len();
+                client
+                    .append_block_children(new_id, chunk.iter().map(|b| b.block.clone()).collect())
+                    .await?
+                    .into_result()?;
+                append_children(client, new_id, chunk, existing).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn substitute_value(
+    value: &mut Value,
+    values: &Map<String, Value>,
+    missing: &mut BTreeSet<String>,
+) {
+    match value {
+        Value::String(text) => *text = substitute(text, values, missing),
+        Value::Array(items) => {
+            for item in items {
+                substitute_value(item, values, missing);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                substitute_value(field, values, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn substitute(text: &str, values: &Map<String, Value>, missing: &mut BTreeSet<String>) -> String {
+    let mut filled = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let len = match rest[start + 2..].find("}}") {
+            Some(len) => len,
+            None => break,
+        };
+        let end = start + len + 4;
+        filled.push_str(&rest[..start]);
+        let name = rest[start + 2..end - 2].trim();
+        match values.get(name) {
+            Some(Value::String(value)) => filled.push_str(value),
+            Some(Value::Null) => {}
+            Some(value) => filled.push_str(&value.to_string()),
+            None => {
+                missing.insert(name.to_string());
+                filled.push_str(&rest[start..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// A timestamp from Notion. Date properties and mentions without a time hold only the date, e.g.
+/// `2021-05-13`, and are written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Time {
+    DateTime(DateTime<Utc>),
+    Date(NaiveDate),
+}
+
+impl Time {
+    /// The instant this is, dates are the start of the day in UTC
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        match self {
+            Self::DateTime(time) => *time,
+            Self::Date(date) => date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            Self::DateTime(time) => time.date_naive(),
+            Self::Date(date) => *date,
+        }
+    }
+
+    pub fn is_date(&self) -> bool {
+        matches!(self, Self::Date(_))
+    }
+}
+
+impl From<DateTime<Utc>> for Time {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self::DateTime(time)
+    }
+}
+
+impl From<NaiveDate> for Time {
+    fn from(date: NaiveDate) -> Self {
+        Self::Date(date)
+    }
+}
+
+/// The form Notion sends, an RFC 3339 time in UTC or just the date
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DateTime(time) => f.write_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Self::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+        DateTime::parse_from_rfc3339(&text)
+            .map(|time| Self::DateTime(time.with_timezone(&Utc)))
+            .map_err(|_| D::Error::custom(format!("{:?} isn't a date or an RFC 3339 time", text)))
+    }
+}
@@ -0,0 +1,104 @@
+//! Helpers that let the types in this crate keep data from Notion they don't understand yet
+//! instead of failing to deserialize. Every enum has an `Unknown { type_name, raw }` fallback
+//! variant and every struct an `extra` map of unrecognized fields, both are written back out
+//! unchanged when serialized. With the `strict` feature enabled these cases are errors instead.
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn unknown<E: Error>(type_name: String, raw: Value) -> Result<(String, Value), E> {
+    if cfg!(feature = "strict") {
+        Err(E::custom(format!("unknown variant `{}`", type_name)))
+    } else {
+        Ok((type_name, raw))
+    }
+}
+
+fn tag<E: Error>(raw: &Value, tag: &'static str) -> Result<String, E> {
+    raw.get(tag)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| E::missing_field(tag))
+}
+
+/// For enums tagged with a field like `type`. Objects with one of the `known` tags are decoded
+/// by `variant` so a malformed one is an error instead of ending up in `unknown_variant`.
+pub(crate) fn deserialize_tagged<'de, D: Deserializer<'de>, T>(
+    deserializer: D,
+    tag: &'static str,
+    known: &[&str],
+    variant: impl FnOnce(Value) -> Result<T, serde_json::Error>,
+    unknown_variant: impl FnOnce(String, Value) -> T,
+) -> Result<T, D::Error> {
+    let raw = Value::deserialize(deserializer)?;
+    let type_name = self::tag(&raw, tag)?;
+    if known.contains(&type_name.as_str()) {
+        return variant(raw).map_err(D::Error::custom);
+    }
+    let (type_name, raw) = unknown(type_name, raw)?;
+    Ok(unknown_variant(type_name, raw))
+}
+
+/// Implements `Deserialize` and `Serialize` for an enum with an `Unknown` variant using
+/// [`deserialize_tagged`]. The enum derives both with `#[serde(remote = "Self")]` and skips
+/// `Unknown`, which is written back out as its raw value.
+macro_rules! tagged_enum {
+    ($name:ident, $tag:literal, [$($known:literal),* $(,)?]) => {
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                crate::unknown::deserialize_tagged(
+                    deserializer,
+                    $tag,
+                    &[$($known),*],
+                    $name::deserialize,
+                    |type_name, raw| $name::Unknown { type_name, raw },
+                )
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    $name::Unknown { raw, .. } => serde::Serialize::serialize(raw, serializer),
+                    known => $name::serialize(known, serializer),
+                }
+            }
+        }
+    };
+}
+pub(crate) use tagged_enum;
+
+/// For enums that are a plain string e.g. colors
+pub(crate) fn deserialize_name<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(String, Value), D::Error> {
+    let raw = Value::deserialize(deserializer)?;
+    let type_name = raw
+        .as_str()
+        .ok_or_else(|| D::Error::custom("expected a string"))?
+        .to_string();
+    unknown(type_name, raw)
+}
+
+pub(crate) fn serialize<S: Serializer>(
+    _type_name: &str,
+    raw: &Value,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    raw.serialize(serializer)
+}
+
+/// For the flattened `extra` map on structs
+pub(crate) fn extra<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Value>, D::Error> {
+    let extra = HashMap::<String, Value>::deserialize(deserializer)?;
+    match extra.keys().next() {
+        Some(field) if cfg!(feature = "strict") => {
+            Err(D::Error::custom(format!("unknown field `{}`", field)))
+        }
+        _ => Ok(extra),
+    }
+}
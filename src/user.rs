@@ -1,9 +1,11 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
+#[serde(remote = "Self")]
 pub enum UserObject {
     Person {
         id: String,
@@ -17,12 +19,67 @@ pub enum UserObject {
         avatar_url: Option<String>,
         bot: Bot,
     },
+    #[serde(skip)]
+    Unknown {
+        type_name: String,
+        raw: serde_json::Value,
+    },
+    /// Notion only sends the id of a user in some places, it's also all that's needed to refer to
+    /// a user when writing. Only users without a `type` are read as this.
+    #[serde(skip)]
+    Partial { id: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct PartialUser {
+    id: String,
+}
+
+impl<'de> Deserialize<'de> for UserObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Value::deserialize(deserializer)?;
+        if raw.get("type").is_none() {
+            let PartialUser { id } = PartialUser::deserialize(raw).map_err(D::Error::custom)?;
+            return Ok(Self::Partial { id });
+        }
+        crate::unknown::deserialize_tagged(
+            raw,
+            "type",
+            &["person", "bot"],
+            UserObject::deserialize,
+            |type_name, raw| Self::Unknown { type_name, raw },
+        )
+        .map_err(D::Error::custom)
+    }
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+impl Serialize for UserObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Unknown { raw, .. } => raw.serialize(serializer),
+            Self::Partial { id } => PartialUser { id: id.clone() }.serialize(serializer),
+            known => UserObject::serialize(known, serializer),
+        }
+    }
+}
+impl UserObject {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Person { id, .. } | Self::Bot { id, .. } | Self::Partial { id } => Some(id),
+            Self::Unknown { raw, .. } => raw.get("id").and_then(serde_json::Value::as_str),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Person {
     pub email: String,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Bot {
     pub bot: Option<HashMap<(), ()>>,
+    #[serde(flatten, deserialize_with = "crate::unknown::extra")]
+    pub extra: HashMap<String, serde_json::Value>,
 }
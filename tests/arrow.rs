@@ -0,0 +1,93 @@
+#![cfg(feature = "arrow")]
+
+mod common;
+
+use arrow::{
+    array::{Array, Date32Array, TimestampMillisecondArray},
+    datatypes::DataType,
+};
+use common::{database, row};
+use inkling::arrow::record_batch;
+use serde_json::json;
+
+fn rows(dates: &[(&str, Option<&str>)]) -> Vec<inkling::page::PageObject> {
+    dates
+        .iter()
+        .enumerate()
+        .map(|(index, (start, end))| {
+            row(
+                &format!("r{}", index),
+                "d1",
+                "Row",
+                json!({ "When": { "id": "wh", "type": "date", "date": { "start": start, "end": end } } }),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn dates_without_a_time_are_date32() {
+    let database = database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({ "When": { "id": "wh", "type": "date", "date": {} } }),
+    );
+    let batch = record_batch(
+        &database,
+        &rows(&[("2021-05-13", Some("2021-05-15")), ("1970-01-02", None)]),
+    )
+    .unwrap();
+
+    let schema = batch.schema();
+    assert_eq!(
+        schema.field_with_name("When").unwrap().data_type(),
+        &DataType::Date32
+    );
+    let start = batch
+        .column_by_name("When")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Date32Array>()
+        .unwrap();
+    assert_eq!(start.value(1), 1);
+    assert_eq!(
+        start.value_as_date(0),
+        chrono::NaiveDate::from_ymd_opt(2021, 5, 13)
+    );
+    let end = batch
+        .column_by_name("When:end")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Date32Array>()
+        .unwrap();
+    assert_eq!(
+        end.value_as_date(0),
+        chrono::NaiveDate::from_ymd_opt(2021, 5, 15)
+    );
+    assert!(end.is_null(1));
+}
+
+#[test]
+fn dates_mixed_with_times_are_timestamps() {
+    let database = database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({ "When": { "id": "wh", "type": "date", "date": {} } }),
+    );
+    let batch = record_batch(
+        &database,
+        &rows(&[("1970-01-02", None), ("1970-01-01T00:00:01.000Z", None)]),
+    )
+    .unwrap();
+
+    let start = batch
+        .column_by_name("When")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<TimestampMillisecondArray>()
+        .unwrap();
+    assert_eq!(start.value(0), 24 * 60 * 60 * 1000);
+    assert_eq!(start.value(1), 1000);
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use async_trait::async_trait;
+use inkling::{
+    backup::{backup, BackupOptions, Manifest},
+    transport::{Request, Response, Transport},
+    Client,
+};
+use serde_json::json;
+use std::{error::Error, fs, path::PathBuf};
+
+fn backup_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A page with two attachments of the same name, and the files themselves
+struct Attachments;
+
+#[async_trait]
+impl Transport for Attachments {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        let list = |results: serde_json::Value| {
+            json!({
+                "object": "list",
+                "results": results,
+                "next_cursor": null,
+                "has_more": false,
+            })
+        };
+        let body = if let Some(file) = request.url.strip_prefix("https://files.example/") {
+            file.as_bytes().to_vec()
+        } else if request.url.ends_with("search") {
+            let search = serde_json::from_slice::<serde_json::Value>(&request.body.unwrap())?;
+            if search["filter"]["value"] != "page" {
+                serde_json::to_vec(&list(json!([])))?
+            } else {
+                let file = |url: &str| {
+                    json!({
+                        "name": "notes.pdf",
+                        "type": "file",
+                        "file": { "url": url, "expiry_time": "2021-05-01T10:00:00.000Z" },
+                    })
+                };
+                let mut page = serde_json::to_value(common::page("p1", "root", "Page"))?;
+                page["object"] = json!("page");
+                page["properties"]["Files"] = json!({
+                    "id": "fl",
+                    "type": "files",
+                    "files": [
+                        file("https://files.example/one"),
+                        file("https://files.example/two"),
+                    ],
+                });
+                serde_json::to_vec(&list(json!([page])))?
+            }
+        } else {
+            serde_json::to_vec(&list(json!([])))?
+        };
+        Ok(Response {
+            status: 200,
+            headers: Vec::new(),
+            body,
+        })
+    }
+}
+
+#[tokio::test]
+async fn keeps_attachments_with_the_same_name_apart() {
+    let dir = backup_dir("backup-attachments");
+    let client = Client::with_transport("secret", Attachments);
+    let options = BackupOptions {
+        download_files: true,
+        ..BackupOptions::default()
+    };
+    backup(&client, &dir, &options).await.unwrap();
+
+    let manifest = Manifest::load(&dir).unwrap();
+    assert_eq!(
+        manifest.pages["p1"].files,
+        ["files/p1/notes.pdf", "files/p1/notes-2.pdf"]
+    );
+    assert_eq!(fs::read(dir.join("files/p1/notes.pdf")).unwrap(), b"one");
+    assert_eq!(fs::read(dir.join("files/p1/notes-2.pdf")).unwrap(), b"two");
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn keeps_rows_that_werent_edited_and_drops_archived_ones() {
+    use inkling::{mock::MockServer, time::Time};
+
+    let edited = |page: &mut inkling::page::PageObject, time: &str| {
+        page.last_edited_time = serde_json::from_value::<Time>(json!(time)).unwrap();
+    };
+    let server = MockServer::start().await.unwrap();
+    server.seed_page(&common::page("top", "root", "Top"));
+    server.seed_database(&common::database("d1", "top", "Tasks", json!({})));
+    let mut rows = ["r1", "r2", "r3"]
+        .iter()
+        .map(|id| common::row(id, "d1", id, json!({})))
+        .collect::<Vec<_>>();
+    edited(&mut rows[0], "2021-05-01T09:00:00.000Z");
+    for row in &rows {
+        server.seed_page(row);
+    }
+
+    let dir = backup_dir("backup-rows");
+    let client = server.client();
+    backup(&client, &dir, &BackupOptions::default())
+        .await
+        .unwrap();
+    assert!(dir.join("databases/d1.json").exists());
+    fs::write(
+        dir.join("databases/d1.json"),
+        "unchanged databases aren't rewritten",
+    )
+    .unwrap();
+
+    rows[1].archived = true;
+    edited(&mut rows[1], "2021-06-01T09:00:00.000Z");
+    server.seed_page(&rows[1]);
+    edited(&mut rows[2], "2021-06-01T09:00:00.000Z");
+    server.seed_page(&rows[2]);
+    server.clear_requests();
+    let report = backup(&client, &dir, &BackupOptions::default())
+        .await
+        .unwrap();
+
+    let manifest = Manifest::load(&dir).unwrap();
+    assert_eq!(manifest.databases["d1"].rows, ["r1", "r3"]);
+    assert_eq!(report.removed, 1);
+    assert!(!dir.join("pages/r2.json").exists());
+    assert_eq!(
+        manifest.pages["r3"].last_edited_time,
+        "2021-06-01T09:00:00Z"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("databases/d1.json")).unwrap(),
+        "unchanged databases aren't rewritten"
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn fetches_pages_edited_in_the_minute_of_the_last_run_again() {
+    use inkling::{block::BlockTree, mock::MockServer, time::Time};
+
+    let tree = |text: &str| BlockTree {
+        block: common::paragraph("b1", text),
+        children: Vec::new(),
+    };
+    let server = MockServer::start().await.unwrap();
+    // Edit times are truncated to the minute, so one that isn't before the last run started
+    // could hide a later edit
+    let mut page = common::page("p1", "root", "Page");
+    page.last_edited_time =
+        serde_json::from_value::<Time>(json!("2100-01-01T00:00:00.000Z")).unwrap();
+    server.seed_page(&page);
+    server.seed_blocks("p1", &[tree("before")]);
+
+    let dir = backup_dir("backup-same-minute");
+    let client = server.client();
+    backup(&client, &dir, &BackupOptions::default())
+        .await
+        .unwrap();
+    server.seed_blocks("p1", &[tree("after")]);
+    let report = backup(&client, &dir, &BackupOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!((report.fetched, report.unchanged), (1, 0));
+    let blocks = fs::read_to_string(dir.join("blocks/p1.json")).unwrap();
+    assert!(blocks.contains("after"));
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "mock")]
+
+use inkling::{
+    block::{BlockObject, BlockTree},
+    graph::{EdgeKind, PageGraph},
+    mock::{Fixtures, MockServer},
+    rich_text::{Mention, RichTextObject},
+};
+
+mod common;
+
+#[tokio::test]
+async fn stops_at_child_pages() {
+    let server = MockServer::start().await.unwrap();
+    let mut fixtures = Fixtures::default();
+    fixtures
+        .pages
+        .push(common::page("parent", "root", "Parent"));
+    fixtures.pages.push(common::page("sub", "parent", "Sub"));
+    fixtures.blocks.insert(
+        "parent".to_string(),
+        vec![
+            BlockTree {
+                block: common::paragraph("p1", "intro"),
+                children: vec![BlockTree {
+                    block: common::paragraph("p2", "nested"),
+                    children: Vec::new(),
+                }],
+            },
+            BlockTree {
+                block: common::child_page("sub", "Sub"),
+                children: Vec::new(),
+            },
+        ],
+    );
+    fixtures.blocks.insert(
+        "sub".to_string(),
+        vec![BlockTree {
+            block: common::paragraph("s1", "inside the sub-page"),
+            children: Vec::new(),
+        }],
+    );
+    server.seed(&fixtures);
+
+    let tree = server.client().get_block_tree("parent").await.unwrap();
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0].children.len(), 1);
+    assert!(matches!(tree[1].block, BlockObject::ChildPage { .. }));
+    assert!(tree[1].block.has_children());
+    assert!(tree[1].children.is_empty());
+
+    let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, ["blocks/parent/children", "blocks/p1/children"]);
+}
+
+#[test]
+fn graph_leaves_sub_page_blocks_to_the_sub_page() {
+    let mention = RichTextObject::mention(Mention::Page {
+        page: serde_json::from_value(serde_json::json!({ "id": "target" })).unwrap(),
+    });
+    let mut linking = common::paragraph("s1", "");
+    *linking.text_mut().unwrap() = vec![mention];
+    // A tree saved before sub-pages were left out still has their blocks under them
+    let blocks = vec![BlockTree {
+        block: common::child_page("sub", "Sub"),
+        children: vec![BlockTree {
+            block: linking,
+            children: Vec::new(),
+        }],
+    }];
+
+    let mut graph = PageGraph::new();
+    graph.add_page(&common::page("parent", "root", "Parent"), &blocks);
+    let links: Vec<_> = graph
+        .links("parent")
+        .into_iter()
+        .map(|edge| (edge.to.as_str(), edge.kind))
+        .collect();
+    assert_eq!(
+        links,
+        [("root", EdgeKind::Parent), ("sub", EdgeKind::ChildPage)]
+    );
+}
@@ -0,0 +1,83 @@
+#![cfg(all(feature = "blocking", feature = "mock"))]
+mod common;
+
+use inkling::{
+    blocking::Client,
+    mock::MockServer,
+    page::{PageProperty, PagePropertyValue, Parent},
+    rich_text::RichTextObject,
+};
+use serde_json::json;
+use std::{collections::HashMap, sync::mpsc, thread};
+
+/// The mock server runs on its own thread, since the blocking client can't be used from inside
+/// an async runtime
+fn server() -> MockServer {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            sender.send(MockServer::start().await.unwrap()).unwrap();
+            std::future::pending::<()>().await
+        })
+    });
+    receiver.recv().unwrap()
+}
+
+fn name(name: &str) -> HashMap<String, PagePropertyValue> {
+    let title = vec![RichTextObject::text(name)];
+    HashMap::from([("Name".to_string(), PagePropertyValue::Title { title })])
+}
+
+fn title(property: &PageProperty) -> String {
+    match property {
+        PageProperty::Title { title, .. } => title.iter().map(|span| span.plain_text()).collect(),
+        other => panic!("Expected a title, got {:?}", other),
+    }
+}
+
+#[test]
+fn round_trips_through_the_mock() {
+    let server = server();
+    server.seed_database(&common::database("d1", "root", "Groceries", json!({})));
+    let client = Client::new("secret_mock").with_base_url(server.url());
+
+    let paragraph = serde_json::from_value(json!({
+        "object": "block",
+        "type": "paragraph",
+        "paragraph": { "text": [RichTextObject::text("Two litres")] },
+    }))
+    .unwrap();
+    let page = client
+        .create_page(
+            Parent::DatabaseId {
+                database_id: "d1".to_string(),
+            },
+            name("Milk"),
+            Some(vec![paragraph]),
+        )
+        .unwrap()
+        .into_page()
+        .unwrap();
+
+    let rows = client.query_database_all("d1", None, None).unwrap();
+    assert_eq!(rows.len(), 1);
+    client
+        .update_page_properties(&page.id, name("Oat milk"))
+        .unwrap()
+        .into_result()
+        .unwrap();
+
+    let read = client.get_page(&page.id).unwrap().into_page().unwrap();
+    assert_eq!(title(&read.properties["Name"]), "Oat milk");
+    assert_eq!(
+        title(&server.page(&page.id).unwrap().properties["Name"]),
+        "Oat milk"
+    );
+    let trees = client.get_block_tree(&page.id).unwrap();
+    let text = trees[0].block.text().unwrap();
+    assert_eq!(text[0].plain_text(), "Two litres");
+}
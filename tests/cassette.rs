@@ -0,0 +1,133 @@
+mod common;
+
+use async_trait::async_trait;
+use inkling::{
+    cassette::{Cassette, CassetteError},
+    transport::{Request, Response, Transport},
+    Client,
+};
+use serde_json::json;
+use std::{error::Error, path::PathBuf};
+
+const CASSETTES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes");
+
+/// Fails every request, so anything that gets an answer was played back
+struct Offline;
+
+#[async_trait]
+impl Transport for Offline {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        Err(format!("Tried to send {}", request.url).into())
+    }
+}
+
+/// Answers every request with a user named after the token it was sent with
+struct Echo;
+
+#[async_trait]
+impl Transport for Echo {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        let (_, auth) = request
+            .headers
+            .iter()
+            .find(|(name, _)| name == "Authorization")
+            .ok_or("No Authorization header")?;
+        let user = json!({ "object": "user", "id": "u1", "type": "bot", "bot": {}, "name": auth });
+        Ok(Response {
+            status: 200,
+            headers: Vec::new(),
+            body: serde_json::to_vec(&user)?,
+        })
+    }
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn replaying(name: &str) -> Client {
+    let cassette = Cassette::replaying(format!("{}/{}", CASSETTES, name)).unwrap();
+    Client::with_transport("", Offline).with_cassette(cassette)
+}
+
+#[tokio::test]
+async fn replays_offline() {
+    let client = replaying("get_page.json");
+    let page = client.get_page("p1").await.unwrap().into_page().unwrap();
+    assert_eq!(page.id, "p1");
+    let list = client
+        .search(Some("Recorded".to_string()), None, None, None, None)
+        .await
+        .unwrap()
+        .into_list()
+        .unwrap();
+    assert!(list.results.is_empty());
+}
+
+#[tokio::test]
+async fn fails_requests_that_werent_recorded() {
+    let client = replaying("get_page.json");
+    client.get_page("p1").await.unwrap();
+    // Each recording is only played back once
+    let error = client.get_page("p1").await.unwrap_err();
+    let error = error.downcast_ref::<CassetteError>().unwrap();
+    assert_eq!(
+        (error.method.as_str(), error.request_path.as_str()),
+        ("GET", "pages/p1")
+    );
+    assert!(error.to_string().contains("No unused recording"));
+
+    let error = client
+        .search(Some("Something else".to_string()), None, None, None, None)
+        .await
+        .unwrap_err();
+    let error = error.downcast_ref::<CassetteError>().unwrap();
+    assert_eq!(error.body, Some(json!({ "query": "Something else" })));
+}
+
+#[tokio::test]
+async fn redacts_the_token() {
+    let path = scratch("redacted.json");
+    let client =
+        Client::with_transport("secret_abc123", Echo).with_cassette(Cassette::recording(&path));
+    let user = client.get_user("u1").await.unwrap();
+    // The client still sees the real response
+    assert!(serde_json::to_string(&user)
+        .unwrap()
+        .contains("secret_abc123"));
+
+    let recorded = std::fs::read_to_string(&path).unwrap();
+    assert!(!recorded.contains("secret_abc123"), "{}", recorded);
+    assert!(recorded.contains("Bearer [redacted]"));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn records_and_replays() {
+    use inkling::{mock::MockServer, NotionObject};
+
+    let server = MockServer::start().await.unwrap();
+    server.seed_page(&common::page("p1", "root", "Round trip"));
+    let path = scratch("round_trip.json");
+    let recording = server.client().with_cassette(Cassette::recording(&path));
+    let recorded = recording.get_page("p1").await.unwrap().into_page().unwrap();
+    let missing = recording.get_page("p2").await.unwrap();
+    assert!(matches!(missing, NotionObject::Error(_)));
+    assert_eq!(server.requests().len(), 2);
+
+    let cassette = Cassette::replaying(&path).unwrap();
+    assert_eq!(cassette.interactions().len(), 2);
+    let client = Client::with_transport("", Offline).with_cassette(cassette);
+    let replayed = client.get_page("p1").await.unwrap().into_page().unwrap();
+    assert_eq!(
+        serde_json::to_value(&replayed).unwrap(),
+        serde_json::to_value(&recorded).unwrap()
+    );
+    assert!(matches!(
+        client.get_page("p2").await.unwrap(),
+        NotionObject::Error(_)
+    ));
+    assert_eq!(server.requests().len(), 2);
+}
@@ -0,0 +1,77 @@
+//! Builders for the objects the tests feed to the client and the mock server
+#![allow(dead_code)]
+
+use inkling::{
+    block::BlockObject, database::DatabaseObject, page::PageObject, rich_text::RichTextObject,
+};
+use serde_json::{json, Value};
+
+pub const TIME: &str = "2021-05-13T09:00:00.000Z";
+
+/// A block of type `kind` holding `body`
+pub fn block_json(id: &str, kind: &str, body: Value) -> Value {
+    let mut block = json!({
+        "id": id,
+        "type": kind,
+        "created_time": TIME,
+        "last_edited_time": TIME,
+        "has_children": false,
+    });
+    block[kind] = body;
+    block
+}
+
+pub fn paragraph(id: &str, text: &str) -> BlockObject {
+    let text = serde_json::to_value(vec![RichTextObject::text(text)]).unwrap();
+    serde_json::from_value(block_json(id, "paragraph", json!({ "text": text }))).unwrap()
+}
+
+pub fn child_page(id: &str, title: &str) -> BlockObject {
+    serde_json::from_value(block_json(id, "child_page", json!({ "title": title }))).unwrap()
+}
+
+/// A page under another page with just a title
+pub fn page(id: &str, parent: &str, title: &str) -> PageObject {
+    let title = serde_json::to_value(vec![RichTextObject::text(title)]).unwrap();
+    serde_json::from_value(json!({
+        "id": id,
+        "created_time": TIME,
+        "last_edited_time": TIME,
+        "archived": false,
+        "parent": { "type": "page_id", "page_id": parent },
+        "properties": { "title": { "id": "title", "type": "title", "title": title } },
+    }))
+    .unwrap()
+}
+
+/// An inline database under a page with the given properties besides its title
+pub fn database(id: &str, parent: &str, title: &str, properties: Value) -> DatabaseObject {
+    let title = serde_json::to_value(vec![RichTextObject::text(title)]).unwrap();
+    let mut properties = properties;
+    properties["Name"] = json!({ "id": "title", "type": "title", "title": {} });
+    serde_json::from_value(json!({
+        "id": id,
+        "created_time": TIME,
+        "last_edited_time": TIME,
+        "title": title,
+        "parent": { "type": "page_id", "page_id": parent },
+        "properties": properties,
+    }))
+    .unwrap()
+}
+
+/// A row of a database with a `Name` title and the given properties
+pub fn row(id: &str, database: &str, name: &str, properties: Value) -> PageObject {
+    let title = serde_json::to_value(vec![RichTextObject::text(name)]).unwrap();
+    let mut properties = properties;
+    properties["Name"] = json!({ "id": "title", "type": "title", "title": title });
+    serde_json::from_value(json!({
+        "id": id,
+        "created_time": TIME,
+        "last_edited_time": TIME,
+        "archived": false,
+        "parent": { "type": "database_id", "database_id": database },
+        "properties": properties,
+    }))
+    .unwrap()
+}
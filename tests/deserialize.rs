@@ -0,0 +1,269 @@
+use inkling::{
+    block::{self, BlockObject, BlockTree},
+    page::{PageFormula, PageProperty, PagePropertyValue, PageRollup},
+    rich_text::{Mention, RichTextObject},
+    time::Time,
+    user::UserObject,
+};
+use serde_json::json;
+
+mod common;
+
+fn block(kind: &str, body: serde_json::Value) -> serde_json::Value {
+    common::block_json("b1", kind, body)
+}
+
+#[test]
+fn empty_select_and_date() {
+    let select: PageProperty =
+        serde_json::from_value(json!({ "id": "a", "type": "select", "select": null })).unwrap();
+    assert!(matches!(select, PageProperty::Select { select: None, .. }));
+
+    let date: PageProperty =
+        serde_json::from_value(json!({ "id": "b", "type": "date", "date": null })).unwrap();
+    assert!(matches!(date, PageProperty::Date { date: None, .. }));
+}
+
+#[test]
+fn empty_number_url_email_and_phone_number() {
+    for kind in ["number", "url", "email", "phone_number"] {
+        let property: PageProperty =
+            serde_json::from_value(json!({ "id": "a", "type": kind, kind: null })).unwrap();
+        assert!(
+            matches!(
+                property,
+                PageProperty::Number { number: None, .. }
+                    | PageProperty::Url { url: None, .. }
+                    | PageProperty::Email { email: None, .. }
+                    | PageProperty::PhoneNumber {
+                        phone_number: None,
+                        ..
+                    }
+            ),
+            "{:?}",
+            property
+        );
+        let value = property.to_value().unwrap();
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!({ kind: null }));
+        let value: PagePropertyValue = serde_json::from_value(json!({ kind: null })).unwrap();
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!({ kind: null }));
+    }
+}
+
+#[test]
+fn date_formula_and_rollup() {
+    let formula: PageProperty = serde_json::from_value(json!({
+        "id": "a",
+        "type": "formula",
+        "formula": { "type": "date", "date": { "start": "2021-05-01", "end": "2021-05-03" } },
+    }))
+    .unwrap();
+    match formula {
+        PageProperty::Formula {
+            formula: PageFormula::Date { date: Some(date) },
+            ..
+        } => {
+            assert_eq!(date.start.date().to_string(), "2021-05-01");
+            assert!(date.end.is_some_and(|end| end.is_date()));
+        }
+        other => panic!("expected a date formula, got {:?}", other),
+    }
+
+    let rollup: PageProperty = serde_json::from_value(json!({
+        "id": "b",
+        "type": "rollup",
+        "rollup": { "type": "date", "date": null },
+    }))
+    .unwrap();
+    assert!(matches!(
+        rollup,
+        PageProperty::Rollup {
+            rollup: PageRollup::Date { date: None },
+            ..
+        }
+    ));
+}
+
+#[test]
+fn date_without_time() {
+    let property: PageProperty = serde_json::from_value(json!({
+        "id": "a",
+        "type": "date",
+        "date": { "start": "2021-05-01", "end": null },
+    }))
+    .unwrap();
+    let date = match property {
+        PageProperty::Date {
+            date: Some(date), ..
+        } => date,
+        other => panic!("expected a date, got {:?}", other),
+    };
+    assert!(date.start.is_date());
+    assert_eq!(date.start.date().to_string(), "2021-05-01");
+    assert_eq!(
+        serde_json::to_value(date.start).unwrap(),
+        json!("2021-05-01")
+    );
+}
+
+#[test]
+fn time_round_trips() {
+    let time: Time = serde_json::from_value(json!("2021-05-13T09:30:00.000+02:00")).unwrap();
+    assert!(!time.is_date());
+    assert_eq!(time.to_utc().to_rfc3339(), "2021-05-13T07:30:00+00:00");
+    assert!(serde_json::from_value::<Time>(json!("yesterday")).is_err());
+}
+
+#[test]
+fn empty_values_are_written_as_null() {
+    let value = PagePropertyValue::Select { select: None };
+    assert_eq!(
+        serde_json::to_value(&value).unwrap(),
+        json!({ "select": null })
+    );
+
+    let value: PagePropertyValue = serde_json::from_value(json!({ "date": null })).unwrap();
+    assert!(matches!(value, PagePropertyValue::Date { date: None }));
+}
+
+#[test]
+fn numbered_list_item() {
+    let block: BlockObject = serde_json::from_value(block(
+        "numbered_list_item",
+        json!({ "text": [{
+            "type": "text",
+            "plain_text": "first",
+            "href": null,
+            "annotations": {
+                "bold": false,
+                "italic": false,
+                "strikethrough": false,
+                "underline": false,
+                "code": false,
+                "color": "default",
+            },
+            "text": { "content": "first", "link": null },
+        }] }),
+    ))
+    .unwrap();
+    assert!(matches!(block, BlockObject::NumberedListItem { .. }));
+    assert_eq!(block.text().unwrap()[0].plain_text(), "first");
+    assert!(block.to_input().is_some());
+}
+
+#[test]
+fn malformed_known_type_is_an_error() {
+    let error =
+        serde_json::from_value::<BlockObject>(block("paragraph", json!({ "text": "not a list" })))
+            .unwrap_err();
+    assert!(error.to_string().contains("invalid type"), "{}", error);
+}
+
+#[cfg(not(feature = "strict"))]
+#[test]
+fn unknown_type_is_kept() {
+    let raw = block("synced_block", json!({ "synced_from": null }));
+    let block: BlockObject = serde_json::from_value(raw.clone()).unwrap();
+    match &block {
+        BlockObject::Unknown { type_name, .. } => assert_eq!(type_name, "synced_block"),
+        other => panic!("expected an unknown block, got {:?}", other),
+    }
+    assert_eq!(block.id(), Some("b1"));
+    assert_eq!(serde_json::to_value(&block).unwrap(), raw);
+}
+
+#[cfg(not(feature = "strict"))]
+#[test]
+fn unknown_block_fields_are_kept() {
+    let mut raw = block("to_do", json!({ "text": [], "checked": true }));
+    raw["archived"] = json!(false);
+    let block: BlockObject = serde_json::from_value(raw).unwrap();
+    assert!(matches!(block, BlockObject::ToDo { .. }));
+    assert_eq!(serde_json::to_value(&block).unwrap()["archived"], false);
+}
+
+#[cfg(feature = "strict")]
+#[test]
+fn unknown_block_fields_are_an_error() {
+    let mut raw = block("to_do", json!({ "text": [], "checked": true }));
+    raw["archived"] = json!(false);
+    let error = serde_json::from_value::<BlockObject>(raw).unwrap_err();
+    assert_eq!(error.to_string(), "unknown field `archived`");
+}
+
+#[cfg(feature = "strict")]
+#[test]
+fn unknown_type_is_an_error() {
+    let error =
+        serde_json::from_value::<BlockObject>(block("synced_block", json!({}))).unwrap_err();
+    assert_eq!(error.to_string(), "unknown variant `synced_block`");
+}
+
+#[test]
+fn user_without_type_is_partial() {
+    let user: UserObject = serde_json::from_value(json!({ "object": "user", "id": "u1" })).unwrap();
+    assert!(matches!(&user, UserObject::Partial { id } if id == "u1"));
+    assert_eq!(serde_json::to_value(&user).unwrap(), json!({ "id": "u1" }));
+
+    // A person missing its details is an error rather than a partial user
+    assert!(serde_json::from_value::<UserObject>(json!({
+        "object": "user",
+        "id": "u2",
+        "type": "person",
+    }))
+    .is_err());
+}
+
+#[cfg(not(feature = "strict"))]
+#[test]
+fn user_of_unknown_type_is_kept() {
+    let raw = json!({ "object": "user", "id": "u3", "type": "group", "group": {} });
+    let user: UserObject = serde_json::from_value(raw.clone()).unwrap();
+    assert!(matches!(&user, UserObject::Unknown { type_name, .. } if type_name == "group"));
+    assert_eq!(user.id(), Some("u3"));
+    assert_eq!(serde_json::to_value(&user).unwrap(), raw);
+}
+
+#[test]
+fn date_mention_without_time() {
+    let mention: Mention = serde_json::from_value(json!({
+        "type": "date",
+        "date": { "start": "2021-05-01", "end": null },
+    }))
+    .unwrap();
+    match mention {
+        Mention::Date { date } => assert!(date.start.is_date()),
+        other => panic!("expected a date mention, got {:?}", other),
+    }
+}
+
+#[test]
+fn mentions_in_nested_blocks() {
+    let mention = |id: &str| {
+        RichTextObject::mention(Mention::Page {
+            page: serde_json::from_value(json!({ "id": id })).unwrap(),
+        })
+    };
+    let paragraph = |text: Vec<RichTextObject>| -> BlockObject {
+        serde_json::from_value(block(
+            "paragraph",
+            json!({ "text": serde_json::to_value(text).unwrap() }),
+        ))
+        .unwrap()
+    };
+    let tree = vec![BlockTree {
+        block: paragraph(vec![mention("p1")]),
+        children: vec![BlockTree {
+            block: paragraph(vec![RichTextObject::text("plain"), mention("p2")]),
+            children: Vec::new(),
+        }],
+    }];
+    let ids: Vec<_> = block::mentions(&tree)
+        .into_iter()
+        .map(|mention| match mention {
+            Mention::Page { page } => page.id.as_str(),
+            _ => "",
+        })
+        .collect();
+    assert_eq!(ids, ["p1", "p2"]);
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use common::{paragraph, row};
+use inkling::{
+    block::{BlockObject, BlockTree},
+    diff::{diff_blocks, diff_pages, same_value, BlockOp},
+    page::PageProperty,
+    rich_text::RichTextObject,
+};
+use serde_json::json;
+
+/// A block of a type this crate doesn't know, which comes without an id
+fn without_id(text: &str, children: Vec<BlockTree>) -> BlockTree {
+    BlockTree {
+        block: BlockObject::Unknown {
+            type_name: "callout".to_string(),
+            raw: json!({ "type": "callout", "callout": { "text": text } }),
+        },
+        children,
+    }
+}
+
+fn leaf(id: &str) -> BlockTree {
+    BlockTree {
+        block: paragraph(id, id),
+        children: Vec::new(),
+    }
+}
+
+fn property(value: serde_json::Value) -> PageProperty {
+    serde_json::from_value(value).unwrap()
+}
+
+fn date(start: &str) -> PageProperty {
+    serde_json::from_value(json!({ "id": "d", "type": "date", "date": { "start": start } }))
+        .unwrap()
+}
+
+#[test]
+fn moves_between_parents_without_ids() {
+    let old = vec![
+        without_id("x", vec![leaf("a")]),
+        without_id("y", vec![leaf("b")]),
+    ];
+    let new = vec![
+        without_id("x", vec![leaf("b")]),
+        without_id("y", vec![leaf("a")]),
+    ];
+
+    let moved = diff_blocks(&old, &new)
+        .ops
+        .into_iter()
+        .filter_map(|op| match op {
+            BlockOp::Move { id, .. } => id,
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(moved, ["b", "a"]);
+}
+
+#[test]
+fn unchanged_blocks_under_parents_without_ids() {
+    let tree = vec![
+        without_id("x", vec![leaf("a"), leaf("b")]),
+        without_id("y", vec![leaf("c")]),
+    ];
+    assert!(diff_blocks(&tree, &tree).is_empty());
+}
+
+#[test]
+fn compares_dates_and_times() {
+    assert!(same_value(&date("2021-05-13"), &date("2021-05-13")));
+    assert!(same_value(
+        &date("2021-05-13T09:00:00.000Z"),
+        &date("2021-05-13T11:00:00.000+02:00")
+    ));
+    assert!(!same_value(&date("2021-05-13"), &date("2021-05-14")));
+    assert!(!same_value(
+        &date("2021-05-13"),
+        &date("2021-05-13T00:00:00.000Z")
+    ));
+}
+
+#[test]
+fn text_split_differently_is_the_same() {
+    let split = json!([RichTextObject::text("Buy "), RichTextObject::text("milk")]);
+    let whole = json!([RichTextObject::text("Buy milk")]);
+    assert!(same_value(
+        &property(json!({ "id": "title", "type": "title", "title": split })),
+        &property(json!({ "id": "title", "type": "title", "title": whole })),
+    ));
+
+    let mut bold = RichTextObject::text("milk");
+    if let RichTextObject::Text { annotations, .. } = &mut bold {
+        annotations.bold = true;
+    }
+    let styled = json!([RichTextObject::text("Buy "), bold]);
+    assert!(!same_value(
+        &property(json!({ "id": "t", "type": "rich_text", "rich_text": styled })),
+        &property(json!({ "id": "t", "type": "rich_text", "rich_text": whole })),
+    ));
+
+    let mut block = paragraph("a", "");
+    let text = block.text_mut().unwrap();
+    text.clear();
+    text.push(RichTextObject::text("Buy "));
+    text.push(RichTextObject::text("milk"));
+    let old = vec![BlockTree {
+        block,
+        children: Vec::new(),
+    }];
+    let new = vec![BlockTree {
+        block: paragraph("a", "Buy milk"),
+        children: Vec::new(),
+    }];
+    assert!(diff_blocks(&old, &new).is_empty());
+}
+
+#[test]
+fn ignores_the_order_of_options_and_relations() {
+    let tags = |names: &[&str]| {
+        let options: Vec<_> = names.iter().map(|name| json!({ "name": name })).collect();
+        property(json!({ "id": "m", "type": "multi_select", "multi_select": options }))
+    };
+    assert!(same_value(&tags(&["a", "b"]), &tags(&["b", "a"])));
+    assert!(!same_value(&tags(&["a", "b"]), &tags(&["a"])));
+
+    let related = |ids: &[&str]| {
+        let pages: Vec<_> = ids.iter().map(|id| json!({ "id": id })).collect();
+        property(json!({ "id": "r", "type": "relation", "relation": pages }))
+    };
+    assert!(same_value(&related(&["p1", "p2"]), &related(&["p2", "p1"])));
+    assert!(!same_value(&related(&["p1"]), &related(&["p2"])));
+}
+
+#[test]
+fn updates_inserts_and_deletes_blocks() {
+    let old = vec![leaf("a"), leaf("b"), leaf("c")];
+    let new = vec![
+        leaf("a"),
+        BlockTree {
+            block: paragraph("b", "bee"),
+            children: Vec::new(),
+        },
+        leaf("d"),
+    ];
+    let diff = diff_blocks(&old, &new);
+    let ops = diff
+        .ops
+        .iter()
+        .map(|op| match op {
+            BlockOp::Insert { tree, .. } => ("insert", tree.block.id().unwrap()),
+            BlockOp::Delete { tree, .. } => ("delete", tree.block.id().unwrap()),
+            BlockOp::Update { id, .. } => ("update", id.as_deref().unwrap()),
+            BlockOp::Move { id, .. } => ("move", id.as_deref().unwrap()),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ops, [("delete", "c"), ("update", "b"), ("insert", "d")]);
+
+    assert_eq!(
+        diff.to_string(),
+        "- [2] paragraph \"c\"\n~ paragraph \"b\" -> paragraph \"bee\"\n+ [2] paragraph \"d\"\n"
+    );
+    let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+    assert_eq!(json["ops"][0]["op"], "delete");
+    assert_eq!(
+        json["ops"][0]["position"],
+        json!({ "parent": null, "index": 2 })
+    );
+    assert_eq!(json["ops"][1]["id"], "b");
+    assert_eq!(json["ops"][2]["tree"]["block"]["id"], "d");
+}
+
+#[test]
+fn shows_page_changes() {
+    let done = json!({ "Done": { "id": "c", "type": "checkbox", "checkbox": true } });
+    let url = json!({ "Link": { "id": "u", "type": "url", "url": "https://example.com" } });
+    let old = row("r1", "d1", "Milk", done);
+    let mut new = row("r1", "d1", "Oat milk", url);
+    new.archived = true;
+    let diff = diff_pages(&old, &new);
+    assert_eq!(
+        diff.to_string(),
+        "archived\n- Done: \"true\"\n+ Link: \"https://example.com\"\n~ Name: \"Milk\" -> \"Oat milk\"\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+    assert_eq!(json["archived"], json!([false, true]));
+    let names: Vec<_> = diff.properties.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["Done", "Link", "Name"]);
+    assert_eq!(json["properties"][0]["new"], json!(null));
+    assert_eq!(json["properties"][1]["new"]["url"], "https://example.com");
+
+    assert!(diff_pages(&old, &old).is_empty());
+}
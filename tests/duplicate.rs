@@ -0,0 +1,161 @@
+#![cfg(feature = "mock")]
+
+use inkling::{
+    block::BlockTree,
+    duplicate::DuplicateOptions,
+    mock::{Fixtures, MockServer},
+    page::PageProperty,
+    restore::SkippedBlock,
+};
+use serde_json::json;
+
+mod common;
+
+fn unsupported(id: &str) -> inkling::block::BlockObject {
+    serde_json::from_value(json!({
+        "id": id,
+        "type": "unsupported",
+        "created_time": common::TIME,
+        "last_edited_time": common::TIME,
+        "has_children": false,
+    }))
+    .unwrap()
+}
+
+fn leaf(block: inkling::block::BlockObject) -> BlockTree {
+    BlockTree {
+        block,
+        children: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn copies_select_values_by_name_and_reports_skipped_blocks() {
+    let server = MockServer::start().await.unwrap();
+    let mut fixtures = Fixtures::default();
+    fixtures.pages.push(common::page("top", "root", "Top"));
+    fixtures
+        .pages
+        .push(common::page("dest", "root", "Destination"));
+    fixtures.databases.push(common::database(
+        "tasks",
+        "top",
+        "Tasks",
+        json!({ "Status": { "id": "st", "type": "select", "select": { "options": [
+            { "id": "opt-old", "name": "Done", "color": "green" },
+        ] } } }),
+    ));
+    fixtures.pages.push(common::row(
+        "task",
+        "tasks",
+        "Write tests",
+        json!({ "Status": { "id": "st", "type": "select", "select": {
+            "id": "opt-old", "name": "Done", "color": "green",
+        } } }),
+    ));
+    fixtures.blocks.insert(
+        "top".to_string(),
+        vec![
+            leaf(common::paragraph("intro", "intro")),
+            leaf(unsupported("embed")),
+            leaf(
+                serde_json::from_value(common::block_json(
+                    "tasks",
+                    "child_database",
+                    json!({ "title": "Tasks" }),
+                ))
+                .unwrap(),
+            ),
+        ],
+    );
+    server.seed(&fixtures);
+    let client = server.client();
+
+    let copy = client
+        .duplicate_page("top", "dest", DuplicateOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        copy.skipped.get("top").map(Vec::as_slice),
+        Some(
+            &[SkippedBlock {
+                id: Some("embed".to_string()),
+                kind: "unsupported".to_string(),
+            }][..]
+        )
+    );
+    assert_eq!(copy.skipped.len(), 1);
+
+    for request in server.requests() {
+        let body = request
+            .body
+            .map(|body| body.to_string())
+            .unwrap_or_default();
+        assert!(
+            !body.contains("opt-old"),
+            "{} {} sent an option id from the source database: {}",
+            request.method,
+            request.path,
+            body
+        );
+    }
+    let task = server.page(&copy.ids["task"]).unwrap();
+    match &task.properties["Status"] {
+        PageProperty::Select {
+            select: Some(option),
+            ..
+        } => {
+            assert_eq!(option.name, "Done");
+            assert_ne!(option.id.as_deref(), Some("opt-old"));
+        }
+        other => panic!("expected a select, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn reads_each_child_page_once() {
+    let server = MockServer::start().await.unwrap();
+    let mut fixtures = Fixtures::default();
+    fixtures.pages.push(common::page("top", "root", "Top"));
+    fixtures.pages.push(common::page("child", "top", "Child"));
+    fixtures
+        .pages
+        .push(common::page("dest", "root", "Destination"));
+    let toggle = serde_json::from_value(common::block_json(
+        "toggle",
+        "toggle",
+        json!({ "text": [] }),
+    ))
+    .unwrap();
+    fixtures.blocks.insert(
+        "top".to_string(),
+        vec![BlockTree {
+            block: toggle,
+            children: vec![leaf(common::child_page("child", "Child"))],
+        }],
+    );
+    fixtures.blocks.insert(
+        "child".to_string(),
+        vec![leaf(common::paragraph("inner", "inside the child"))],
+    );
+    server.seed(&fixtures);
+
+    let copy = server
+        .client()
+        .duplicate_page("top", "dest", DuplicateOptions::default())
+        .await
+        .unwrap();
+    assert!(copy.ids.contains_key("child"));
+
+    let reads = |path: &str| {
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.method == "GET" && request.path == path)
+            .count()
+    };
+    assert_eq!(reads("pages/child"), 1);
+    assert_eq!(reads("blocks/child/children"), 1);
+    assert_eq!(reads("blocks/toggle/children"), 1);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{database, row};
+use inkling::export::{ExportOptions, Exporter};
+use serde_json::{json, Value};
+
+fn fixture() -> (
+    inkling::database::DatabaseObject,
+    Vec<inkling::page::PageObject>,
+) {
+    let options = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| json!({ "id": name, "name": name, "color": "default" }))
+            .collect::<Vec<_>>()
+    };
+    let properties = json!({
+        "Status": { "id": "st", "type": "select", "select": { "options": options(&["Done"]) } },
+        "Tags": {
+            "id": "tg",
+            "type": "multi_select",
+            "multi_select": { "options": options(&["red", "Smith, J", "say \"hi\""]) },
+        },
+        "When": { "id": "wh", "type": "date", "date": {} },
+    });
+    let database = database("d1", "root", "Tasks", properties);
+    let rows = vec![
+        row(
+            "r1",
+            "d1",
+            "Full",
+            json!({
+                "Status": { "id": "st", "type": "select", "select": { "id": "Done", "name": "Done", "color": "default" } },
+                "Tags": { "id": "tg", "type": "multi_select", "multi_select": options(&["red", "Smith, J", "say \"hi\""]) },
+                "When": { "id": "wh", "type": "date", "date": { "start": "2021-05-13", "end": "2021-05-15" } },
+            }),
+        ),
+        row(
+            "r2",
+            "d1",
+            "Empty",
+            json!({
+                "Status": { "id": "st", "type": "select", "select": null },
+                "Tags": { "id": "tg", "type": "multi_select", "multi_select": [] },
+                "When": { "id": "wh", "type": "date", "date": null },
+            }),
+        ),
+        row(
+            "r3",
+            "d1",
+            "Timed",
+            json!({
+                "When": { "id": "wh", "type": "date", "date": { "start": "2021-05-13T09:30:00.000+00:00", "end": null } },
+            }),
+        ),
+    ];
+    (database, rows)
+}
+
+fn csv(options: ExportOptions) -> String {
+    let (database, rows) = fixture();
+    let mut out = Vec::new();
+    Exporter::new(&database, options)
+        .write_csv(&rows, &mut out)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn writes_dates_as_iso_ranges_and_empty_values_as_empty_cells() {
+    let out = csv(ExportOptions::default());
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines[0], "Name,Status,Tags,When");
+    assert_eq!(lines[2], "Empty,,,");
+    assert!(lines[1].ends_with(",2021-05-13/2021-05-15"), "{}", lines[1]);
+    assert!(
+        lines[3].starts_with("Timed,,,2021-05-13T09:30:00"),
+        "{}",
+        lines[3]
+    );
+    assert!(!lines[3].contains('/'), "{}", lines[3]);
+}
+
+#[test]
+fn quotes_list_values_that_contain_the_delimiter() {
+    let out = csv(ExportOptions::default());
+    let mut reader = csv::Reader::from_reader(out.as_bytes());
+    let first = reader.records().next().unwrap().unwrap();
+    assert_eq!(&first[2], r#"red, "Smith, J", "say ""hi""""#);
+
+    let out = csv(ExportOptions {
+        delimiter: Some(";".to_string()),
+        ..ExportOptions::default()
+    });
+    let mut reader = csv::Reader::from_reader(out.as_bytes());
+    let first = reader.records().next().unwrap().unwrap();
+    assert_eq!(&first[2], r#"red; Smith, J; "say ""hi""""#);
+}
+
+#[test]
+fn writes_empty_values_as_null_in_json_lines() {
+    let (database, rows) = fixture();
+    let mut out = Vec::new();
+    Exporter::new(&database, ExportOptions::default())
+        .write_jsonl(&rows, &mut out)
+        .unwrap();
+    let lines: Vec<Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines[0]["Tags"], json!(["red", "Smith, J", "say \"hi\""]));
+    assert_eq!(lines[0]["When"], "2021-05-13/2021-05-15");
+    assert_eq!(
+        lines[1],
+        json!({ "Name": "Empty", "Status": null, "Tags": [], "When": null })
+    );
+}
+
+#[test]
+fn writes_null_numbers_urls_emails_and_phones_as_empty() {
+    let properties = json!({
+        "Count": { "id": "ct", "type": "number", "number": { "format": "number" } },
+        "Link": { "id": "ln", "type": "url", "url": {} },
+        "Mail": { "id": "ml", "type": "email", "email": {} },
+        "Phone": { "id": "ph", "type": "phone_number", "phone_number": {} },
+    });
+    let database = database("d1", "root", "Contacts", properties);
+    let values = |count: Value, text: Value| {
+        json!({
+            "Count": { "id": "ct", "type": "number", "number": count },
+            "Link": { "id": "ln", "type": "url", "url": text },
+            "Mail": { "id": "ml", "type": "email", "email": text },
+            "Phone": { "id": "ph", "type": "phone_number", "phone_number": text },
+        })
+    };
+    let rows = vec![
+        row("r1", "d1", "Full", values(json!(2.5), json!("x"))),
+        row("r2", "d1", "Empty", values(Value::Null, Value::Null)),
+    ];
+    let exporter = Exporter::new(&database, ExportOptions::default());
+
+    let mut out = Vec::new();
+    exporter.write_csv(&rows, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(
+        lines,
+        ["Name,Count,Link,Mail,Phone", "Full,2.5,x,x,x", "Empty,,,,"]
+    );
+
+    let mut out = Vec::new();
+    exporter.write_jsonl(&rows[1..], &mut out).unwrap();
+    let line: Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(
+        line,
+        json!({ "Name": "Empty", "Count": null, "Link": null, "Mail": null, "Phone": null })
+    );
+}
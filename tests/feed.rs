@@ -0,0 +1,261 @@
+mod common;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as Minutes, TimeZone, Utc};
+use inkling::{
+    feed::{
+        ChangeFeed, ChangeKind, Clock, FeedOptions, FileStore, MemoryStore, MockClock, PageSource,
+    },
+    page::PageObject,
+};
+use std::{
+    cell::{Cell, RefCell},
+    error::Error,
+    time::Duration,
+};
+
+fn at(minute: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 5, 13, 9, 0, 0).unwrap() + Minutes::minutes(minute)
+}
+
+fn page(id: &str, title: &str, minute: i64) -> PageObject {
+    let mut page = common::page(id, "root", title);
+    page.last_edited_time = at(minute).into();
+    page
+}
+
+/// Pages newest first, two at a time
+#[derive(Default)]
+struct FakeSource {
+    pages: RefCell<Vec<PageObject>>,
+    /// How many pages have been handed out
+    fetched: Cell<usize>,
+}
+
+impl FakeSource {
+    fn put(&self, page: PageObject) {
+        let mut pages = self.pages.borrow_mut();
+        pages.retain(|p| p.id != page.id);
+        pages.push(page);
+        pages.sort_by_key(|p| std::cmp::Reverse(p.last_edited_time.to_utc()));
+    }
+
+    fn remove(&self, id: &str) {
+        self.pages.borrow_mut().retain(|p| p.id != id);
+    }
+}
+
+#[async_trait(?Send)]
+impl PageSource for FakeSource {
+    async fn edited_pages(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<(Vec<PageObject>, Option<String>), Box<dyn Error>> {
+        let pages = self.pages.borrow();
+        let start = cursor.map_or(0, |cursor| cursor.parse().unwrap());
+        let end = (start + 2).min(pages.len());
+        self.fetched.set(self.fetched.get() + end - start);
+        let next = Some(end.to_string()).filter(|_| end < pages.len());
+        Ok((pages[start..end].to_vec(), next))
+    }
+}
+
+fn options() -> FeedOptions {
+    FeedOptions {
+        interval: Duration::from_secs(60),
+        overlap: Duration::from_secs(120),
+        emit_initial: false,
+        full_scan_every: None,
+    }
+}
+
+fn ids(events: &[inkling::feed::ChangeEvent]) -> Vec<(&str, ChangeKind)> {
+    events
+        .iter()
+        .map(|event| (event.new.id.as_str(), event.kind))
+        .collect()
+}
+
+#[tokio::test]
+async fn reports_every_page_on_the_first_poll_only_when_asked() {
+    let source = FakeSource::default();
+    source.put(page("a", "A", 0));
+    source.put(page("b", "B", 1));
+
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(5)),
+        options(),
+    );
+    assert!(feed.poll().await.unwrap().is_empty());
+
+    let mut options = options();
+    options.emit_initial = true;
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(5)),
+        options,
+    );
+    let events = feed.poll().await.unwrap();
+    assert_eq!(
+        ids(&events),
+        [("a", ChangeKind::Created), ("b", ChangeKind::Created)]
+    );
+    assert!(feed.poll().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn picks_up_edits_that_share_the_newest_timestamp() {
+    let source = FakeSource::default();
+    source.put(page("a", "A", 10));
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(10)),
+        options(),
+    );
+    feed.poll().await.unwrap();
+
+    // Edited in the same minute as the last poll saw
+    source.put(page("b", "B", 10));
+    source.put(page("a", "A again", 10));
+    let events = feed.poll().await.unwrap();
+    let mut found = ids(&events);
+    found.sort_by_key(|(id, _)| *id);
+    assert_eq!(
+        found,
+        [("a", ChangeKind::Updated), ("b", ChangeKind::Created)]
+    );
+    assert_eq!(
+        events
+            .iter()
+            .find(|e| e.new.id == "a")
+            .unwrap()
+            .changes
+            .len(),
+        1
+    );
+
+    // Nothing is reported twice
+    assert!(feed.poll().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reports_edits_to_the_content_alone() {
+    let source = FakeSource::default();
+    source.put(page("a", "A", 10));
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(10)),
+        options(),
+    );
+    feed.poll().await.unwrap();
+
+    // Only the blocks changed so the properties are the same
+    source.put(page("a", "A", 11));
+    let events = feed.poll().await.unwrap();
+    assert_eq!(ids(&events), [("a", ChangeKind::Updated)]);
+    assert!(events[0].changes.is_empty());
+    assert!(feed.poll().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn fetches_back_to_the_overlap() {
+    let source = FakeSource::default();
+    for (index, id) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+        source.put(page(id, id, index as i64 * 10));
+    }
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(40)),
+        options(),
+    );
+    feed.poll().await.unwrap();
+    assert_eq!(source.fetched.get(), 5);
+
+    // An edit that only shows up after a later one, but still within the overlap
+    source.put(page("late", "Late", 39));
+    source.fetched.set(0);
+    let events = feed.poll().await.unwrap();
+    assert_eq!(ids(&events), [("late", ChangeKind::Created)]);
+    // Stops at the first page before the overlap instead of listing everything
+    assert_eq!(source.fetched.get(), 4);
+
+    // Too late to be seen
+    source.put(page("stale", "Stale", 20));
+    assert!(feed.poll().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn carries_on_from_a_file_after_a_restart() {
+    let path = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("feed.json");
+    let _ = std::fs::remove_file(&path);
+    let source = FakeSource::default();
+    source.put(page("a", "A", 0));
+    {
+        let mut feed = ChangeFeed::new(
+            &source,
+            FileStore(path.clone()),
+            MockClock::new(at(0)),
+            options(),
+        );
+        feed.poll().await.unwrap();
+    }
+
+    source.put(page("a", "Renamed", 5));
+    let mut feed = ChangeFeed::new(
+        &source,
+        FileStore(path.clone()),
+        MockClock::new(at(5)),
+        options(),
+    );
+    let events = feed.poll().await.unwrap();
+    assert_eq!(ids(&events), [("a", ChangeKind::Updated)]);
+    let old = events[0].old.as_ref().unwrap();
+    assert_eq!(old.last_edited_time.to_utc(), at(0));
+}
+
+#[tokio::test]
+async fn waits_for_the_interval_between_polls() {
+    let source = FakeSource::default();
+    let clock = MockClock::new(at(0));
+    let mut feed = ChangeFeed::new(&source, MemoryStore::default(), &clock, options());
+    feed.poll().await.unwrap();
+
+    source.put(page("a", "A", 0));
+    let event = feed.next().await.unwrap();
+    assert_eq!(event.new.id, "a");
+    assert_eq!(clock.now(), at(1));
+}
+
+#[tokio::test]
+async fn reports_pages_that_are_gone_on_a_full_scan() {
+    let source = FakeSource::default();
+    source.put(page("a", "A", 0));
+    source.put(page("b", "B", 1));
+    let mut options = options();
+    options.full_scan_every = Some(2);
+    let mut feed = ChangeFeed::new(
+        &source,
+        MemoryStore::default(),
+        MockClock::new(at(1)),
+        options,
+    );
+    feed.poll().await.unwrap();
+
+    // Only every second poll looks for pages that are gone
+    source.remove("a");
+    let events = feed.poll().await.unwrap();
+    assert_eq!(ids(&events), [("a", ChangeKind::Archived)]);
+    assert!(events[0].new.archived);
+    source.remove("b");
+    assert!(feed.poll().await.unwrap().is_empty());
+    assert_eq!(
+        ids(&feed.poll().await.unwrap()),
+        [("b", ChangeKind::Archived)]
+    );
+}
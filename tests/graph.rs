@@ -0,0 +1,156 @@
+mod common;
+
+use common::{block_json, child_page, database, page, paragraph, row};
+use inkling::{
+    block::BlockTree,
+    graph::{EdgeKind, PageGraph},
+    page::Parent,
+    rich_text::Annotations,
+};
+use serde_json::json;
+
+fn tree(block: serde_json::Value) -> BlockTree {
+    BlockTree {
+        block: serde_json::from_value(block).unwrap(),
+        children: Vec::new(),
+    }
+}
+
+/// A home page with a sub-page, a mention of another top level page and a link to a database
+/// whose row relates to that page as well
+fn graph() -> PageGraph {
+    let mut graph = PageGraph::new();
+    graph.add_database(&database("d1", "a", "Tasks", json!({})));
+
+    let mut home = page("a", "", "Home \"sweet\"\\home\nagain");
+    home.parent = Parent::Workspace;
+    let mention = json!([{
+        "type": "mention",
+        "plain_text": "C",
+        "annotations": Annotations::default(),
+        "mention": { "type": "page", "page": { "id": "c" } },
+    }]);
+    graph.add_page(
+        &home,
+        &[
+            BlockTree {
+                block: child_page("b", "Sub page"),
+                children: vec![BlockTree {
+                    block: paragraph("b1", "inside the sub page"),
+                    children: Vec::new(),
+                }],
+            },
+            BlockTree {
+                block: paragraph("a1", "see"),
+                children: vec![tree(block_json(
+                    "a2",
+                    "paragraph",
+                    json!({ "text": mention }),
+                ))],
+            },
+            tree(block_json(
+                "a3",
+                "link_to_page",
+                json!({ "type": "database_id", "database_id": "d1" }),
+            )),
+        ],
+    );
+    graph.add_page(&page("b", "a", "Sub page"), &[]);
+
+    for id in ["c", "e"] {
+        let mut top = page(id, "", id);
+        top.parent = Parent::Workspace;
+        graph.add_page(&top, &[]);
+    }
+    let relation =
+        json!({ "Related": { "id": "rl", "type": "relation", "relation": [{ "id": "c" }] } });
+    graph.add_page(&row("r1", "d1", "Row", relation), &[]);
+    graph
+}
+
+fn edges(edges: Vec<&inkling::graph::Edge>) -> Vec<(&str, &str, EdgeKind)> {
+    edges
+        .into_iter()
+        .map(|e| (e.from.as_str(), e.to.as_str(), e.kind))
+        .collect()
+}
+
+#[test]
+fn finds_links_and_backlinks() {
+    let graph = graph();
+    assert_eq!(
+        edges(graph.links("a")),
+        [
+            ("a", "b", EdgeKind::ChildPage),
+            ("a", "c", EdgeKind::Mention),
+            ("a", "d1", EdgeKind::LinkToPage),
+        ]
+    );
+    assert_eq!(
+        edges(graph.backlinks("c")),
+        [
+            ("a", "c", EdgeKind::Mention),
+            ("r1", "c", EdgeKind::Relation)
+        ]
+    );
+    assert_eq!(
+        edges(graph.links("b")),
+        [("b", "a", EdgeKind::Parent)],
+        "blocks under a child page belong to that page"
+    );
+}
+
+#[test]
+fn finds_orphans() {
+    assert_eq!(graph().orphans(), ["e"]);
+}
+
+#[test]
+fn finds_the_shortest_path() {
+    let graph = graph();
+    assert_eq!(graph.shortest_path("b", "c").unwrap(), ["b", "a", "c"]);
+    assert_eq!(graph.shortest_path("b", "d1").unwrap(), ["b", "a", "d1"]);
+    assert_eq!(graph.shortest_path("a", "a").unwrap(), ["a"]);
+    assert!(graph.shortest_path("c", "a").is_none());
+}
+
+#[test]
+fn writes_dot_with_escaped_labels() {
+    let dot = graph().to_dot();
+    assert!(dot.starts_with("digraph notion {\n"), "{}", dot);
+    assert!(
+        dot.contains("  \"a\" [label=\"Home \\\"sweet\\\"\\\\home\\nagain\", shape=box];\n"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("  \"d1\" [label=\"Tasks\", shape=cylinder];\n"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("  \"r1\" -> \"c\" [label=\"relation\"];\n"),
+        "{}",
+        dot
+    );
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn writes_json_that_reads_back() {
+    let graph = graph();
+    let json = graph.to_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        value["nodes"]["d1"],
+        json!({ "kind": "database", "title": "Tasks" })
+    );
+    assert_eq!(
+        value["edges"][0],
+        json!({ "from": "a", "to": "b", "kind": "child_page" })
+    );
+
+    let read: PageGraph = serde_json::from_str(&json).unwrap();
+    assert_eq!(read.nodes.len(), graph.nodes.len());
+    assert_eq!(read.edges, graph.edges);
+}
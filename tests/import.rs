@@ -0,0 +1,270 @@
+#![cfg(feature = "mock")]
+mod common;
+
+use common::{database, page, row};
+use inkling::{
+    database::{DatabaseProperty, NumberFormat},
+    export::{ExportOptions, Exporter},
+    import::{import_csv, infer_schema, ImportOptions},
+    mock::MockServer,
+    page::{PageObject, PageProperty},
+    rich_text::RichTextObject,
+};
+use serde_json::json;
+
+const DATABASE: &str = "d1";
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await.unwrap();
+    server.seed_page(&page("root", "root", "Root"));
+    let options = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| json!({ "id": name, "name": name, "color": "default" }))
+            .collect::<Vec<_>>()
+    };
+    let properties = json!({
+        "Count": { "id": "cnt", "type": "number", "number": { "format": "number" } },
+        "Share": { "id": "shr", "type": "number", "number": { "format": "percent" } },
+        "When": { "id": "wh", "type": "date", "date": {} },
+        "Tags": {
+            "id": "tg",
+            "type": "multi_select",
+            "multi_select": { "options": options(&["red", "Smith, J", "say \"hi\""]) },
+        },
+    });
+    server.seed_database(&database(DATABASE, "root", "Things", properties));
+    server
+}
+
+async fn all_rows(server: &MockServer) -> Vec<PageObject> {
+    let mut rows: Vec<PageObject> = server
+        .client()
+        .query_database_all(DATABASE, None, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.into_page().unwrap())
+        .collect();
+    rows.sort_by_key(name);
+    rows
+}
+
+fn name(row: &PageObject) -> String {
+    match &row.properties["Name"] {
+        PageProperty::Title { title, .. } => title.iter().map(RichTextObject::plain_text).collect(),
+        _ => panic!("Name isn't a title"),
+    }
+}
+
+fn number(row: &PageObject, property: &str) -> Option<f64> {
+    match row.properties.get(property) {
+        Some(PageProperty::Number { number, .. }) => *number,
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn rejects_ambiguous_numbers() {
+    let server = server().await;
+    let csv = "Name,Count,Share\n\
+               a,\"1,500\",12.5%\n\
+               b,\"1,5\",\n\
+               c,$-7,\n\
+               d,\"12,34.5\",\n\
+               e,inf,\n\
+               f,\"€ 2,000.25\",50%\n";
+    let report = import_csv(
+        &server.client(),
+        DATABASE,
+        csv.as_bytes(),
+        &ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    let failed: Vec<_> = report.errors.iter().map(|error| error.line).collect();
+    assert_eq!(failed, [3, 5, 6], "{:?}", report.errors);
+    assert!(report.errors[0].message.contains("ambiguous"));
+    assert_eq!(report.created, 3);
+
+    let rows = all_rows(&server).await;
+    let counts: Vec<_> = rows
+        .iter()
+        .map(|row| (name(row), number(row, "Count")))
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            ("a".to_string(), Some(1500.0)),
+            ("c".to_string(), Some(-7.0)),
+            ("f".to_string(), Some(2000.25)),
+        ]
+    );
+    assert_eq!(number(&rows[0], "Share"), Some(0.125));
+    assert_eq!(number(&rows[2], "Share"), Some(0.5));
+}
+
+#[tokio::test]
+async fn reads_dates_without_a_time_and_quoted_list_values() {
+    let server = server().await;
+    let csv = "Name,When,Tags\n\
+               a,2021-05-13,\"red, \"\"Smith, J\"\", \"\"say \"\"\"\"hi\"\"\"\"\"\"\"\n\
+               b,2021-05-13/2021-05-15T10:00:00Z,\n";
+    let report = import_csv(
+        &server.client(),
+        DATABASE,
+        csv.as_bytes(),
+        &ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    // Exporting the rows again gives back the same cells
+    let database = server
+        .client()
+        .get_database(DATABASE)
+        .await
+        .unwrap()
+        .into_database()
+        .unwrap();
+    let exporter = Exporter::new(
+        &database,
+        ExportOptions {
+            columns: vec!["Name".into(), "When".into(), "Tags".into()],
+            ..ExportOptions::default()
+        },
+    );
+    let mut out = Vec::new();
+    exporter
+        .write_csv(all_rows(&server).await, &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), csv);
+}
+
+#[tokio::test]
+async fn reports_rows_with_an_empty_key() {
+    let server = server().await;
+    server.seed_page(&row(
+        "r1",
+        DATABASE,
+        "a",
+        json!({
+            "Count": { "id": "cnt", "type": "number", "number": 1 },
+        }),
+    ));
+    let csv = "Name,Count\na,2\n,3\nb,4\n";
+    let options = ImportOptions {
+        key: Some("Name".to_string()),
+        ..ImportOptions::default()
+    };
+    let report = import_csv(&server.client(), DATABASE, csv.as_bytes(), &options)
+        .await
+        .unwrap();
+    assert_eq!((report.updated, report.created), (1, 1));
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].line, 3);
+    assert!(report.errors[0].message.contains("empty"));
+
+    let rows = all_rows(&server).await;
+    let counts: Vec<_> = rows
+        .iter()
+        .map(|row| (name(row), number(row, "Count")))
+        .collect();
+    assert_eq!(
+        counts,
+        [("a".to_string(), Some(2.0)), ("b".to_string(), Some(4.0))]
+    );
+}
+
+const PEOPLE: &str = "Name,Age,Active,Joined,Email,Site,Team,Notes\n\
+                      Ann,34,yes,2021-01-04,ann@example.com,https://ann.example.com,Red,Likes tea\n\
+                      Bob,27,no,2020-11-30,bob@example.com,http://bob.example.com,Blue,\n\
+                      Cat,41,yes,2019-06-01,cat@example.com,https://cat.example.com,Red,Has a cat\n\
+                      Dan,19,no,2022-02-02,dan@example.com,https://dan.example.com,Blue,Plays chess\n";
+
+fn kinds(properties: &serde_json::Value) -> Vec<(String, String)> {
+    properties
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(name, property)| {
+            let kind = property
+                .as_object()
+                .unwrap()
+                .keys()
+                .find(|k| *k != "name")
+                .unwrap();
+            (name.clone(), kind.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn infers_a_schema() {
+    let schema = infer_schema(PEOPLE.as_bytes(), &ImportOptions::default()).unwrap();
+    let schema = serde_json::to_value(&schema).unwrap();
+    let mut found = kinds(&schema);
+    found.sort();
+    let expected = [
+        ("Active", "checkbox"),
+        ("Age", "number"),
+        ("Email", "email"),
+        ("Joined", "date"),
+        ("Name", "title"),
+        ("Notes", "rich_text"),
+        ("Site", "url"),
+        ("Team", "select"),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|(name, kind)| (name.to_string(), kind.to_string()))
+        .collect();
+    assert_eq!(found, expected);
+    assert_eq!(schema["Age"]["number"]["format"], "number");
+    assert_eq!(
+        schema["Team"]["select"]["options"],
+        json!([{ "name": "Blue" }, { "name": "Red" }])
+    );
+}
+
+#[tokio::test]
+async fn adds_inferred_columns() {
+    let server = server().await;
+    let options = ImportOptions {
+        add_columns: true,
+        ..ImportOptions::default()
+    };
+    let report = import_csv(&server.client(), DATABASE, PEOPLE.as_bytes(), &options)
+        .await
+        .unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert!(report.ignored_columns.is_empty());
+    assert_eq!(report.created, 4);
+
+    let database = server
+        .client()
+        .get_database(DATABASE)
+        .await
+        .unwrap()
+        .into_database()
+        .unwrap();
+    assert!(matches!(
+        &database.properties["Age"],
+        DatabaseProperty::Number { number, .. } if number.format == NumberFormat::Number
+    ));
+    assert!(matches!(
+        database.properties["Active"],
+        DatabaseProperty::Checkbox { .. }
+    ));
+    assert!(matches!(
+        database.properties["Team"],
+        DatabaseProperty::Select { .. }
+    ));
+    let rows = all_rows(&server).await;
+    assert_eq!(number(&rows[0], "Age"), Some(34.0));
+    assert!(matches!(
+        rows[0].properties["Active"],
+        PageProperty::Checkbox { checkbox: true, .. }
+    ));
+}
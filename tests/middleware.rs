@@ -0,0 +1,314 @@
+use async_trait::async_trait;
+use inkling::{
+    middleware::{Headers, Metrics},
+    transport::{Request, Response, Transport},
+    Client,
+};
+use serde_json::json;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+fn list() -> Response {
+    let list = json!({ "object": "list", "results": [], "next_cursor": null, "has_more": false });
+    Response {
+        status: 200,
+        headers: Vec::new(),
+        body: serde_json::to_vec(&list).unwrap(),
+    }
+}
+
+/// Keeps every request and answers with an empty list
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Request>>>);
+
+#[async_trait]
+impl Transport for Recorder {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        self.0.lock().unwrap().push(request);
+        Ok(list())
+    }
+}
+
+#[tokio::test]
+async fn headers_replace_the_ones_the_client_sends() {
+    let recorder = Recorder::default();
+    let client = Client::with_transport("secret", recorder.clone()).with_middleware(
+        Headers::new()
+            .with("notion-version", "2022-06-28")
+            .with("X-Request-Source", "tests"),
+    );
+    client.list_users(None, None).await.unwrap();
+
+    let requests = recorder.0.lock().unwrap();
+    let headers = requests[0]
+        .headers
+        .iter()
+        .filter(|(name, _)| !name.eq_ignore_ascii_case("Authorization"))
+        .filter(|(name, _)| !name.eq_ignore_ascii_case("Content-Type"))
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            ("notion-version", "2022-06-28"),
+            ("X-Request-Source", "tests")
+        ]
+    );
+}
+
+/// A page, then a slow 404 and then no response at all
+struct Flaky(Mutex<u32>);
+
+#[async_trait]
+impl Transport for Flaky {
+    async fn send(&self, _: Request) -> Result<Response, Box<dyn Error>> {
+        let attempt = {
+            let mut attempt = self.0.lock().unwrap();
+            *attempt += 1;
+            *attempt
+        };
+        match attempt {
+            1 => Ok(list()),
+            2 => {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let error = json!({
+                    "object": "error",
+                    "status": 404,
+                    "code": "object_not_found",
+                    "message": "Could not find page",
+                });
+                Ok(Response {
+                    status: 404,
+                    headers: Vec::new(),
+                    body: serde_json::to_vec(&error)?,
+                })
+            }
+            _ => Err("connection reset".into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn metrics_count_errors_and_latency_per_endpoint() {
+    let metrics = Metrics::new();
+    let client =
+        Client::with_transport("secret", Flaky(Mutex::new(0))).with_middleware(metrics.clone());
+    for _ in 0..3 {
+        let _ = client.get_page("p1").await;
+    }
+    client.list_users(None, None).await.unwrap_err();
+
+    let page = metrics.endpoint("get_page").unwrap();
+    assert_eq!((page.requests, page.errors), (3, 2));
+    assert!(page.max_latency >= Duration::from_millis(50));
+    assert!(page.mean_latency() <= page.max_latency);
+    assert_eq!(page.mean_latency(), page.total_latency / 3);
+    assert_eq!(metrics.endpoint("list_users").unwrap().errors, 1);
+
+    metrics.reset();
+    assert!(metrics.snapshot().is_empty());
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_spans {
+    use super::*;
+    use inkling::middleware::{DebugLog, Tracing};
+    use std::{
+        fmt,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        subscriber::Interest,
+        Event, Metadata, Subscriber,
+    };
+    use tracing_core::span::Current;
+
+    /// Keeps the stack of entered spans and the fields recorded on any span
+    #[derive(Clone, Default)]
+    struct Spans {
+        next: Arc<AtomicU64>,
+        spans: Arc<Mutex<Vec<&'static Metadata<'static>>>>,
+        stack: Arc<Mutex<Vec<u64>>>,
+        fields: Arc<Mutex<Vec<(u64, String, String)>>>,
+    }
+
+    impl Spans {
+        fn current(&self) -> Option<&'static str> {
+            let stack = self.stack.lock().unwrap();
+            let id = *stack.last()?;
+            Some(self.spans.lock().unwrap()[id as usize - 1].name())
+        }
+
+        fn field(&self, name: &str) -> Option<String> {
+            self.fields
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(_, field, _)| field == name)
+                .map(|(_, _, value)| value.clone())
+        }
+
+        /// The values of every field called `name`, in the order they were recorded
+        fn fields(&self, name: &str) -> Vec<String> {
+            self.fields
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, field, _)| field == name)
+                .map(|(_, _, value)| value.clone())
+                .collect()
+        }
+    }
+
+    struct Fields<'a>(u64, &'a Mutex<Vec<(u64, String, String)>>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let value = format!("{:?}", value).trim_matches('"').to_string();
+            self.1
+                .lock()
+                .unwrap()
+                .push((self.0, field.name().to_string(), value));
+        }
+    }
+
+    impl Subscriber for Spans {
+        fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+            Interest::always()
+        }
+
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next.fetch_add(1, Ordering::SeqCst) + 1;
+            self.spans.lock().unwrap().push(span.metadata());
+            span.record(&mut Fields(id, &self.fields));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            values.record(&mut Fields(span.into_u64(), &self.fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        /// Event fields are kept under span 0
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut Fields(0, &self.fields));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.stack.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.stack.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.stack.lock().unwrap().last() {
+                Some(&id) => Current::new(
+                    Id::from_u64(id),
+                    self.spans.lock().unwrap()[id as usize - 1],
+                ),
+                None => Current::none(),
+            }
+        }
+    }
+
+    /// Notes the span that was current while sending and answers with an empty list
+    struct Watch(Spans, Arc<Mutex<Option<&'static str>>>);
+
+    #[async_trait]
+    impl Transport for Watch {
+        async fn send(&self, _: Request) -> Result<Response, Box<dyn Error>> {
+            *self.1.lock().unwrap() = self.0.current();
+            Ok(list())
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_the_request_inside_the_span() {
+        let spans = Spans::default();
+        let _default = tracing::subscriber::set_default(spans.clone());
+        let during_send = Arc::new(Mutex::new(None));
+        let client = Client::with_transport("secret", Watch(spans.clone(), during_send.clone()))
+            .with_middleware(Tracing);
+
+        client.list_users(None, None).await.unwrap();
+
+        assert_eq!(*during_send.lock().unwrap(), Some("notion_request"));
+        assert_eq!(spans.field("endpoint").as_deref(), Some("list_users"));
+        assert_eq!(spans.field("status").as_deref(), Some("200"));
+        assert!(spans.field("latency_ms").is_some());
+        assert_eq!(spans.current(), None);
+    }
+
+    /// Answers with the token and OAuth secrets in the body
+    struct Leaky;
+
+    #[async_trait]
+    impl Transport for Leaky {
+        async fn send(&self, _: Request) -> Result<Response, Box<dyn Error>> {
+            let body = json!({
+                "access_token": "secret_oauth",
+                "owner": { "client_secret": "shh" },
+                "echo": "secret_token",
+            });
+            Ok(Response {
+                status: 200,
+                headers: Vec::new(),
+                body: serde_json::to_vec(&body)?,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn debug_log_redacts_secrets() {
+        let spans = Spans::default();
+        let _default = tracing::subscriber::set_default(spans.clone());
+        let client = Client::with_transport("secret_token", Leaky).with_middleware(DebugLog::new());
+        let _ = client
+            .search(Some("secret_token".to_string()), None, None, None, None)
+            .await;
+
+        let headers = spans.field("headers").unwrap();
+        assert!(headers.contains("Authorization: [redacted]"), "{}", headers);
+        let bodies = spans.fields("body");
+        assert_eq!(bodies.len(), 2);
+        assert!(
+            bodies[0].contains(r#""query":"[redacted]""#),
+            "{}",
+            bodies[0]
+        );
+        for field in [
+            r#""access_token":"[redacted]""#,
+            r#""client_secret":"[redacted]""#,
+            r#""echo":"[redacted]""#,
+        ] {
+            assert!(bodies[1].contains(field), "{}", bodies[1]);
+        }
+        let logged = format!("{} {:?}", headers, bodies);
+        for secret in ["secret_token", "secret_oauth", "shh"] {
+            assert!(!logged.contains(secret), "{}", logged);
+        }
+    }
+
+    #[tokio::test]
+    async fn debug_log_cuts_off_long_bodies() {
+        let spans = Spans::default();
+        let _default = tracing::subscriber::set_default(spans.clone());
+        let client = Client::with_transport("secret_token", Recorder::default())
+            .with_middleware(DebugLog { max_body: 8 });
+        client.list_users(None, None).await.unwrap();
+
+        assert_eq!(spans.fields("body"), ["", r#"{"has_mo..."#]);
+    }
+}
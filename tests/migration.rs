@@ -0,0 +1,207 @@
+mod common;
+
+use common::database;
+use inkling::database::{
+    DatabaseColor, DatabaseObject, DatabasePropertyInput, Empty, Number, NumberFormat, SelectInput,
+    SelectOptionsInput,
+};
+use inkling::migration::{DesiredSchema, MigrationStep};
+use serde_json::json;
+
+#[test]
+fn renames_the_title_property() {
+    let live = database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({ "Done": { "id": "dn", "type": "checkbox", "checkbox": {} } }),
+    );
+    let desired = DesiredSchema::new()
+        .property("Task", DatabasePropertyInput::Title(Empty {}))
+        .property("Done", DatabasePropertyInput::Checkbox(Empty {}));
+
+    let plan = desired.diff(&live);
+    assert!(
+        matches!(
+            plan.steps.as_slice(),
+            [MigrationStep::RenameProperty { name, new_name }] if name == "Name" && new_name == "Task"
+        ),
+        "{:?}",
+        plan.steps
+    );
+}
+
+#[test]
+fn keeps_a_title_with_the_same_name() {
+    let live = database("d1", "root", "Tasks", json!({}));
+    let desired = DesiredSchema::new().property("Name", DatabasePropertyInput::Title(Empty {}));
+    assert!(desired.diff(&live).steps.is_empty());
+}
+
+fn options(options: &[(&str, Option<DatabaseColor>)]) -> SelectInput {
+    SelectInput {
+        options: options
+            .iter()
+            .map(|(name, color)| SelectOptionsInput {
+                name: name.to_string(),
+                color: color.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// A database with a few properties of different types
+fn tasks() -> DatabaseObject {
+    database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({
+            "Notes": { "id": "nt", "type": "rich_text", "rich_text": {} },
+            "Done": { "id": "dn", "type": "checkbox", "checkbox": {} },
+            "Due": { "id": "du", "type": "date", "date": {} },
+            "Status": {
+                "id": "st",
+                "type": "select",
+                "select": { "options": [
+                    { "id": "o1", "name": "Todo", "color": "gray" },
+                    { "id": "o2", "name": "Doing", "color": "blue" },
+                ] },
+            },
+        }),
+    )
+}
+
+#[test]
+fn adds_removes_and_retypes_properties() {
+    let desired = DesiredSchema::new()
+        .property("Name", DatabasePropertyInput::Title(Empty {}))
+        .property(
+            "Notes",
+            DatabasePropertyInput::Number(Number {
+                format: NumberFormat::Number,
+                extra: Default::default(),
+            }),
+        )
+        .property(
+            "Status",
+            DatabasePropertyInput::Select(options(&[("Todo", None), ("Doing", None)])),
+        )
+        .property("Link", DatabasePropertyInput::Url(Empty {}));
+
+    let plan = desired.diff(&tasks());
+    let steps = plan
+        .steps
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        [
+            "! retype \"Notes\" rich_text -> number",
+            "+ add \"Link\" (url)",
+            "- remove \"Done\"",
+            "- remove \"Due\"",
+        ]
+    );
+    assert!(plan.is_destructive());
+    assert!(!plan.steps[1].is_destructive());
+}
+
+#[test]
+fn adds_and_recolors_options_keeping_the_rest() {
+    let desired = DesiredSchema::from_json(
+        r#"{ "properties": [
+            { "name": "Name", "title": {} },
+            { "name": "Notes", "rich_text": {} },
+            { "name": "Done", "checkbox": {} },
+            { "name": "Due", "date": {} },
+            { "name": "Status", "select": { "options": [
+                { "name": "Todo", "color": "red" },
+                { "name": "Done" }
+            ] } }
+        ] }"#,
+    )
+    .unwrap();
+
+    let plan = desired.diff(&tasks());
+    match plan.steps.as_slice() {
+        [MigrationStep::UpdateOptions {
+            name,
+            added,
+            recolored,
+            property: DatabasePropertyInput::Select(SelectInput { options }),
+        }] => {
+            assert_eq!(name, "Status");
+            assert_eq!(added.len(), 1);
+            assert_eq!(recolored[0].from, DatabaseColor::Gray);
+            assert_eq!(recolored[0].to, DatabaseColor::Red);
+            // Doing isn't listed but Notion would drop it if it wasn't sent
+            let sent = options
+                .iter()
+                .map(|o| (o.name.as_str(), o.color.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                sent,
+                [
+                    ("Todo", Some(DatabaseColor::Red)),
+                    ("Doing", Some(DatabaseColor::Blue)),
+                    ("Done", None),
+                ]
+            );
+        }
+        other => panic!("Expected an options update, got {:?}", other),
+    }
+    assert!(!plan.is_destructive());
+    assert_eq!(
+        plan.steps[0].to_string(),
+        "~ options of \"Status\":\n    + option \"Done\"\n    ~ option \"Todo\" Gray -> Red"
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn applies_a_plan_until_theres_nothing_left_to_do() {
+    use inkling::{migration::MigrationError, mock::MockServer, Client};
+
+    let server = MockServer::start().await.unwrap();
+    server.seed_database(&tasks());
+    let client = server.client();
+    let desired = DesiredSchema::new()
+        .property("Task", DatabasePropertyInput::Title(Empty {}))
+        .renamed_property(
+            "Details",
+            vec!["Notes".to_string()],
+            DatabasePropertyInput::RichText(Empty {}),
+        )
+        .property("Done", DatabasePropertyInput::Checkbox(Empty {}))
+        .property(
+            "Status",
+            DatabasePropertyInput::Select(options(&[("Blocked", Some(DatabaseColor::Red))])),
+        )
+        .property("Link", DatabasePropertyInput::Url(Empty {}));
+
+    async fn live(client: &Client) -> DatabaseObject {
+        let database = client.get_database("d1").await.unwrap();
+        database.into_database().unwrap()
+    }
+    let plan = desired.diff(&live(&client).await);
+    assert!(plan.is_destructive());
+
+    // Removing Due needs confirmation, nothing is sent without it
+    server.clear_requests();
+    let error = plan.apply(&client, false).await.unwrap_err();
+    let error = error.downcast_ref::<MigrationError>().unwrap();
+    assert_eq!(error.steps, ["- remove \"Due\""]);
+    assert!(server.requests().is_empty());
+
+    assert!(plan.apply(&client, true).await.unwrap().is_some());
+    let plan = desired.diff(&live(&client).await);
+    assert!(plan.is_empty(), "{}", plan);
+    assert!(plan.apply(&client, false).await.unwrap().is_none());
+
+    let database = server.database("d1").unwrap();
+    let mut names = database.properties.keys().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["Details", "Done", "Link", "Status", "Task"]);
+}
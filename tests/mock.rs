@@ -0,0 +1,281 @@
+#![cfg(feature = "mock")]
+mod common;
+
+use common::{database, paragraph, row};
+use inkling::{
+    database::{Direction, NumberFilter, QueryDatabaseFilter, QueryDatabaseSort, TextFilter},
+    error::ErrorCode,
+    mock::{MockFailure, MockServer},
+    page::{PageObject, PageProperty},
+    NotionObject,
+};
+use serde_json::json;
+
+const DATABASE: &str = "d0000000-0000-0000-0000-000000000000";
+
+/// A database of fruit with how many of each there are
+async fn server() -> MockServer {
+    let server = MockServer::start().await.unwrap();
+    let properties =
+        json!({ "Count": { "id": "cnt", "type": "number", "number": { "format": "number" } } });
+    server.seed_database(&database(DATABASE, "root", "Fruit", properties));
+    for (id, name, count) in [("r1", "Apple", 3), ("r2", "Banana", 1), ("r3", "Cherry", 7)] {
+        let count = json!({ "Count": { "id": "cnt", "type": "number", "number": count } });
+        server.seed_page(&row(id, DATABASE, name, count));
+    }
+    server.clear_requests();
+    server
+}
+
+fn names(rows: Vec<NotionObject>) -> Vec<String> {
+    rows.into_iter()
+        .map(|row| match row {
+            NotionObject::Page(page) => title(&page),
+            other => panic!("Expected a page, got {}", other.kind()),
+        })
+        .collect()
+}
+
+fn title(page: &PageObject) -> String {
+    match &page.properties["Name"] {
+        PageProperty::Title { title, .. } => title.iter().map(|span| span.plain_text()).collect(),
+        _ => panic!("Name isn't a title"),
+    }
+}
+
+fn contains(text: &str) -> TextFilter {
+    TextFilter {
+        equals: None,
+        does_not_equal: None,
+        contains: Some(text.to_string()),
+        does_not_contain: None,
+        starts_with: None,
+        ends_with: None,
+        is_empty: None,
+        is_not_empty: None,
+    }
+}
+
+fn greater_than(number: f64) -> NumberFilter {
+    NumberFilter {
+        equals: None,
+        does_not_equal: None,
+        greater_than: Some(number.to_string()),
+        less_than: None,
+        greater_than_or_equal_to: None,
+        less_than_or_equal_to: None,
+        is_empty: None,
+        is_not_empty: None,
+    }
+}
+
+fn by_count(direction: Direction) -> QueryDatabaseSort {
+    QueryDatabaseSort {
+        property: Some("Count".to_string()),
+        timestamp: None,
+        direction: Some(direction),
+    }
+}
+
+#[tokio::test]
+async fn filters_rows() {
+    let server = server().await;
+    let client = server.client();
+
+    let filter = QueryDatabaseFilter::Title {
+        property: "Name".to_string(),
+        title: contains("an"),
+    };
+    let rows = client
+        .query_database_all(DATABASE, Some(filter), None)
+        .await
+        .unwrap();
+    assert_eq!(names(rows), ["Banana"]);
+
+    let filter = QueryDatabaseFilter::Or {
+        or: vec![
+            QueryDatabaseFilter::Number {
+                property: "Count".to_string(),
+                number: greater_than(5.0),
+            },
+            QueryDatabaseFilter::Title {
+                property: "Name".to_string(),
+                title: contains("apple"),
+            },
+        ],
+    };
+    let rows = client
+        .query_database_all(DATABASE, Some(filter), None)
+        .await
+        .unwrap();
+    assert_eq!(names(rows), ["Apple", "Cherry"]);
+
+    let filter = QueryDatabaseFilter::Title {
+        property: "Colour".to_string(),
+        title: contains("red"),
+    };
+    let error = client
+        .query_database(DATABASE, Some(filter), None, None, None)
+        .await
+        .unwrap()
+        .into_result()
+        .unwrap_err();
+    assert!(error.to_string().contains("Colour"), "{}", error);
+}
+
+#[tokio::test]
+async fn rejects_filters_for_another_type_of_property() {
+    let server = server().await;
+    let client = server.client();
+
+    for filter in [
+        QueryDatabaseFilter::RichText {
+            property: "Name".to_string(),
+            rich_text: contains("an"),
+        },
+        QueryDatabaseFilter::Phone {
+            property: "Count".to_string(),
+            phone_number: contains("5"),
+        },
+    ] {
+        match client
+            .query_database(DATABASE, Some(filter), None, None, None)
+            .await
+            .unwrap()
+        {
+            NotionObject::Error(error) => {
+                assert!(matches!(error.code, ErrorCode::ValidationError))
+            }
+            other => panic!("Expected an error, got {}", other.kind()),
+        }
+    }
+    let request = server.requests().pop().unwrap();
+    assert_eq!(
+        request.body.unwrap()["filter"]["phone_number"]["contains"],
+        "5"
+    );
+}
+
+#[tokio::test]
+async fn sorts_rows() {
+    let server = server().await;
+    let client = server.client();
+    for (direction, expected) in [
+        (Direction::Ascending, ["Banana", "Apple", "Cherry"]),
+        (Direction::Descending, ["Cherry", "Apple", "Banana"]),
+    ] {
+        let rows = client
+            .query_database_all(DATABASE, None, Some(vec![by_count(direction)]))
+            .await
+            .unwrap();
+        assert_eq!(names(rows), expected);
+    }
+}
+
+#[tokio::test]
+async fn pages_through_rows_with_cursors() {
+    let server = server().await;
+    let client = server.client();
+    let sorts = Some(vec![by_count(Direction::Ascending)]);
+
+    let first = client
+        .query_database(DATABASE, None, sorts.clone(), None, Some(2))
+        .await
+        .unwrap()
+        .into_list()
+        .unwrap();
+    assert!(first.has_more);
+    assert_eq!(names(first.results), ["Banana", "Apple"]);
+    let second = client
+        .query_database(DATABASE, None, sorts, first.next_cursor, Some(2))
+        .await
+        .unwrap()
+        .into_list()
+        .unwrap();
+    assert!(!second.has_more);
+    assert_eq!(second.next_cursor, None);
+    assert_eq!(names(second.results), ["Cherry"]);
+
+    let cursors: Vec<_> = server
+        .requests()
+        .into_iter()
+        .map(|request| request.body.unwrap()["start_cursor"].clone())
+        .collect();
+    assert_eq!(cursors, [json!(null), json!("r3")]);
+}
+
+#[tokio::test]
+async fn fails_the_next_request() {
+    let server = server().await;
+    let client = server.client();
+    let failures = [
+        (
+            MockFailure::RateLimited { retry_after: 2 },
+            429,
+            "rate_limited",
+        ),
+        (MockFailure::ServerError, 500, "internal_server_error"),
+        (
+            MockFailure::Validation("Name is too long".to_string()),
+            400,
+            "validation_error",
+        ),
+    ];
+    for (failure, status, code) in failures {
+        server.fail_next(failure);
+        match client.get_page("r1").await.unwrap() {
+            NotionObject::Error(error) => {
+                assert_eq!(error.status, status);
+                assert_eq!(serde_json::to_value(&error.code).unwrap(), code);
+                if status == 400 {
+                    assert!(matches!(error.code, ErrorCode::ValidationError));
+                    assert_eq!(error.message, "Name is too long");
+                }
+            }
+            other => panic!("Expected an error, got {}", other.kind()),
+        }
+        // Only the one request fails
+        let page = client.get_page("r1").await.unwrap().into_page().unwrap();
+        assert_eq!(title(&page), "Apple");
+    }
+}
+
+#[tokio::test]
+async fn records_requests() {
+    let server = server().await;
+    let client = server.client();
+    client.get_page("r2").await.unwrap().into_page().unwrap();
+    client
+        .query_database(DATABASE, None, None, None, Some(10))
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "pages/r2");
+    assert_eq!(requests[0].body, None);
+    assert!(requests[0].headers["authorization"].starts_with("Bearer "));
+    assert!(requests[0].headers.contains_key("notion-version"));
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].path, format!("databases/{}/query", DATABASE));
+    assert_eq!(requests[1].body.as_ref().unwrap()["page_size"], 10);
+
+    server.clear_requests();
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_too_many_children() {
+    let server = server().await;
+    let client = server.client();
+    let block = paragraph("", "Hi").to_input().unwrap();
+    let children = vec![block; 150];
+    let error = client
+        .append_block_children("r1", children)
+        .await
+        .unwrap()
+        .into_result()
+        .unwrap_err();
+    assert!(error.to_string().contains("instead was `150`"), "{}", error);
+}
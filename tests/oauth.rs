@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use inkling::{
+    oauth::OAuthClient,
+    transport::{Request, Response, Transport},
+};
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+/// Keeps the requests it's sent and answers them all with a 500
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Request>>>);
+
+#[async_trait]
+impl Transport for Capture {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        self.0.lock().unwrap().push(request);
+        Ok(Response {
+            status: 500,
+            headers: Vec::new(),
+            body: b"{}".to_vec(),
+        })
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &str) -> &'a str {
+    request
+        .headers
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+        .unwrap()
+}
+
+#[test]
+fn percent_encodes_the_authorization_url() {
+    let client = OAuthClient::with_transport("id 1", "secret", Capture::default())
+        .with_base_url("https://example.com/v1/")
+        .with_redirect_uri("https://app.example.com/callback?from=notion");
+    assert_eq!(
+        client.authorization_url("a&b=c/ü~"),
+        "https://example.com/v1/oauth/authorize?client_id=id%201&response_type=code&owner=user\
+         &redirect_uri=https%3A%2F%2Fapp.example.com%2Fcallback%3Ffrom%3Dnotion\
+         &state=a%26b%3Dc%2F%C3%BC~"
+    );
+}
+
+#[tokio::test]
+async fn sends_the_credentials_as_base64() {
+    // Every length of the last group of three bytes, so both kinds of padding are covered
+    for (id, secret, encoded) in [
+        ("Aladdin", "open sesame", "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+        ("ab", "c", "YWI6Yw=="),
+        ("abc", "d", "YWJjOmQ="),
+        ("abc", "de", "YWJjOmRl"),
+        ("", "", "Og=="),
+        ("ü", "\u{1F600}", "w7w68J+YgA=="),
+    ] {
+        let transport = Capture::default();
+        let client = OAuthClient::with_transport(id, secret, transport.clone());
+        assert!(client.exchange_code("code").await.is_err());
+        let requests = transport.0.lock().unwrap();
+        assert_eq!(
+            header(&requests[0], "Authorization"),
+            format!("Basic {}", encoded)
+        );
+    }
+}
+
+#[cfg(feature = "mock")]
+mod mock {
+    use inkling::{
+        mock::MockServer,
+        oauth::{OAuthError, Owner, TokenResponse},
+    };
+    use serde_json::json;
+
+    fn token() -> TokenResponse {
+        serde_json::from_value(json!({
+            "access_token": "secret_granted",
+            "token_type": "bearer",
+            "bot_id": "bot",
+            "workspace_id": "w1",
+            "workspace_name": "Workspace",
+            "workspace_icon": null,
+            "owner": { "type": "workspace", "workspace": true },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exchanges_a_granted_code_once() {
+        let server = MockServer::start().await.unwrap();
+        server.grant("code-1", &token());
+        let oauth = server.oauth_client();
+
+        let token = oauth.exchange_code("code-1").await.unwrap();
+        assert_eq!(token.access_token, "secret_granted");
+        assert_eq!(token.workspace_id, "w1");
+        assert!(matches!(token.owner, Owner::Workspace { workspace: true }));
+
+        let request = &server.requests()[0];
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "oauth/token")
+        );
+        assert_eq!(
+            request.body,
+            Some(json!({ "grant_type": "authorization_code", "code": "code-1" }))
+        );
+        assert!(request.headers["authorization"].starts_with("Basic "));
+
+        let error = oauth.exchange_code("code-1").await.unwrap_err();
+        let error = error.downcast_ref::<OAuthError>().unwrap();
+        assert_eq!((error.status, error.error.as_str()), (400, "invalid_grant"));
+        let error = oauth.exchange_code("never-granted").await.unwrap_err();
+        assert!(error.to_string().contains("invalid_grant"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn makes_clients_that_use_the_oauth_clients_server() {
+        let server = MockServer::start().await.unwrap();
+        server.grant("code-1", &token());
+        let oauth = server.oauth_client();
+        let token = oauth.exchange_code("code-1").await.unwrap();
+        server.clear_requests();
+
+        let client = oauth.client(&token);
+        client
+            .list_users(None, None)
+            .await
+            .unwrap()
+            .into_list()
+            .unwrap();
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "users");
+        assert_eq!(request.headers["authorization"], "Bearer secret_granted");
+    }
+}
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use inkling::{
+    oauth::{MemoryTokenStore, TokenResponse, TokenStore},
+    pool::ClientPool,
+    transport::{Request, Response, Transport},
+};
+use serde_json::json;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Answers with each response in turn and then with an empty list, keeping every request
+#[derive(Clone, Default)]
+struct Replies {
+    replies: Arc<Mutex<Vec<Response>>>,
+    sent: Arc<Mutex<Vec<Request>>>,
+}
+
+#[async_trait]
+impl Transport for Replies {
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        self.sent.lock().unwrap().push(request);
+        let mut replies = self.replies.lock().unwrap();
+        if !replies.is_empty() {
+            return Ok(replies.remove(0));
+        }
+        let list =
+            json!({ "object": "list", "results": [], "next_cursor": null, "has_more": false });
+        Ok(Response {
+            status: 200,
+            headers: Vec::new(),
+            body: serde_json::to_vec(&list)?,
+        })
+    }
+}
+
+fn rate_limited(retry_after: &str) -> Response {
+    let error =
+        json!({ "object": "error", "status": 429, "code": "rate_limited", "message": "Slow down" });
+    Response {
+        status: 429,
+        headers: vec![("Retry-After".to_string(), retry_after.to_string())],
+        body: serde_json::to_vec(&error).unwrap(),
+    }
+}
+
+/// A pool with tokens for workspaces `a` and `b`
+async fn pool(transport: Replies) -> ClientPool {
+    let store = MemoryTokenStore::new();
+    for workspace in ["a", "b"] {
+        let token: TokenResponse = serde_json::from_value(json!({
+            "access_token": format!("secret_{}", workspace),
+            "token_type": "bearer",
+            "bot_id": "bot",
+            "workspace_id": workspace,
+            "workspace_name": null,
+            "workspace_icon": null,
+            "owner": { "type": "workspace", "workspace": true },
+        }))
+        .unwrap();
+        store.put(token).await.unwrap();
+    }
+    ClientPool::with_transport(store, transport)
+}
+
+#[tokio::test]
+async fn bad_retry_after_backs_off_instead() {
+    for retry_after in ["-1", "inf", "NaN", "1e300", "soon"] {
+        let transport = Replies::default();
+        transport
+            .replies
+            .lock()
+            .unwrap()
+            .push(rate_limited(retry_after));
+        let pool = pool(transport).await;
+        let client = pool.client("a").await.unwrap();
+        let users = client.list_users(None, None).await.unwrap();
+        assert_eq!(users.kind(), "list", "Retry-After: {}", retry_after);
+        let metrics = pool.metrics("a").unwrap();
+        assert_eq!((metrics.rate_limited, metrics.retries), (1, 1));
+    }
+}
+
+#[tokio::test]
+#[should_panic(expected = "requests_per_second must be positive")]
+async fn zero_rate_limit_is_rejected() {
+    let _ = pool(Replies::default()).await.with_rate_limit(0.0, 1);
+}
+
+#[tokio::test]
+async fn makes_clients_from_the_token_store_when_first_asked() {
+    let transport = Replies::default();
+    let pool = pool(transport.clone()).await;
+    assert!(pool.is_empty());
+
+    let client = pool.client("a").await.unwrap();
+    assert!(Arc::ptr_eq(&client, &pool.client("a").await.unwrap()));
+    assert_eq!(pool.workspaces(), ["a"]);
+    match pool.client("c").await {
+        Err(error) => assert_eq!(error.to_string(), "No token stored for workspace c"),
+        Ok(_) => panic!("Expected no client without a token"),
+    }
+    assert_eq!(pool.len(), 1);
+
+    // Both workspaces send through the one transport with their own token
+    client.list_users(None, None).await.unwrap();
+    pool.client("b")
+        .await
+        .unwrap()
+        .list_users(None, None)
+        .await
+        .unwrap();
+    let sent = transport.sent.lock().unwrap();
+    let tokens = sent
+        .iter()
+        .flat_map(|request| &request.headers)
+        .filter(|(name, _)| name == "Authorization")
+        .map(|(_, value)| value.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, ["Bearer secret_a", "Bearer secret_b"]);
+}
+
+#[tokio::test]
+async fn workspaces_dont_throttle_each_other() {
+    let pool = pool(Replies::default()).await.with_rate_limit(10.0, 1);
+    let a = pool.client("a").await.unwrap();
+    let b = pool.client("b").await.unwrap();
+    for _ in 0..3 {
+        a.list_users(None, None).await.unwrap();
+    }
+    b.list_users(None, None).await.unwrap();
+
+    assert!(pool.metrics("a").unwrap().throttled >= Duration::from_millis(150));
+    assert_eq!(pool.metrics("b").unwrap().throttled, Duration::ZERO);
+}
+
+#[tokio::test]
+async fn evicts_clients_but_keeps_their_rate_limit() {
+    let pool = pool(Replies::default())
+        .await
+        .with_rate_limit(10.0, 1)
+        .with_max_clients(1);
+    pool.client("a")
+        .await
+        .unwrap()
+        .list_users(None, None)
+        .await
+        .unwrap();
+    pool.client("b").await.unwrap();
+    assert_eq!(pool.workspaces(), ["b"]);
+
+    // A new client for `a` has to wait for the request the old one just sent
+    pool.client("a")
+        .await
+        .unwrap()
+        .list_users(None, None)
+        .await
+        .unwrap();
+    assert!(pool.metrics("a").unwrap().throttled > Duration::ZERO);
+
+    assert!(pool.evict("a"));
+    assert!(!pool.evict("a"));
+    pool.client("b").await.unwrap();
+    assert_eq!(pool.evict_idle(Duration::ZERO), 1);
+    assert!(pool.is_empty());
+}
+
+#[tokio::test]
+async fn counts_requests_per_endpoint() {
+    let transport = Replies::default();
+    transport.replies.lock().unwrap().push(rate_limited("0"));
+    let pool = pool(transport).await;
+    let client = pool.client("a").await.unwrap();
+    client.list_users(None, None).await.unwrap();
+    client.list_users(None, None).await.unwrap();
+
+    let metrics = pool.metrics("a").unwrap();
+    let endpoint = &metrics.endpoints["list_users"];
+    assert_eq!((endpoint.requests, endpoint.errors), (2, 0));
+    assert_eq!((metrics.rate_limited, metrics.retries), (1, 1));
+    assert!(pool.metrics("b").is_none());
+    assert_eq!(pool.all_metrics().len(), 1);
+}
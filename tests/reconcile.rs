@@ -0,0 +1,112 @@
+mod common;
+
+use common::{block_json, paragraph};
+use inkling::{
+    block::{BlockObject, BlockTree},
+    reconcile::{ReconcilePlan, ReconcileStep},
+    template::TemplateBlock,
+};
+use serde_json::json;
+
+fn tree(block: BlockObject) -> BlockTree {
+    BlockTree {
+        block,
+        children: Vec::new(),
+    }
+}
+
+fn unsupported(id: &str) -> BlockTree {
+    tree(serde_json::from_value(block_json(id, "unsupported", json!({}))).unwrap())
+}
+
+fn desired(text: &str) -> TemplateBlock {
+    TemplateBlock {
+        block: paragraph("", text).to_input().unwrap(),
+        children: Vec::new(),
+    }
+}
+
+fn inserted(step: &ReconcileStep) -> Option<(Option<&str>, usize)> {
+    match step {
+        ReconcileStep::Insert { after, blocks, .. } => Some((after.as_deref(), blocks.len())),
+        _ => None,
+    }
+}
+
+#[test]
+fn keeps_blocks_that_cant_be_written_back() {
+    let current = vec![
+        tree(paragraph("a", "one")),
+        unsupported("u"),
+        tree(paragraph("b", "two")),
+    ];
+    let plan = ReconcilePlan::new("page", &current, &[desired("one"), desired("two")]);
+    assert!(plan.is_empty(), "{}", plan);
+    assert_eq!(plan.unchanged, 3);
+
+    // Edits around it still pair up blocks on either side
+    let plan = ReconcilePlan::new("page", &current, &[desired("uno"), desired("dos")]);
+    let updated: Vec<_> = plan
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            ReconcileStep::Update { id, .. } => Some(id.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(updated, ["a", "b"]);
+    assert!(!plan
+        .steps
+        .iter()
+        .any(|step| matches!(step, ReconcileStep::Delete { .. })));
+}
+
+#[test]
+fn inserts_after_a_first_block_that_cant_be_recreated() {
+    let current = vec![unsupported("u"), tree(paragraph("a", "one"))];
+    let plan = ReconcilePlan::new("page", &current, &[desired("zero"), desired("one")]);
+    let steps: Vec<_> = plan.steps.iter().filter_map(inserted).collect();
+    assert_eq!(steps, [(Some("u"), 1)]);
+    assert_eq!(plan.steps.len(), 1, "{}", plan);
+}
+
+#[test]
+fn text_split_differently_is_unchanged() {
+    let mut block = paragraph("a", "");
+    let text = block.text_mut().unwrap();
+    text.clear();
+    text.push(inkling::rich_text::RichTextObject::text("on"));
+    text.push(inkling::rich_text::RichTextObject::text("e"));
+    let plan = ReconcilePlan::new("page", &[tree(block)], &[desired("one")]);
+    assert!(plan.is_empty(), "{}", plan);
+}
+
+#[test]
+fn keeps_updates_inserts_and_deletes_blocks() {
+    let heading = block_json("c", "heading_1", json!({ "text": [] }));
+    let current = vec![
+        tree(paragraph("a", "one")),
+        tree(paragraph("b", "two")),
+        tree(serde_json::from_value(heading).unwrap()),
+    ];
+    let plan = ReconcilePlan::new(
+        "page",
+        &current,
+        &[desired("one"), desired("two!"), desired("three")],
+    );
+    assert_eq!(plan.unchanged, 1, "{}", plan);
+    assert_eq!(plan.steps.len(), 3, "{}", plan);
+    assert!(plan
+        .steps
+        .iter()
+        .any(|step| matches!(step, ReconcileStep::Delete { id, .. } if id == "c")));
+    assert!(plan
+        .steps
+        .iter()
+        .any(|step| matches!(step, ReconcileStep::Update { id, .. } if id == "b")));
+    let steps: Vec<_> = plan.steps.iter().filter_map(inserted).collect();
+    assert_eq!(steps, [(Some("b"), 1)]);
+    assert!(plan
+        .to_string()
+        .contains("~ b paragraph \"two\" -> paragraph \"two!\""));
+}
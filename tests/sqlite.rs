@@ -0,0 +1,111 @@
+#![cfg(all(feature = "sqlite", feature = "mock"))]
+
+mod common;
+
+use inkling::{
+    mock::MockServer,
+    sqlite::{Mirror, SyncOptions},
+    time::Time,
+};
+use serde_json::json;
+use std::{path::PathBuf, time::Duration};
+
+fn mirror_path(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn text(value: &str) -> serde_json::Value {
+    json!({ "id": "tx", "type": "rich_text", "rich_text": [{
+        "type": "text",
+        "text": { "content": value, "link": null },
+        "plain_text": value,
+        "href": null,
+        "annotations": {
+            "bold": false, "italic": false, "strikethrough": false,
+            "underline": false, "code": false, "color": "default",
+        },
+    }] })
+}
+
+#[tokio::test]
+async fn keeps_properties_named_like_the_fixed_columns_apart() {
+    let server = MockServer::start().await.unwrap();
+    server.seed_database(&common::database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({ "ID": { "id": "tx", "type": "rich_text", "rich_text": {} } }),
+    ));
+    server.seed_page(&common::row(
+        "r1",
+        "d1",
+        "One",
+        json!({ "ID": text("TASK-1") }),
+    ));
+
+    let mut mirror = Mirror::open(mirror_path("fixed-columns.db"), "d1").unwrap();
+    mirror
+        .sync(&server.client(), &SyncOptions::default())
+        .await
+        .unwrap();
+
+    let (id, property): (String, String) = mirror
+        .connection()
+        .query_row(r#"SELECT id, "property:ID" FROM rows"#, [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(id, "r1");
+    assert_eq!(property, "TASK-1");
+}
+
+#[tokio::test]
+async fn reconciling_writes_changed_rows_from_the_full_listing() {
+    let server = MockServer::start().await.unwrap();
+    server.seed_database(&common::database(
+        "d1",
+        "root",
+        "Tasks",
+        json!({ "Notes": { "id": "tx", "type": "rich_text", "rich_text": {} } }),
+    ));
+    let mut rows = ["r1", "r2", "r3"]
+        .iter()
+        .map(|id| common::row(id, "d1", id, json!({ "Notes": text("old") })))
+        .collect::<Vec<_>>();
+    for row in &rows {
+        server.seed_page(row);
+    }
+    let client = server.client();
+    let mut mirror = Mirror::open(mirror_path("reconcile.db"), "d1").unwrap();
+    let first = mirror.sync(&client, &SyncOptions::default()).await.unwrap();
+    assert_eq!((first.upserted, first.reconciled), (3, true));
+    let queries = server
+        .requests()
+        .iter()
+        .filter(|request| request.path == "databases/d1/query")
+        .count();
+    assert_eq!(queries, 1);
+
+    rows[0] = common::row("r1", "d1", "r1", json!({ "Notes": text("new") }));
+    rows[0].last_edited_time =
+        serde_json::from_value::<Time>(json!("2021-06-01T09:00:00.000Z")).unwrap();
+    server.seed_page(&rows[0]);
+    rows[1].archived = true;
+    server.seed_page(&rows[1]);
+    let options = SyncOptions {
+        reconcile_every: Some(Duration::ZERO),
+        ..SyncOptions::default()
+    };
+    let report = mirror.sync(&client, &options).await.unwrap();
+
+    assert_eq!((report.upserted, report.deleted), (1, 1));
+    let notes: String = mirror
+        .connection()
+        .query_row(r#"SELECT "Notes" FROM rows WHERE id = 'r1'"#, [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(notes, "new");
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use inkling::{
+    page::PagePropertyValue,
+    rich_text::RichTextObject,
+    template::{Template, TemplateBlock, TemplateError},
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+fn text(content: &str) -> Value {
+    serde_json::to_value(vec![RichTextObject::text(content)]).unwrap()
+}
+
+fn paragraph(content: &str, children: Vec<Value>) -> Value {
+    json!({
+        "object": "block",
+        "type": "paragraph",
+        "paragraph": { "text": text(content) },
+        "children": children,
+    })
+}
+
+/// A task page with its name, status and estimate left to fill in
+fn task() -> Template {
+    let template = json!({
+        "properties": {
+            "title": { "title": text("{{name}}") },
+            "Status": { "select": { "name": "{{ status }}" } },
+            "Estimate": { "rich_text": text("{{estimate}} days") },
+        },
+        "children": [paragraph("Owner: {{owner}}", vec![paragraph("Notes on {{name}}", vec![])])],
+    });
+    Template::from_json(&template.to_string()).unwrap()
+}
+
+fn plain_text(text: &[RichTextObject]) -> String {
+    text.iter().map(|span| span.plain_text()).collect()
+}
+
+fn block_text(block: &TemplateBlock) -> String {
+    plain_text(block.block.clone().text_mut().unwrap())
+}
+
+#[derive(Serialize)]
+struct Task {
+    name: String,
+    status: String,
+    estimate: u32,
+    owner: Option<String>,
+}
+
+#[test]
+fn lists_placeholders() {
+    let names = task().placeholders().unwrap();
+    assert_eq!(
+        names.into_iter().collect::<Vec<_>>(),
+        ["estimate", "name", "owner", "status"]
+    );
+}
+
+#[test]
+fn fills_placeholders_from_a_struct() {
+    let filled = task()
+        .fill(&Task {
+            name: "Write docs".to_string(),
+            status: "Todo".to_string(),
+            estimate: 3,
+            owner: None,
+        })
+        .unwrap();
+
+    match &filled.properties["title"] {
+        PagePropertyValue::Title { title } => assert_eq!(plain_text(title), "Write docs"),
+        other => panic!("Expected a title, got {:?}", other),
+    }
+    match &filled.properties["Status"] {
+        PagePropertyValue::Select {
+            select: Some(select),
+        } => assert_eq!(select.name, "Todo"),
+        other => panic!("Expected a select, got {:?}", other),
+    }
+    // Anything that isn't a string goes in as JSON, and null as nothing
+    match &filled.properties["Estimate"] {
+        PagePropertyValue::RichText { rich_text } => assert_eq!(plain_text(rich_text), "3 days"),
+        other => panic!("Expected rich text, got {:?}", other),
+    }
+    let children = &filled.children;
+    assert_eq!(block_text(&children[0]), "Owner: ");
+    assert_eq!(block_text(&children[0].children[0]), "Notes on Write docs");
+    assert!(filled.placeholders().unwrap().is_empty());
+}
+
+#[test]
+fn missing_values_are_an_error() {
+    let error = task().fill(&json!({ "name": "Write docs" })).unwrap_err();
+    let error = error.downcast_ref::<TemplateError>().unwrap();
+    assert_eq!(error.missing, ["estimate", "owner", "status"]);
+
+    assert!(task().fill(&"not a map").is_err());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn reads_toml() {
+    let template = Template::from_toml(
+        r#"
+        [properties.Status]
+        select = { name = "{{status}}" }
+
+        [properties.Estimate]
+        number = 3.0
+        "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        template.properties["Estimate"],
+        PagePropertyValue::Number { number: Some(n) } if n == 3.0
+    ));
+    assert_eq!(
+        template
+            .placeholders()
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        ["status"]
+    );
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn captures_a_page_without_its_child_pages() {
+    use inkling::{block::BlockTree, mock::MockServer};
+
+    let server = MockServer::start().await.unwrap();
+    server.seed_page(&common::page("p1", "root", "Weekly review"));
+    server.seed_blocks(
+        "p1",
+        &[
+            BlockTree {
+                block: common::paragraph("b1", "Wins"),
+                children: vec![BlockTree {
+                    block: common::paragraph("b2", "Shipped it"),
+                    children: Vec::new(),
+                }],
+            },
+            BlockTree {
+                block: common::child_page("c1", "Archive"),
+                children: Vec::new(),
+            },
+        ],
+    );
+
+    let template = Template::capture(&server.client(), "p1").await.unwrap();
+    match &template.properties["title"] {
+        PagePropertyValue::Title { title } => assert_eq!(plain_text(title), "Weekly review"),
+        other => panic!("Expected a title, got {:?}", other),
+    }
+    assert_eq!(template.children.len(), 1);
+    assert_eq!(block_text(&template.children[0].children[0]), "Shipped it");
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn creates_more_blocks_than_fit_in_one_request() {
+    use inkling::{mock::MockServer, page::Parent};
+
+    let server = MockServer::start().await.unwrap();
+    server.seed_page(&common::page("root", "workspace", "Root"));
+    let nested = |count: usize| {
+        (0..count)
+            .map(|i| paragraph(&i.to_string(), vec![]))
+            .collect()
+    };
+    let children = (0..150)
+        .map(|i| match i {
+            0 => paragraph("first", nested(120)),
+            120 => paragraph("after the first chunk", nested(2)),
+            _ => paragraph(&i.to_string(), vec![]),
+        })
+        .collect::<Vec<_>>();
+    let template =
+        Template::from_json(&json!({ "properties": {}, "children": children }).to_string())
+            .unwrap();
+
+    let page = template
+        .create(
+            &server.client(),
+            Parent::PageId {
+                page_id: "root".to_string(),
+            },
+        )
+        .await
+        .unwrap()
+        .into_page()
+        .unwrap();
+
+    let trees = server.blocks(&page.id);
+    assert_eq!(trees.len(), 150);
+    assert_eq!(trees[0].children.len(), 120);
+    assert_eq!(
+        plain_text(trees[0].children[119].block.text().unwrap()),
+        "119"
+    );
+    assert_eq!(
+        plain_text(trees[120].block.text().unwrap()),
+        "after the first chunk"
+    );
+    assert_eq!(trees[120].children.len(), 2);
+    assert!(trees[1].children.is_empty());
+}